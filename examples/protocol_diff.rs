@@ -0,0 +1,53 @@
+//! Compare the protocol seen in two recorded sessions, e.g. against the old
+//! and a new build of the judge server.
+//!
+//! ```text
+//! cargo run --example protocol_diff -- old.jsonl new.jsonl
+//! ```
+//!
+//! Each recording holds one frame per line, either as a `RecordedFrame` in
+//! JSON (see `AgentClient::recent_frames`) or as the bare frame. Every added
+//! or removed message and field, changed field type, widened number range and
+//! new enum value is printed. Exits with a non-zero code if anything changed.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use thuai_8_agent_rust::agent::schema::{self, Schema};
+
+#[derive(Parser)]
+struct Cli {
+    /// Recording made against the old server.
+    old: PathBuf,
+    /// Recording made against the new server.
+    new: PathBuf,
+}
+
+fn load(path: &PathBuf) -> Result<Schema, String> {
+    let file = File::open(path).map_err(|err| format!("Cannot open {}: {err}", path.display()))?;
+    Schema::from_recording(BufReader::new(file))
+        .map_err(|err| format!("Cannot read {}: {err}", path.display()))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let (old, new) = match (load(&cli.old), load(&cli.new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(err), _) | (_, Err(err)) => {
+            eprintln!("{err}");
+            return ExitCode::from(2);
+        }
+    };
+    let changes = schema::diff(&old, &new);
+    if changes.is_empty() {
+        println!("No protocol changes");
+        return ExitCode::SUCCESS;
+    }
+    for change in &changes {
+        println!("{change}");
+    }
+    ExitCode::FAILURE
+}
@@ -0,0 +1,223 @@
+//! Soak test: run an [`AgentClient`] against a local mock server for a long
+//! time while the server randomly misbehaves, and watch for leaks.
+//!
+//! ```text
+//! cargo run --release --example soak -- --minutes 120
+//! ```
+//!
+//! Every report interval the resident memory, alive tokio tasks, reconnects
+//! and tick progress are logged. The run fails (non-zero exit code) if the
+//! client stops making progress, misses a reset, or memory or the number of
+//! tasks keeps growing.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use futures::{SinkExt, StreamExt};
+use thuai_8_agent_rust::agent::connection::{
+    AgentClient, ClientConfig, ExponentialBackoff, JitteredBackoff, Keepalive, KeepaliveProbe,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{error, info, warn};
+
+#[derive(Parser)]
+struct Cli {
+    /// How long to run.
+    #[arg(long, default_value_t = 1)]
+    minutes: u64,
+    /// Server tick length.
+    #[arg(long, default_value_t = 20)]
+    tick_ms: u64,
+    /// Chance per tick (in 1/10000) that the server resets the connection.
+    #[arg(long, default_value_t = 5)]
+    reset_rate: u64,
+    /// Chance per tick (in 1/10000) that the server stalls for a while.
+    #[arg(long, default_value_t = 20)]
+    stall_rate: u64,
+    /// Chance per tick (in 1/10000) that the server replays an old tick.
+    #[arg(long, default_value_t = 50)]
+    stale_rate: u64,
+    /// Seconds between two reports.
+    #[arg(long, default_value_t = 10)]
+    report_secs: u64,
+}
+
+/// Cheap randomness without an extra dependency.
+struct Dice(RandomState, u64);
+
+impl Dice {
+    fn new() -> Dice {
+        Dice(RandomState::new(), 0)
+    }
+
+    /// True with probability `rate` / 10000.
+    fn roll(&mut self, rate: u64) -> bool {
+        self.1 += 1;
+        self.0.hash_one(self.1) % 10_000 < rate
+    }
+}
+
+fn statistics(tick: u64) -> Message {
+    Message::text(format!(
+        r#"{{"messageType":"GAME_STATISTICS","currentStage":"BATTLE","countDown":0,"ticks":{tick},"scores":[]}}"#
+    ))
+}
+
+/// Serve one connection until the client leaves or an impairment resets it.
+async fn serve(stream: TcpStream, cli: Arc<Cli>, tick: Arc<AtomicU64>, resets: Arc<AtomicU64>) {
+    let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let (mut write, mut read) = ws.split();
+    let reader = tokio::spawn(async move { while let Some(Ok(_)) = read.next().await {} });
+    let mut dice = Dice::new();
+    loop {
+        sleep(Duration::from_millis(cli.tick_ms)).await;
+        let now = tick.fetch_add(1, Ordering::Relaxed) + 1;
+        if dice.roll(cli.reset_rate) {
+            // Dropping the socket without a close frame looks like a crash.
+            resets.fetch_add(1, Ordering::Relaxed);
+            reader.abort();
+            return;
+        }
+        if dice.roll(cli.stall_rate) {
+            sleep(Duration::from_millis(cli.tick_ms * 20)).await;
+        }
+        let sent = if dice.roll(cli.stale_rate) {
+            write.send(statistics(now.saturating_sub(5))).await
+        } else {
+            write.send(statistics(now)).await
+        };
+        if sent.is_err() {
+            reader.abort();
+            return;
+        }
+    }
+}
+
+async fn mock_server(listener: TcpListener, cli: Arc<Cli>, resets: Arc<AtomicU64>) {
+    let tick = Arc::new(AtomicU64::new(0));
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(serve(stream, cli.clone(), tick.clone(), resets.clone()));
+    }
+}
+
+/// Resident set size in KiB, where `/proc` is available.
+fn resident_kib() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4)
+}
+
+fn tick_of(frame: &str) -> Option<u64> {
+    let value: serde_json::Value = serde_json::from_str(frame).ok()?;
+    value.get("ticks")?.as_u64()
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt().init();
+    let cli = Arc::new(Cli::parse());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server = format!("ws://{}", listener.local_addr().unwrap());
+    let resets = Arc::new(AtomicU64::new(0));
+    tokio::spawn(mock_server(listener, cli.clone(), resets.clone()));
+
+    let config = ClientConfig::new()
+        .with_retry_policy(JitteredBackoff::new(ExponentialBackoff::new(
+            10,
+            Duration::from_millis(50),
+            Duration::from_secs(2),
+        )))
+        .with_keepalive(Keepalive::new(
+            Duration::from_secs(1),
+            KeepaliveProbe::GameStatistics,
+        ));
+    let mut client = AgentClient::with_config(server, "soak".to_string(), config).await;
+
+    let end = Instant::now() + Duration::from_secs(cli.minutes * 60);
+    let report = Duration::from_secs(cli.report_secs);
+    let mut next_report = Instant::now() + report;
+    let mut baseline_kib = None;
+    let mut baseline_tasks = None;
+    let mut last_tick = 0;
+    let mut received = 0u64;
+    let mut failed = false;
+    while Instant::now() < end {
+        match timeout(report, client.recv()).await {
+            Ok(Some(frame)) => {
+                received += 1;
+                if let Some(tick) = tick_of(&frame) {
+                    if tick < last_tick {
+                        error!("Tick went backwards: {tick} after {last_tick}");
+                        failed = true;
+                    }
+                    last_tick = last_tick.max(tick);
+                }
+            }
+            Ok(None) => {
+                error!("Every connection closed for good");
+                failed = true;
+                break;
+            }
+            Err(_) => {
+                error!("No message for {:?}", report);
+                failed = true;
+            }
+        }
+        if Instant::now() >= next_report {
+            next_report += report;
+            let stats = client.stats();
+            let kib = resident_kib();
+            let tasks = tokio::runtime::Handle::current()
+                .metrics()
+                .num_alive_tasks();
+            baseline_kib = baseline_kib.or(kib);
+            baseline_tasks = baseline_tasks.or(Some(tasks));
+            info!(
+                "tick {last_tick}, received {received}, resets {}, reconnects {}, \
+                stale {}, alive tasks {}, resident {:?} KiB",
+                resets.load(Ordering::Relaxed),
+                stats.reconnects(),
+                stats.stale_messages(),
+                tasks,
+                kib,
+            );
+        }
+    }
+
+    let resets = resets.load(Ordering::Relaxed);
+    let reconnects = u64::from(*client.stats().reconnects());
+    if reconnects < resets {
+        error!("Server reset {resets} connections, but only {reconnects} reconnects");
+        failed = true;
+    }
+    if let (Some(baseline), Some(now)) = (baseline_kib, resident_kib())
+        && now > baseline * 2
+    {
+        warn!("Resident memory grew from {baseline} KiB to {now} KiB");
+        failed = true;
+    }
+    let tasks = tokio::runtime::Handle::current()
+        .metrics()
+        .num_alive_tasks();
+    if let Some(baseline) = baseline_tasks
+        && tasks > baseline + 4
+    {
+        warn!("Alive tasks grew from {baseline} to {tasks}");
+        failed = true;
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        info!("Soak test passed");
+        ExitCode::SUCCESS
+    }
+}
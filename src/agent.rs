@@ -1,46 +1,1072 @@
+pub mod ballistics;
+pub mod collision;
 pub mod connection;
+pub mod control;
+pub mod damage;
+pub mod danger;
+pub mod diff;
+pub mod events;
+pub mod freshness;
+pub mod history;
+pub mod legality;
+pub mod logging;
+pub mod map;
+pub mod messages;
 pub mod model;
+pub mod navigation;
+pub mod pathfinding;
 pub mod player_api;
+pub mod poller;
+pub mod prediction;
+pub mod query;
+pub mod report;
+pub mod schema;
+pub mod shooting;
+pub mod skills;
+pub mod spectator;
+pub mod symmetry;
+pub mod visibility;
 
-use connection::{AgentClient, ConnectionAPI, PerformMessage};
+use crate::logic::Logic;
+use connection::{
+    AgentClient, ClientConfig, ConnectError, ConnectionAPI, ConnectionState, ConnectionStats,
+    InvalidPerform, MessageTooLarge, PerformMessage, QueryAPI,
+};
+use control::AgentControl;
+use events::{EVENT_CAPACITY, GameEvent, StageChange, Subscribers};
+use freshness::{CachedState, Freshness, Updated};
+use futures::{Stream, stream};
+use history::{Snapshot, StateHistory};
+use legality::IllegalAction;
+use map::GameMap;
+use messages::ServerMessage;
 use model::{
-    AvailableBuffs, BuffKind, EnvironmentInfo, GameStatistics, MoveDirection, Players, RequestType,
-    SkillKind, TurnDirection,
+    Angle, AvailableBuffs, BuffKind, EnvironmentInfo, GameStatistics, MoveDirection, PlayerToken,
+    Players, Position, RequestType, SkillKind, Stage, TurnDirection,
 };
+use navigation::{NavigationStep, Navigator};
 use player_api::PlayerOperate;
+use poller::{PollSchedule, Poller};
+use prediction::OpponentPredictor;
+use skills::{SkillManager, SkillNotReady};
 use std::error::Error;
-use tracing::{debug, error};
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, error, info, warn};
+
+/// Callback invoked with an action that was never delivered and the reason,
+/// e.g. to retry it or adjust the strategy.
+pub type SendFailureHook = Arc<dyn Fn(&PerformMessage, &dyn Error) + Send + Sync>;
+
+/// How long [`Agent`]'s `fetch_*` methods wait for a reply by default.
+pub const DEFAULT_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How far off [`Agent::aim_at`] still counts as aimed by default.
+pub const DEFAULT_AIM_TOLERANCE_DEGREES: f64 = 2.0;
+
+/// Why a `fetch_*` method of [`Agent`] got no reply.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchError {
+    /// No reply of the message type arrived in time.
+    TimedOut(&'static str),
+    /// Every connection closed before the reply.
+    Closed,
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::TimedOut(message_type) => write!(f, "no {message_type} reply in time"),
+            FetchError::Closed => write!(f, "connection closed before the reply"),
+        }
+    }
+}
+
+impl Error for FetchError {}
+
+/// A part of the state an [`Agent`] method needs was not received yet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NotReceived(pub CachedState);
+
+impl Display for NotReceived {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} not received yet", self.0)
+    }
+}
+
+impl Error for NotReceived {}
+
+/// A selected buff the server did not grant, see
+/// [`Agent::handle_buff_selection`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BuffNotGranted(pub BuffKind);
+
+impl Display for BuffNotGranted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "buff {} was not granted", self.0)
+    }
+}
+
+impl Error for BuffNotGranted {}
 
+/// Why an action of [`PlayerOperate`] was not performed.
+#[derive(Debug)]
+pub enum AgentError {
+    /// The rules forbid it in the current stage.
+    Illegal(IllegalAction),
+    /// The skill is still cooling down.
+    SkillNotReady(SkillNotReady),
+    /// It could not be delivered to the server, because of the given error.
+    Delivery(Box<dyn Error + Send + Sync>),
+}
+
+impl Display for AgentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AgentError::Illegal(err) => write!(f, "{}", err),
+            AgentError::SkillNotReady(err) => write!(f, "{}", err),
+            AgentError::Delivery(err) => write!(f, "delivery failed: {}", err),
+        }
+    }
+}
+
+impl Error for AgentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AgentError::Illegal(err) => Some(err),
+            AgentError::SkillNotReady(err) => Some(err),
+            AgentError::Delivery(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+impl From<Box<dyn Error>> for AgentError {
+    fn from(err: Box<dyn Error>) -> Self {
+        let err = match err.downcast::<IllegalAction>() {
+            Ok(illegal) => return AgentError::Illegal(*illegal),
+            Err(err) => err,
+        };
+        match err.downcast::<SkillNotReady>() {
+            Ok(not_ready) => AgentError::SkillNotReady(*not_ready),
+            Err(err) => AgentError::Delivery(delivery_error(err)),
+        }
+    }
+}
+
+/// Keep the errors [`AgentClient::send`] may return as they are, so they stay
+/// available through [`Error::source`]; anything else keeps its message.
+fn delivery_error(err: Box<dyn Error>) -> Box<dyn Error + Send + Sync> {
+    fn take<T: Error + Send + Sync + 'static>(
+        err: Box<dyn Error>,
+    ) -> Result<Box<dyn Error + Send + Sync>, Box<dyn Error>> {
+        err.downcast::<T>()
+            .map(|err| err as Box<dyn Error + Send + Sync>)
+    }
+    take::<InvalidPerform>(err)
+        .or_else(take::<MessageTooLarge>)
+        .or_else(take::<serde_json::Error>)
+        .or_else(take::<tokio_tungstenite::tungstenite::Error>)
+        .unwrap_or_else(|err| err.to_string().into())
+}
+
+/// A player connected to the server, caching the game state it received.
+///
+/// Should be created with [`Agent::new`] or [`Agent::with_config`].
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> Result<(), thuai_8_agent_rust::agent::AgentError> {
+/// use thuai_8_agent_rust::agent::Agent;
+/// use thuai_8_agent_rust::agent::player_api::PlayerOperate;
+///
+/// let mut agent = Agent::new("ws://127.0.0.1:14514".to_string(), "1919810").await;
+/// agent.move_forward(1.0).await?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct Agent {
-    // TODO: fields in Agent
     client: AgentClient,
-    token: String,
+    token: PlayerToken,
     players_info: Option<Players>,
     game_statistics: Option<GameStatistics>,
     environment_info: Option<EnvironmentInfo>,
     available_buffs: Option<AvailableBuffs>,
+    send_failure_hooks: Vec<SendFailureHook>,
+    subscribers: Subscribers,
+    events: broadcast::Sender<GameEvent>,
+    fetch_timeout: Duration,
+    freshness: Freshness,
+    navigator: Option<Navigator>,
+    history: StateHistory,
+    aim_tolerance: Angle,
+    last_attack_tick: Option<u64>,
+    skills: SkillManager,
+    selected_buff: Option<BuffKind>,
+    control: AgentControl,
+    poller: Poller,
 }
 
-impl ConnectionAPI for Agent {
+impl Agent {
+    /// Connect to `server` as the player with `token`, and request the
+    /// whole game state, see [`Agent::request_state`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if connecting to server always fail, see [`AgentClient::new`].
+    pub async fn new(server: String, token: impl Into<PlayerToken>) -> Agent {
+        Self::with_config(server, token, ClientConfig::default()).await
+    }
+
+    /// Connect like [`Agent::new`], applying `config` to the handshake.
+    ///
+    /// A failure to request the game state is only logged, as the state is
+    /// requested again as the game goes on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if connecting to server always fail, see [`AgentClient::with_config`].
+    pub async fn with_config(
+        server: String,
+        token: impl Into<PlayerToken>,
+        config: ClientConfig,
+    ) -> Agent {
+        match Self::try_with_config(server, token, config).await {
+            Ok(agent) => agent,
+            Err(err) => {
+                error!("{}", err);
+                panic!("Connection Error!");
+            }
+        }
+    }
+
+    /// Connect like [`Agent::with_config`], but return a [`ConnectError`]
+    /// where it panics.
+    pub async fn try_with_config(
+        server: String,
+        token: impl Into<PlayerToken>,
+        config: ClientConfig,
+    ) -> Result<Agent, ConnectError> {
+        let token = token.into();
+        let client = AgentClient::try_with_config(server, token.clone(), config).await?;
+        let mut agent = Agent {
+            client,
+            token,
+            players_info: None,
+            game_statistics: None,
+            environment_info: None,
+            available_buffs: None,
+            send_failure_hooks: Vec::new(),
+            subscribers: Subscribers::default(),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            fetch_timeout: DEFAULT_FETCH_TIMEOUT,
+            freshness: Freshness::default(),
+            navigator: None,
+            history: StateHistory::default(),
+            aim_tolerance: Angle::from_degrees(DEFAULT_AIM_TOLERANCE_DEGREES),
+            last_attack_tick: None,
+            skills: SkillManager::default(),
+            selected_buff: None,
+            control: AgentControl::new(),
+            poller: Poller::default(),
+        };
+        match agent.request_state().await {
+            Ok(()) => info!("Agent {} ready", agent.token),
+            Err(err) => warn!("Requesting the initial game state failed: {}", err),
+        }
+        Ok(agent)
+    }
+
+    /// Request the game statistics, the environment, both players and the
+    /// available buffs. The replies arrive as messages from the server.
+    pub async fn request_state(&mut self) -> Result<(), Box<dyn Error>> {
+        self.send_get_game_statistics().await?;
+        self.send_get_environment_info().await?;
+        self.send_get_player_info().await?;
+        self.send_get_available_buffs().await
+    }
+
+    /// Request the parts of the game state due by the poll schedule at the
+    /// current tick, see [`Agent::set_poll_schedule`], and return them.
+    ///
+    /// [`run_agent`](crate::run_agent) calls this every poll interval.
+    pub async fn poll(&mut self) -> Result<Vec<CachedState>, Box<dyn Error>> {
+        let stage = self
+            .game_statistics
+            .as_ref()
+            .map(|statistics| *statistics.current_stage());
+        let due = self.poller.due(self.client.current_tick(), stage);
+        for state in &due {
+            match state {
+                CachedState::Players => self.send_get_player_info().await?,
+                CachedState::Environment => self.send_get_environment_info().await?,
+                CachedState::Statistics => self.send_get_game_statistics().await?,
+                CachedState::Buffs => self.send_get_available_buffs().await?,
+            }
+        }
+        Ok(due)
+    }
+
+    /// Request each part of the game state as often as `schedule` sets
+    /// in [`Agent::poll`].
+    pub fn set_poll_schedule(&mut self, schedule: PollSchedule) {
+        self.poller = Poller::new(schedule);
+    }
+
+    /// Wait at most `timeout` for each reply of the `fetch_*` methods.
+    pub fn set_fetch_timeout(&mut self, timeout: Duration) {
+        self.fetch_timeout = timeout;
+    }
+
+    /// Be paused, resumed and stopped through `control` from now on, e.g.
+    /// a handle kept by a GUI.
+    pub fn set_control(&mut self, control: AgentControl) {
+        self.control = control;
+    }
+
+    /// A handle to pause, resume and stop this agent from elsewhere.
+    pub fn control(&self) -> AgentControl {
+        self.control.clone()
+    }
+
+    /// Suspend the strategy and the polling of
+    /// [`run_agent`](crate::run_agent), keeping the connection alive.
+    pub fn pause(&self) {
+        self.control.pause();
+    }
+
+    /// Continue after [`Agent::pause`].
+    pub fn resume(&self) {
+        self.control.resume();
+    }
+
+    /// End the run of [`run_agent`](crate::run_agent), which then closes
+    /// the connection.
+    pub fn stop(&self) {
+        self.control.stop();
+    }
+
+    /// Count [`Agent::aim_at`] as aimed when at most `tolerance` off.
+    pub fn set_aim_tolerance(&mut self, tolerance: Angle) {
+        self.aim_tolerance = tolerance;
+    }
+
+    /// Request both players and resolve with every cached player once both
+    /// replies arrived.
+    ///
+    /// Like every `fetch_*` method, messages received meanwhile update the
+    /// cached state as usual, and an `ERROR` reply fails the fetch with the
+    /// [`ServerError`](messages::ServerError). Otherwise a [`FetchError`]
+    /// tells why no reply came.
+    ///
+    /// An `ERROR` does not tell which request it answers, so any `ERROR`
+    /// received while waiting fails the fetch, also one rejecting a command
+    /// sent before it, e.g. by another task sharing the connection. Errors
+    /// already received when the fetch starts are applied and do not count.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use thuai_8_agent_rust::agent::Agent;
+    ///
+    /// let mut agent = Agent::new("ws://127.0.0.1:14514".to_string(), "1919810").await;
+    /// let statistics = agent.fetch_game_statistics().await?;
+    /// let players = agent.fetch_player_info().await?;
+    /// println!("{:?}: {} players", statistics.current_stage(), players.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_player_info(&mut self) -> Result<Players, Box<dyn Error>> {
+        self.sync();
+        self.send_get_player_info().await?;
+        // One reply for self, one for the opponent.
+        self.await_replies("PLAYERS_INFO", 2).await?;
+        Ok(self.players_info.clone().unwrap_or_default())
+    }
+
+    /// Request the environment and resolve with the reply, see
+    /// [`Agent::fetch_player_info`].
+    pub async fn fetch_environment_info(&mut self) -> Result<EnvironmentInfo, Box<dyn Error>> {
+        self.sync();
+        self.send_get_environment_info().await?;
+        self.await_replies("ENVIRONMENT_INFO", 1).await?;
+        Ok(self.environment_info.clone().expect("reply applied"))
+    }
+
+    /// Request the game statistics and resolve with the reply, see
+    /// [`Agent::fetch_player_info`].
+    pub async fn fetch_game_statistics(&mut self) -> Result<GameStatistics, Box<dyn Error>> {
+        self.sync();
+        self.send_get_game_statistics().await?;
+        self.await_replies("GAME_STATISTICS", 1).await?;
+        Ok(self.game_statistics.clone().expect("reply applied"))
+    }
+
+    /// Request the buffs to choose from and resolve with the reply, see
+    /// [`Agent::fetch_player_info`].
+    pub async fn fetch_available_buffs(&mut self) -> Result<AvailableBuffs, Box<dyn Error>> {
+        self.sync();
+        self.send_get_available_buffs().await?;
+        self.await_replies("AVAILABLE_BUFFS", 1).await?;
+        Ok(self.available_buffs.clone().expect("reply applied"))
+    }
+
+    /// Select a buff once per `REST` stage, picked by [`Logic::select_buff`].
+    ///
+    /// Outside the `REST` stage, or once a buff was selected in it, nothing
+    /// happens. Otherwise the available buffs are fetched and the pick is
+    /// sent, then confirmed by the players requested after it: an `ERROR`
+    /// before their reply rejects the pick, and a buff granting a skill must
+    /// show up among the skills of this agent's player, else
+    /// [`BuffNotGranted`]. Resolves with the buff selected by this call, if
+    /// any; a failed selection is tried again on the next call.
+    ///
+    /// [`run_agent`](crate::run_agent) calls this every poll interval.
+    pub async fn handle_buff_selection(&mut self) -> Result<Option<BuffKind>, Box<dyn Error>> {
+        self.sync();
+        let resting = self
+            .game_statistics
+            .as_ref()
+            .is_some_and(|statistics| *statistics.current_stage() == Stage::Rest);
+        if !resting || self.selected_buff.is_some() {
+            return Ok(None);
+        }
+        let buffs = self.fetch_available_buffs().await?;
+        let Some(buff) = <Agent as Logic>::select_buff(self, &buffs).await else {
+            return Ok(None);
+        };
+        self.send_perform_select(buff).await?;
+        self.fetch_player_info().await?;
+        let granted = buff.skill().is_none_or(|skill| {
+            self.self_player()
+                .is_some_and(|player| player.skill(skill).is_some())
+        });
+        if !granted {
+            return Err(BuffNotGranted(buff).into());
+        }
+        info!("Agent {} selected buff {}", self.token, buff);
+        self.selected_buff = Some(buff);
+        Ok(Some(buff))
+    }
+
+    /// The buff selected in the current `REST` stage, see
+    /// [`Agent::handle_buff_selection`].
+    pub fn selected_buff(&self) -> Option<BuffKind> {
+        self.selected_buff
+    }
+
+    /// Wait for `replies` messages of `message_type`, applying every message
+    /// received meanwhile. Any `ERROR` fails the wait, as it cannot be told
+    /// apart from one answering the request.
+    async fn await_replies(
+        &mut self,
+        message_type: &'static str,
+        mut replies: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        let timeout = self.fetch_timeout;
+        let wait = async {
+            while replies > 0 {
+                match self.next_message().await {
+                    None => return Err(FetchError::Closed.into()),
+                    Some(ServerMessage::Error(err)) => return Err(err.into()),
+                    Some(message) if message.message_type() == message_type => replies -= 1,
+                    Some(_) => {}
+                }
+            }
+            Ok(())
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| FetchError::TimedOut(message_type))?
+    }
+
+    /// Wait for the next message from the server that parses, and update
+    /// the cached game state with it, see [`Agent::apply`].
+    ///
+    /// Returns [`None`] once every connection has been closed.
+    pub async fn next_message(&mut self) -> Option<ServerMessage> {
+        loop {
+            let frame = self.client.recv().await?;
+            if let Some(message) = self.parse_and_apply(&frame) {
+                return Some(message);
+            }
+        }
+    }
+
+    /// Update the cached game state with every message already received,
+    /// without waiting for more. Returns how many were applied.
+    ///
+    /// Actions call this before they are checked, so they see the latest
+    /// stage; [`run_agent`](crate::run_agent) calls it before each run of
+    /// the strategy.
+    pub fn sync(&mut self) -> usize {
+        let mut applied = 0;
+        while let Some(frame) = self.client.try_recv() {
+            if self.parse_and_apply(&frame).is_some() {
+                applied += 1;
+            }
+        }
+        applied
+    }
+
+    /// Update the cached game state with `message`, and start the callbacks
+    /// registered for what changed. Players are merged by token, see
+    /// [`Agent::update_players_info`]; everything else replaces the cached
+    /// value.
+    ///
+    /// The callbacks are spawned on the current tokio runtime; outside of
+    /// one they are skipped with a warning.
+    pub fn apply(&mut self, message: &ServerMessage) {
+        // Only diff the state when someone listens to the events.
+        let streamed = self.events.receiver_count() > 0;
+        let updated = match message {
+            ServerMessage::PlayersInfo(_) => Some(CachedState::Players),
+            ServerMessage::EnvironmentInfo(_) => Some(CachedState::Environment),
+            ServerMessage::GameStatistics(_) => Some(CachedState::Statistics),
+            ServerMessage::AvailableBuffs(_) => Some(CachedState::Buffs),
+            ServerMessage::Error(_) => None,
+        };
+        if let Some(state) = updated {
+            self.freshness.touch(state, self.client.current_tick());
+        }
+        let callbacks = match message {
+            ServerMessage::PlayersInfo(info) => {
+                let previous = self.players_info.clone().filter(|_| streamed);
+                self.update_players_info(info.players().clone());
+                let players = self.players_info.as_ref().expect("just updated");
+                let tick = self.client.current_tick();
+                // Snapshots use the `u32` ticks of the protocol; a tick beyond
+                // that is not recorded rather than wrapped around.
+                if let Some(tick) = tick.and_then(|tick| u32::try_from(tick).ok()) {
+                    let mut snapshot = Snapshot::new(tick, players.clone());
+                    if let Some(statistics) = &self.game_statistics {
+                        snapshot = snapshot.with_statistics(statistics.clone());
+                    }
+                    self.history.record(snapshot);
+                }
+                if let Some(me) = info.players().iter().find(|p| *p.token() == self.token) {
+                    self.skills.update(me.skills(), tick);
+                }
+                if let Some(previous) = previous {
+                    self.emit(events::player_events(&previous, players));
+                }
+                self.subscribers.players_updated(players)
+            }
+            ServerMessage::EnvironmentInfo(info) => {
+                let previous = self.environment_info.replace(info.clone());
+                if streamed && let Some(previous) = previous {
+                    self.emit(events::environment_events(&previous, info));
+                }
+                self.subscribers.environment_updated(info)
+            }
+            ServerMessage::GameStatistics(statistics) => {
+                let from = self
+                    .game_statistics
+                    .replace(statistics.clone())
+                    .map(|previous| *previous.current_stage());
+                let to = *statistics.current_stage();
+                if from == Some(to) {
+                    Vec::new()
+                } else {
+                    self.selected_buff = None;
+                    let change = StageChange::new(from, to);
+                    self.emit([GameEvent::StageChanged(change)]);
+                    self.subscribers.stage_changed(change)
+                }
+            }
+            ServerMessage::AvailableBuffs(buffs) => {
+                self.available_buffs = Some(buffs.buffs().clone());
+                self.emit([GameEvent::BuffsAvailable(buffs.buffs().clone())]);
+                self.subscribers.buffs_available(buffs.buffs())
+            }
+            ServerMessage::Error(err) => {
+                warn!("Server replied with {}", err);
+                Vec::new()
+            }
+        };
+        self.client.record_applied();
+        if callbacks.is_empty() {
+            return;
+        }
+        match Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(events::in_order(callbacks));
+            }
+            Err(_) => warn!(
+                "Skipping {} callbacks outside of an async runtime",
+                callbacks.len()
+            ),
+        }
+    }
+
+    /// The players received at the last ticks, one snapshot per tick.
+    ///
+    /// Only ticks announced by the server are recorded.
+    pub fn history(&self) -> &StateHistory {
+        &self.history
+    }
+
+    /// Ticks until the own `skill` is off cool down, [`None`] if it is not
+    /// owned. Counted down locally from the last player info, see
+    /// [`SkillManager`].
+    pub fn ticks_until_ready(&self, skill: SkillKind) -> Option<u64> {
+        self.skills
+            .ticks_until_ready(skill, self.client.current_tick())
+    }
+
+    /// When `state` was last updated, [`None`] if it never was.
+    pub fn last_updated(&self, state: CachedState) -> Option<Updated> {
+        self.freshness.get(state)
+    }
+
+    /// The tick `state` was last updated at, if it was and the tick was known.
+    pub fn last_updated_tick(&self, state: CachedState) -> Option<u64> {
+        self.last_updated(state)?.tick()
+    }
+
+    /// Whether `state` was last updated more than `max_age` ago, or never.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(mut agent: thuai_8_agent_rust::agent::Agent) {
+    /// use std::time::Duration;
+    /// use thuai_8_agent_rust::agent::freshness::CachedState;
+    ///
+    /// if agent.is_stale(CachedState::Players, Duration::from_millis(200)) {
+    ///     let _ = agent.fetch_player_info().await;
+    /// }
+    /// # }
+    /// ```
+    pub fn is_stale(&self, state: CachedState, max_age: Duration) -> bool {
+        self.last_updated(state)
+            .is_none_or(|updated| updated.is_stale(max_age))
+    }
+
+    /// Stream the [`GameEvent`]s from now on, derived as the cached state is
+    /// updated, see [`Agent::apply`].
+    ///
+    /// Events are only derived while a stream exists, and a stream lagging
+    /// more than 256 events behind misses the oldest.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(agent: thuai_8_agent_rust::agent::Agent) {
+    /// use futures::StreamExt;
+    /// use thuai_8_agent_rust::agent::events::GameEvent;
+    ///
+    /// let mut events = Box::pin(agent.events());
+    /// tokio::spawn(async move {
+    ///     while let Some(event) = events.next().await {
+    ///         if let GameEvent::DamageTaken { token, health, .. } = event {
+    ///             println!("{token} lost {health} health");
+    ///         }
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn events(&self) -> impl Stream<Item = GameEvent> + Send + 'static {
+        stream::unfold(self.events.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Event stream lagging, missed {} events", missed)
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    fn emit(&self, events: impl IntoIterator<Item = GameEvent>) {
+        for event in events {
+            // Fails only when nobody listens.
+            let _ = self.events.send(event);
+        }
+    }
+
+    fn parse_and_apply(&mut self, frame: &str) -> Option<ServerMessage> {
+        match serde_json::from_str(frame) {
+            Ok(message) => {
+                self.apply(&message);
+                Some(message)
+            }
+            Err(err) => {
+                debug!("Skipping message that does not parse: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Stop the background tasks of the connection and close it, see
+    /// [`AgentClient::shutdown`].
+    pub fn shutdown(&self) {
+        self.client.shutdown();
+    }
+
+    /// Resolves once the game reaches `tick`, e.g. to schedule an action
+    /// without polling [`GameStatistics`]. See [`AgentClient::at_tick`].
+    pub fn at_tick(&self, tick: u64) -> impl Future<Output = Option<u64>> + Send + 'static {
+        self.client.at_tick(tick)
+    }
+
+    /// Resolves `ticks` ticks from now, e.g. to re-engage after a retreat.
+    /// See [`AgentClient::after_ticks`].
+    pub fn after_ticks(&self, ticks: u64) -> impl Future<Output = Option<u64>> + Send + 'static {
+        self.client.after_ticks(ticks)
+    }
+
+    /// The current state of the connection to the server.
+    pub fn connection_state(&self) -> ConnectionState {
+        self.client.connection_state()
+    }
+
+    /// Watch the state of the connection, e.g. to stop issuing moves while
+    /// reconnecting. See [`AgentClient::watch_connection_state`].
+    pub fn watch_connection_state(&self) -> watch::Receiver<ConnectionState> {
+        self.client.watch_connection_state()
+    }
+
+    /// Call `hook` whenever an action is not delivered, because it is illegal
+    /// in the current stage, invalid, or the connection failed.
+    ///
+    /// The hook sees the same failures the caller already gets as `Err`, so
+    /// they can be logged or counted in one place. Hooks run in registration
+    /// order.
+    pub fn on_send_failure(
+        &mut self,
+        hook: impl Fn(&PerformMessage, &dyn Error) + Send + Sync + 'static,
+    ) {
+        self.send_failure_hooks.push(Arc::new(hook));
+    }
+
+    /// Run `callback` whenever the stage of the game changes, including when
+    /// it is first told.
+    ///
+    /// The callbacks of one update run one after the other, in registration
+    /// order, in a task of their own, so they must not expect the agent to
+    /// wait for them.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(mut agent: thuai_8_agent_rust::agent::Agent) {
+    /// use thuai_8_agent_rust::agent::events::StageChange;
+    ///
+    /// agent.on_stage_change(|change: StageChange| async move {
+    ///     println!("{:?} -> {:?}", change.from(), change.to());
+    /// });
+    /// # }
+    /// ```
+    pub fn on_stage_change<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(StageChange) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.subscribers.on_stage_change(callback);
+    }
+
+    /// Run `callback` with every player whenever player info arrives, see
+    /// [`Agent::on_stage_change`].
+    pub fn on_player_update<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(Players) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.subscribers.on_player_update(callback);
+    }
+
+    /// Run `callback` whenever the environment arrives, see
+    /// [`Agent::on_stage_change`].
+    pub fn on_environment_update<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(EnvironmentInfo) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.subscribers.on_environment_update(callback);
+    }
+
+    /// Run `callback` whenever the buffs to choose from arrive, see
+    /// [`Agent::on_stage_change`].
+    pub fn on_buffs_available<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(AvailableBuffs) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.subscribers.on_buffs_available(callback);
+    }
+
+    /// Take the next step towards `target`: turn to face the next waypoint
+    /// of the path there, or move forward to it. See [`Navigator`].
+    ///
+    /// Meant to be called once per tick until it returns
+    /// [`NavigationStep::Arrived`] or [`NavigationStep::Unreachable`]. The
+    /// path is kept between calls and planned again when `target` changes
+    /// or the environment was updated.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(mut agent: thuai_8_agent_rust::agent::Agent) {
+    /// use thuai_8_agent_rust::agent::model::Position;
+    /// use thuai_8_agent_rust::agent::navigation::NavigationStep;
+    ///
+    /// let target = Position::new(8.5, 3.5, 0.0);
+    /// match agent.move_to(target).await {
+    ///     Ok(NavigationStep::Arrived) => println!("There"),
+    ///     Ok(NavigationStep::Unreachable) => println!("No way there"),
+    ///     Ok(_) => {}
+    ///     Err(err) => println!("Could not move: {err}"),
+    /// }
+    /// # }
+    /// ```
+    pub async fn move_to(
+        &mut self,
+        target: Position<f64>,
+    ) -> Result<NavigationStep, Box<dyn Error>> {
+        self.sync();
+        if self
+            .navigator
+            .as_ref()
+            .is_none_or(|navigator| *navigator.goal() != target)
+        {
+            self.navigator = Some(Navigator::new(target));
+        }
+        let position = self.self_player().map(|player| player.position().clone());
+        let (Some(environment), Some(position)) = (&self.environment_info, position) else {
+            return Ok(NavigationStep::Waiting);
+        };
+        let map = GameMap::new(environment);
+        let updated = self
+            .last_updated(CachedState::Environment)
+            .map(|updated| updated.at());
+        let navigator = self.navigator.as_mut().expect("set above");
+        let step = navigator.next_step(&map, &position, updated);
+        match step {
+            NavigationStep::Turn(direction, angle) => {
+                self.send_perform_turn(direction, angle).await?
+            }
+            NavigationStep::Forward(distance) => {
+                self.send_perform_move(MoveDirection::Forth, distance)
+                    .await?
+            }
+            _ => {}
+        }
+        Ok(step)
+    }
+
+    /// Turn the shortest way to face `angle`, from the cached heading.
+    ///
+    /// Returns the whole degrees turned; nothing is sent when that rounds
+    /// to zero. Fails with [`NotReceived`] before the own player info
+    /// arrived.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(mut agent: thuai_8_agent_rust::agent::Agent) {
+    /// use thuai_8_agent_rust::agent::model::Angle;
+    ///
+    /// // Face up, whichever way is shorter.
+    /// agent.turn_to(Angle::QUARTER_TURN).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn turn_to(&mut self, angle: Angle) -> Result<u32, Box<dyn Error>> {
+        self.sync();
+        let heading = *self
+            .self_player()
+            .ok_or(NotReceived(CachedState::Players))?
+            .position()
+            .angle();
+        let (direction, turn) = heading.turn_to(angle);
+        let degrees = turn.degrees().round() as u32;
+        if degrees > 0 {
+            self.send_perform_turn(direction, degrees).await?;
+        }
+        Ok(degrees)
+    }
+
+    /// Turn to face `target`.
+    ///
+    /// Resolves with whether the cached heading is already within the aim
+    /// tolerance, see [`Agent::set_aim_tolerance`], so firing now would
+    /// hit; otherwise a turn is sent. Fails with [`NotReceived`] before the
+    /// own player info arrived.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(mut agent: thuai_8_agent_rust::agent::Agent) {
+    /// use thuai_8_agent_rust::agent::model::Position;
+    /// use thuai_8_agent_rust::agent::player_api::PlayerOperate;
+    ///
+    /// if agent.aim_at(&Position::new(3.5, 7.5, 0.0)).await.unwrap() {
+    ///     agent.attack().await.unwrap();
+    /// }
+    /// # }
+    /// ```
+    pub async fn aim_at(&mut self, target: &Position<f64>) -> Result<bool, Box<dyn Error>> {
+        self.sync();
+        let position = self
+            .self_player()
+            .ok_or(NotReceived(CachedState::Players))?
+            .position();
+        let wanted = position.angle_to(target);
+        if position
+            .angle()
+            .approx_eq(wanted, self.aim_tolerance.radians())
+        {
+            return Ok(true);
+        }
+        self.turn_to(wanted).await?;
+        Ok(false)
+    }
+
+    /// Like [`Agent::aim_at`], at the player with `token`.
+    ///
+    /// With `lead`, aims where the player will be when a bullet of the own
+    /// weapon gets there, predicted from [`Agent::history`] with an
+    /// [`OpponentPredictor`]. Lasers hit at once and need no lead. The
+    /// bullet speed is taken as distance per tick.
+    pub async fn aim_at_player(
+        &mut self,
+        token: &PlayerToken,
+        lead: bool,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.sync();
+        let (Some(me), Some(target)) = (
+            self.self_player(),
+            self.players_info
+                .iter()
+                .flatten()
+                .find(|player| player.token() == token),
+        ) else {
+            return Err(NotReceived(CachedState::Players).into());
+        };
+        let mut aim = target.position().clone();
+        if lead
+            && !me.weapon().is_laser()
+            && let Some(predictor) = OpponentPredictor::new(&self.history, token)
+        {
+            aim = predictor
+                .lead(me.position(), *me.weapon().bullet_speed())
+                .position()
+                .clone();
+        }
+        self.aim_at(&aim).await
+    }
+
+    /// Attack only if the own weapon can fire, see
+    /// [`Weapon::can_fire_after`](model::Weapon::can_fire_after), and
+    /// resolve with whether it did.
+    ///
+    /// The cooldown is tracked locally from the tick of the last attack
+    /// sent, so it is only known once the server announced ticks. Fails
+    /// with [`NotReceived`] before the own player info arrived.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(mut agent: thuai_8_agent_rust::agent::Agent) {
+    /// if !agent.attack_if_ready().await.unwrap() {
+    ///     println!("Still reloading");
+    /// }
+    /// # }
+    /// ```
+    pub async fn attack_if_ready(&mut self) -> Result<bool, Box<dyn Error>> {
+        self.sync();
+        let weapon = self
+            .self_player()
+            .ok_or(NotReceived(CachedState::Players))?
+            .weapon();
+        let since = self
+            .last_attack_tick
+            .zip(self.client.current_tick())
+            // A tick before the last shot belongs to a new game.
+            .and_then(|(last, now)| now.checked_sub(last));
+        if !weapon.can_fire_after(since) {
+            return Ok(false);
+        }
+        self.send_perform_attack().await?;
+        Ok(true)
+    }
+
+    /// Merge a `PLAYERS_INFO` reply into the cached players by token, see
+    /// [`model::merge_players`]. Replies to self and opponent requests come
+    /// separately, so one must not drop the other's entry.
+    ///
+    /// A reply with this agent's token covers this agent, and a reply with
+    /// any other token, or without this agent's, covers the opponents:
+    /// cached opponents missing from it left the game.
+    pub fn update_players_info(&mut self, players: Players) {
+        let has_self = players.iter().any(|player| *player.token() == self.token);
+        let has_opponents = players.iter().any(|player| *player.token() != self.token);
+        let token = &self.token;
+        let covers = |cached: &PlayerToken| {
+            if cached == token {
+                has_self
+            } else {
+                has_opponents || !has_self
+            }
+        };
+        match &mut self.players_info {
+            Some(cached) => model::merge_players(cached, players, covers),
+            None => self.players_info = Some(players),
+        }
+    }
+
+    /// Check and send `msg`, reporting a failure to the send failure hooks.
+    async fn perform(&mut self, msg: PerformMessage) -> Result<(), Box<dyn Error>> {
+        self.sync();
+        let result = match self.check_legal(&msg) {
+            Ok(()) => self.client.send_perform(msg.clone()).await,
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = &result {
+            for hook in &self.send_failure_hooks {
+                hook(&msg, err.as_ref());
+            }
+        }
+        result
+    }
+
+    /// Reject `msg` early if the rules forbid it in the current stage.
+    fn check_legal(&self, msg: &PerformMessage) -> Result<(), IllegalAction> {
+        let stage = self
+            .game_statistics
+            .as_ref()
+            .map(|statistics| statistics.current_stage());
+        legality::check(stage, msg)
+    }
+}
+
+impl QueryAPI for Agent {
     async fn send_get_available_buffs(&mut self) -> Result<(), Box<dyn Error>> {
         let msg = PerformMessage::GetAvailableBuffs {
             token: self.token.clone(),
         };
-        self.client.send(msg).await?;
+        self.client.send_perform(msg).await?;
         Ok(())
     }
     async fn send_get_environment_info(&mut self) -> Result<(), Box<dyn Error>> {
         let msg = PerformMessage::GetEnvironmentInfo {
             token: self.token.clone(),
         };
-        self.client.send(msg).await?;
+        self.client.send_perform(msg).await?;
         Ok(())
     }
     async fn send_get_game_statistics(&mut self) -> Result<(), Box<dyn Error>> {
         let msg = PerformMessage::GetGameStatistics {
             token: self.token.clone(),
         };
-        self.client.send(msg).await?;
+        self.client.send_perform(msg).await?;
         Ok(())
     }
     async fn send_get_player_info(&mut self) -> Result<(), Box<dyn Error>> {
@@ -52,15 +1078,19 @@ impl ConnectionAPI for Agent {
             token: self.token.clone(),
             request: RequestType::TheSelf,
         };
-        self.client.send(msg).await?;
-        self.client.send(msg2).await?;
+        self.client.send_perform(msg).await?;
+        self.client.send_perform(msg2).await?;
         Ok(())
     }
+}
+
+impl ConnectionAPI for Agent {
     async fn send_perform_attack(&mut self) -> Result<(), Box<dyn Error>> {
         let msg = PerformMessage::PerformAttack {
             token: self.token.clone(),
         };
-        self.client.send(msg).await?;
+        self.perform(msg).await?;
+        self.last_attack_tick = self.client.current_tick();
         Ok(())
     }
     async fn send_perform_move(
@@ -73,23 +1103,27 @@ impl ConnectionAPI for Agent {
             direction,
             distance,
         };
-        self.client.send(msg).await?;
-        Ok(())
+        self.perform(msg).await
     }
     async fn send_perform_select(&mut self, buff_name: BuffKind) -> Result<(), Box<dyn Error>> {
         let msg = PerformMessage::PerformSelect {
             token: self.token.clone(),
             buff_name,
         };
-        self.client.send(msg).await?;
+        self.perform(msg).await?;
+        self.emit([GameEvent::BuffSelected(buff_name)]);
         Ok(())
     }
     async fn send_perform_skill(&mut self, skill_name: SkillKind) -> Result<(), Box<dyn Error>> {
+        self.sync();
+        let tick = self.client.current_tick();
+        self.skills.check(skill_name, tick)?;
         let msg = PerformMessage::PerformSkill {
             token: self.token.clone(),
             skill_name,
         };
-        self.client.send(msg).await?;
+        self.perform(msg).await?;
+        self.skills.used(skill_name, tick);
         Ok(())
     }
     async fn send_perform_turn(
@@ -102,13 +1136,12 @@ impl ConnectionAPI for Agent {
             direction,
             angle,
         };
-        self.client.send(msg).await?;
-        Ok(())
+        self.perform(msg).await
     }
 }
 
 impl PlayerOperate for Agent {
-    fn token(&self) -> &str {
+    fn token(&self) -> &PlayerToken {
         &self.token
     }
 
@@ -128,60 +1161,314 @@ impl PlayerOperate for Agent {
         self.available_buffs.as_ref()
     }
 
-    async fn move_forward(&mut self, distance: f64) {
+    fn connection_stats(&self) -> ConnectionStats {
+        self.client.stats()
+    }
+
+    async fn move_forward(&mut self, distance: f64) -> Result<(), AgentError> {
         debug!("Agent moving forward");
-        self.send_perform_move(MoveDirection::Forth, distance)
-            .await
-            .unwrap_or_else(|err| {
-                error!("Sending moving forward message failed: {}", err);
-            });
+        Ok(self
+            .send_perform_move(MoveDirection::Forth, distance)
+            .await?)
     }
 
-    async fn move_backward(&mut self, distance: f64) {
+    async fn move_backward(&mut self, distance: f64) -> Result<(), AgentError> {
         debug!("Agent moving backward");
-        self.send_perform_move(MoveDirection::Back, distance)
-            .await
-            .unwrap_or_else(|err| {
-                error!("Sending move backward message failed: {}", err);
-            })
+        Ok(self
+            .send_perform_move(MoveDirection::Back, distance)
+            .await?)
     }
 
-    async fn turn_clockwise(&mut self, angle: u32) {
+    async fn turn_clockwise(&mut self, angle: u32) -> Result<(), AgentError> {
         debug!("Agent turning clockwise");
-        self.send_perform_turn(TurnDirection::Clockwise, angle)
-            .await
-            .unwrap_or_else(|err| {
-                error!("Sending turning clockwise message failed: {}", err);
-            })
+        Ok(self
+            .send_perform_turn(TurnDirection::Clockwise, angle)
+            .await?)
     }
 
-    async fn turn_counter_clockwise(&mut self, angle: u32) {
+    async fn turn_counter_clockwise(&mut self, angle: u32) -> Result<(), AgentError> {
         debug!("Agent turning counter clockwise");
-        self.send_perform_turn(TurnDirection::CounterClockwise, angle)
-            .await
-            .unwrap_or_else(|err| {
-                error!("Sending turning counter-clockwise message failed: {}", err);
-            })
+        Ok(self
+            .send_perform_turn(TurnDirection::CounterClockwise, angle)
+            .await?)
     }
 
-    async fn attack(&mut self) {
+    async fn attack(&mut self) -> Result<(), AgentError> {
         debug!("Agent attacking");
-        self.send_perform_attack().await.unwrap_or_else(|err| {
-            error!("Sending attack message failed: {}", err);
-        })
+        Ok(self.send_perform_attack().await?)
     }
 
-    async fn use_skill(&mut self, skill: SkillKind) {
+    async fn use_skill(&mut self, skill: SkillKind) -> Result<(), AgentError> {
         debug!("Agent using skill {}", skill);
-        self.send_perform_skill(skill).await.unwrap_or_else(|err| {
-            error!("Sending performing skill {} message failed: {}", skill, err);
-        })
+        Ok(self.send_perform_skill(skill).await?)
     }
 
-    async fn select_buff(&mut self, buff: BuffKind) {
+    async fn select_buff(&mut self, buff: BuffKind) -> Result<(), AgentError> {
         debug!("Agent selecting buff {}", buff);
-        self.send_perform_select(buff).await.unwrap_or_else(|err| {
-            error!("Sending selecting buff {} message failed: {}", buff, err);
-        })
+        Ok(self.send_perform_select(buff).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::PlayerBuilder;
+    use futures::{SinkExt, StreamExt};
+    use model::Skill;
+    use std::sync::Mutex;
+    use std::time::Instant;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// How long a tick of the fake server takes.
+    const TICK: Duration = Duration::from_millis(5);
+
+    /// The state the fake server answers from.
+    struct Game {
+        stage: &'static str,
+        started: Instant,
+        /// Answer a selection with an `ERROR`.
+        reject_select: bool,
+        /// Grant the skill of a selected buff.
+        grant_select: bool,
+        skills: Vec<Skill>,
+    }
+
+    impl Game {
+        fn new(stage: &'static str) -> Arc<Mutex<Game>> {
+            Arc::new(Mutex::new(Game {
+                stage,
+                started: Instant::now(),
+                reject_select: false,
+                grant_select: true,
+                skills: Vec::new(),
+            }))
+        }
+
+        fn answer(&mut self, request: &serde_json::Value) -> Option<String> {
+            let ticks = self.started.elapsed().as_millis() / TICK.as_millis();
+            let reply = match request["messageType"].as_str().unwrap_or_default() {
+                "GET_PLAYER_INFO" if request["request"] == "SELF" => {
+                    let player = PlayerBuilder::new("1919810")
+                        .with_skills(self.skills.clone())
+                        .build();
+                    let players = serde_json::to_string(&[player]).unwrap();
+                    format!(r#"{{"messageType":"PLAYERS_INFO","players":{players}}}"#)
+                }
+                "GET_PLAYER_INFO" => r#"{"messageType":"PLAYERS_INFO","players":[]}"#.to_string(),
+                "GET_ENVIRONMENT_INFO" => r#"{"messageType":"ENVIRONMENT_INFO","mapSize":10,"walls":[],"fences":[],"bullets":[]}"#.to_string(),
+                "GET_GAME_STATISTICS" => format!(
+                    r#"{{"messageType":"GAME_STATISTICS","currentStage":"{}","countDown":0,"ticks":{},"scores":[]}}"#,
+                    self.stage, ticks
+                ),
+                "GET_AVAILABLE_BUFFS" => {
+                    r#"{"messageType":"AVAILABLE_BUFFS","buffs":["FLASH","DODGE"]}"#.to_string()
+                }
+                "PERFORM_SELECT" if self.reject_select => {
+                    r#"{"messageType":"ERROR","errorCode":1,"message":"Not now"}"#.to_string()
+                }
+                "PERFORM_SELECT" => {
+                    let buff: BuffKind =
+                        serde_json::from_value(request["buffName"].clone()).unwrap();
+                    if let Some(skill) = buff.skill()
+                        && self.grant_select
+                    {
+                        self.skills.push(Skill::new(skill, 20, 0, false));
+                    }
+                    return None;
+                }
+                _ => return None,
+            };
+            Some(reply)
+        }
+    }
+
+    /// A server for one agent, answering each request from `game` and
+    /// recording the `messageType` of every request.
+    struct FakeServer {
+        url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeServer {
+        async fn start(game: Arc<Mutex<Game>>) -> FakeServer {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("ws://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(frame)) = socket.next().await {
+                    let Message::Text(text) = frame else {
+                        continue;
+                    };
+                    let value: serde_json::Value = serde_json::from_str(text.as_str()).unwrap();
+                    let request = value["messageType"].as_str().unwrap_or_default();
+                    recorded.lock().unwrap().push(request.to_string());
+                    let reply = game.lock().unwrap().answer(&value);
+                    if let Some(reply) = reply
+                        && socket.send(Message::text(reply)).await.is_err()
+                    {
+                        return;
+                    }
+                }
+            });
+            FakeServer { url, requests }
+        }
+
+        /// The requests received since the last call.
+        fn take_requests(&self) -> Vec<String> {
+            std::mem::take(&mut self.requests.lock().unwrap())
+        }
+
+        /// Connect an agent and apply the replies to its initial requests.
+        async fn connect(&self) -> Agent {
+            let mut agent = Agent::new(self.url.clone(), "1919810").await;
+            for _ in 0..4 {
+                agent.next_message().await.unwrap();
+            }
+            self.take_requests();
+            agent
+        }
+    }
+
+    fn run(test: impl Future<Output = ()>) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime
+            .block_on(async { tokio::time::timeout(Duration::from_secs(10), test).await })
+            .expect("test timed out");
+    }
+
+    #[test]
+    fn keeps_polling_with_ticks_from_statistics_only() {
+        run(async {
+            let server = FakeServer::start(Game::new("BATTLE")).await;
+            let mut agent = server.connect().await;
+            let tick = agent.client.current_tick().unwrap();
+
+            // About one poll per tick, for 40 ticks.
+            let mut interval = tokio::time::interval(TICK);
+            let (mut players, mut statistics) = (0, 0);
+            for _ in 0..40 {
+                interval.tick().await;
+                let due = agent.poll().await.unwrap();
+                assert!(!due.contains(&CachedState::Buffs));
+                players += due.contains(&CachedState::Players) as u32;
+                statistics += due.contains(&CachedState::Statistics) as u32;
+                for _ in &due {
+                    agent.next_message().await.unwrap();
+                }
+            }
+            assert!(players >= 15, "players polled {players} times");
+            assert!(
+                (2..=15).contains(&statistics),
+                "statistics polled {statistics} times"
+            );
+            assert!(agent.client.current_tick().unwrap() >= tick + 20);
+        });
+    }
+
+    #[test]
+    fn selects_a_buff_once_per_rest_stage() {
+        run(async {
+            let game = Game::new("BATTLE");
+            let server = FakeServer::start(game.clone()).await;
+            let mut agent = server.connect().await;
+
+            assert_eq!(agent.handle_buff_selection().await.unwrap(), None);
+            assert!(server.take_requests().is_empty());
+
+            game.lock().unwrap().stage = "REST";
+            agent.fetch_game_statistics().await.unwrap();
+            server.take_requests();
+            assert_eq!(
+                agent.handle_buff_selection().await.unwrap(),
+                Some(BuffKind::Flash)
+            );
+            assert_eq!(
+                server.take_requests(),
+                [
+                    "GET_AVAILABLE_BUFFS",
+                    "PERFORM_SELECT",
+                    "GET_PLAYER_INFO",
+                    "GET_PLAYER_INFO"
+                ]
+            );
+            assert_eq!(agent.selected_buff(), Some(BuffKind::Flash));
+            assert_eq!(agent.handle_buff_selection().await.unwrap(), None);
+
+            // The next rest stage selects again.
+            game.lock().unwrap().stage = "BATTLE";
+            agent.fetch_game_statistics().await.unwrap();
+            assert_eq!(agent.selected_buff(), None);
+            game.lock().unwrap().stage = "REST";
+            agent.fetch_game_statistics().await.unwrap();
+            assert_eq!(
+                agent.handle_buff_selection().await.unwrap(),
+                Some(BuffKind::Flash)
+            );
+        });
+    }
+
+    #[test]
+    fn retries_a_rejected_buff_selection() {
+        run(async {
+            let game = Game::new("REST");
+            game.lock().unwrap().reject_select = true;
+            let server = FakeServer::start(game.clone()).await;
+            let mut agent = server.connect().await;
+
+            assert!(agent.handle_buff_selection().await.is_err());
+            assert_eq!(agent.selected_buff(), None);
+
+            game.lock().unwrap().reject_select = false;
+            assert_eq!(
+                agent.handle_buff_selection().await.unwrap(),
+                Some(BuffKind::Flash)
+            );
+            assert_eq!(agent.selected_buff(), Some(BuffKind::Flash));
+        });
+    }
+
+    #[test]
+    fn rejects_a_buff_not_granted() {
+        run(async {
+            let game = Game::new("REST");
+            game.lock().unwrap().grant_select = false;
+            let server = FakeServer::start(game.clone()).await;
+            let mut agent = server.connect().await;
+
+            let err = agent.handle_buff_selection().await.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<BuffNotGranted>(),
+                Some(&BuffNotGranted(BuffKind::Flash))
+            );
+            assert_eq!(agent.selected_buff(), None);
+        });
+    }
+
+    #[test]
+    fn delivery_error_keeps_its_source() {
+        let err: Box<dyn Error> = Box::new(MessageTooLarge {
+            size: 2,
+            max_size: 1,
+        });
+        let err = AgentError::from(err);
+        let source = err
+            .source()
+            .and_then(|err| err.downcast_ref::<MessageTooLarge>());
+        assert_eq!(
+            source,
+            Some(&MessageTooLarge {
+                size: 2,
+                max_size: 1
+            })
+        );
+        let err = AgentError::from(Box::<dyn Error>::from("closed"));
+        assert_eq!(err.to_string(), "delivery failed: closed");
     }
 }
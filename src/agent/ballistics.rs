@@ -0,0 +1,475 @@
+//! Where bullets and laser beams are headed.
+//!
+//! Bullets fly straight and bounce off walls and the edges of the map, until
+//! they have flown their range or hit a fence. [`predict`] traces the path of
+//! a [`Bullet`] over a [`GameMap`] as timed waypoints, e.g. to dodge it.
+//! Laser beams bounce the same way, up to a number of times, see
+//! [`LaserPath`].
+
+use super::map::{Barrier, GameMap, Side};
+use super::model::{Angle, Bullet, Player, Position};
+use crate::math::geometry::{self, Segment};
+use getset::Getters;
+
+/// Bounces traced at most, so a bullet caught between two walls cannot
+/// stall the prediction.
+const MAX_REFLECTIONS: usize = 64;
+
+/// Distance within which a point counts as passed through.
+const POINT_RADIUS: f64 = 1e-6;
+
+/// Tolerance when comparing distances to a bounce.
+const EPSILON: f64 = 1e-9;
+
+/// A point on a [`Trajectory`]: where the bullet starts, bounces or stops.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct Waypoint {
+    /// Where the bullet is, heading on from there along `position.angle`.
+    position: Position<f64>,
+    /// Time from now until the bullet gets there, in the unit of its speed.
+    time: f64,
+}
+
+/// Why a [`Trajectory`] ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrajectoryEnd {
+    /// The bullet flew its whole range.
+    Spent,
+    /// The bullet hit a fence.
+    Fence,
+    /// Tracing stopped after many bounces.
+    Truncated,
+}
+
+/// The predicted path of a bullet.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct Trajectory {
+    /// The current position, every bounce and the end, in order.
+    waypoints: Vec<Waypoint>,
+    /// Distance the bullet can still fly, from its current position.
+    remaining_distance: f64,
+    end: TrajectoryEnd,
+}
+
+impl Trajectory {
+    /// Where the bullet will be after `time`, or [`None`] once it is gone.
+    pub fn position_at(&self, time: f64) -> Option<Position<f64>> {
+        let (last, segments) = self.waypoints.split_last()?;
+        if time > last.time || time < 0.0 {
+            return None;
+        }
+        for (start, end) in segments.iter().zip(&self.waypoints[1..]) {
+            if time <= end.time {
+                let span = end.time - start.time;
+                let t = if span > 0.0 {
+                    (time - start.time) / span
+                } else {
+                    0.0
+                };
+                let (x, y) = (
+                    start.position.x() + (end.position.x() - start.position.x()) * t,
+                    start.position.y() + (end.position.y() - start.position.y()) * t,
+                );
+                return Some(Position::new(x, y, *start.position.angle()));
+            }
+        }
+        Some(last.position.clone())
+    }
+
+    /// Time from now until the bullet first comes within `radius` of
+    /// `target`, bounces included, or [`None`] if it never does before it
+    /// is gone.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::ballistics;
+    /// use thuai_8_agent_rust::agent::map::GameMap;
+    /// use thuai_8_agent_rust::agent::model::{Bullet, EnvironmentInfo, Position};
+    ///
+    /// let map = GameMap::new(&EnvironmentInfo::new(4, Vec::new(), Vec::new(), Vec::new()));
+    /// // Flying along +x at 2 per tick, bouncing off the right edge.
+    /// let bullet = Bullet::new(1, false, false, Position::new(0.5, 0.5, 0.0), 2.0, 1.0, 0.0);
+    /// let trajectory = ballistics::predict(&map, &bullet, 10.0);
+    ///
+    /// let tank = Position::new(2.5, 0.5, 0.0);
+    /// assert_eq!(trajectory.time_to_reach(&tank, 0.5), Some(0.75));
+    /// assert_eq!(trajectory.time_to_reach(&Position::new(2.5, 2.0, 0.0), 0.5), None);
+    /// ```
+    pub fn time_to_reach(&self, target: &Position<f64>, radius: f64) -> Option<f64> {
+        let first = self.waypoints.first()?;
+        if first.position.distance_to(target) <= radius {
+            return Some(first.time).filter(|time| time.is_finite());
+        }
+        self.waypoints.windows(2).find_map(|pair| {
+            let (start, end) = (&pair[0], &pair[1]);
+            let segment = Segment::new(start.position.clone(), end.position.clone());
+            let entry = geometry::circle_segment_intersection(target, radius, &segment)
+                .into_iter()
+                .next()?;
+            let length = segment.length();
+            let fraction = if length > 0.0 {
+                start.position.distance_to(&entry) / length
+            } else {
+                0.0
+            };
+            Some(start.time + (end.time - start.time) * fraction).filter(|time| time.is_finite())
+        })
+    }
+}
+
+/// The path of `bullet` across `map`, given that bullets fly `range` in
+/// total.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::ballistics::{self, TrajectoryEnd};
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Angle, Bullet, EnvironmentInfo, Position, Wall};
+///
+/// let environment = EnvironmentInfo::new(
+///     4,
+///     vec![Wall::new(2, 0, Angle::from_degrees(90.0))],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+/// // Flying along +x at 2 per tick, 1 of its range of 5 already flown.
+/// let bullet = Bullet::new(1, false, false, Position::new(0.5, 0.5, 0.0), 2.0, 1.0, 1.0);
+///
+/// let trajectory = ballistics::predict(&map, &bullet, 5.0);
+///
+/// let bounce = &trajectory.waypoints()[1];
+/// assert_eq!(bounce.position(), &Position::new(2.0, 0.5, 0.0));
+/// assert_eq!(bounce.time(), &0.75);
+/// assert_eq!(trajectory.position_at(1.75), Some(Position::new(0.0, 0.5, 0.0)));
+/// assert_eq!(trajectory.end(), &TrajectoryEnd::Spent);
+/// ```
+pub fn predict(map: &GameMap, bullet: &Bullet, range: f64) -> Trajectory {
+    let remaining_distance = (range - bullet.traveled_distance()).max(0.0);
+    let speed = *bullet.speed();
+    let (points, end) = trace(map, bullet.position(), remaining_distance, MAX_REFLECTIONS);
+    let waypoints = points
+        .into_iter()
+        .map(|(position, distance)| Waypoint {
+            position,
+            time: if speed > 0.0 {
+                distance / speed
+            } else {
+                f64::INFINITY
+            },
+        })
+        .collect();
+    Trajectory {
+        waypoints,
+        remaining_distance,
+        end,
+    }
+}
+
+/// Time from now until `bullet` passes through `target`, bounces included,
+/// given that bullets fly `range` in total; [`None`] if it is gone before.
+/// The time is in the unit of its speed.
+///
+/// A tank is hit before its centre is reached; see
+/// [`Trajectory::time_to_reach`] to take its radius into account.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::ballistics;
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Bullet, EnvironmentInfo, Position};
+///
+/// let map = GameMap::new(&EnvironmentInfo::new(4, Vec::new(), Vec::new(), Vec::new()));
+/// // Flying along +x at 2 per tick.
+/// let bullet = Bullet::new(1, false, false, Position::new(0.5, 0.5, 0.0), 2.0, 1.0, 0.0);
+/// let ahead = Position::new(1.0, 0.5, 0.0);
+/// let behind = Position::new(0.25, 0.5, 0.0);
+///
+/// let close = |time: Option<f64>, expected: f64| (time.unwrap() - expected).abs() < 1e-6;
+/// assert!(close(ballistics::time_to_reach(&map, &bullet, &ahead, 10.0), 0.25));
+/// // On the way back from the right edge.
+/// assert!(close(ballistics::time_to_reach(&map, &bullet, &behind, 10.0), 3.625));
+/// // The range is spent before it gets back.
+/// assert_eq!(ballistics::time_to_reach(&map, &bullet, &behind, 7.0), None);
+/// ```
+pub fn time_to_reach(
+    map: &GameMap,
+    bullet: &Bullet,
+    target: &Position<f64>,
+    range: f64,
+) -> Option<f64> {
+    predict(map, bullet, range).time_to_reach(target, POINT_RADIUS)
+}
+
+/// Follow a straight line from `start` along `start.angle` for `distance`,
+/// bouncing off walls and map edges at most `max_bounces` times. Returns the
+/// start, every bounce and the end, each with the distance flown to it.
+fn trace(
+    map: &GameMap,
+    start: &Position<f64>,
+    distance: f64,
+    max_bounces: usize,
+) -> (Vec<(Position<f64>, f64)>, TrajectoryEnd) {
+    // Longest straight line on the map; any line longer meets an edge.
+    let diagonal = map.size() as f64 * std::f64::consts::SQRT_2 + 1.0;
+    let mut position = start.clone();
+    let mut points = vec![(position.clone(), 0.0)];
+    let mut flown = 0.0;
+    for bounces in 0..=max_bounces {
+        let left = distance - flown;
+        let (sin, cos) = position.angle().sin_cos();
+        let reach = left.min(diagonal);
+        let end = position.offset(reach * cos, reach * sin);
+        let barrier = map.first_hit(&position, &end).map(|hit| Bounce {
+            distance: position.distance_to(hit.point()),
+            side: *hit.side(),
+            fence: matches!(hit.barrier(), Barrier::Fence(_)),
+        });
+        let edge =
+            edge_hit(map.size() as f64, &position, cos, sin).filter(|edge| edge.distance <= left);
+        let bounce = match (barrier, edge) {
+            // A barrier ending on the edge is hit first, e.g. in a corner.
+            (Some(barrier), Some(edge)) if edge.distance < barrier.distance - EPSILON => Some(edge),
+            (barrier, edge) => barrier.or(edge),
+        };
+        let Some(bounce) = bounce else {
+            points.push((end, distance));
+            return (points, TrajectoryEnd::Spent);
+        };
+        flown += bounce.distance;
+        let point = position.offset(bounce.distance * cos, bounce.distance * sin);
+        // Put the point exactly on the grid line, so it is not hit again.
+        let (x, y, angle) = match bounce.side {
+            Side::Left | Side::Right => (
+                point.x().round(),
+                *point.y(),
+                Angle::HALF_TURN - *position.angle(),
+            ),
+            Side::Down | Side::Up => (*point.x(), point.y().round(), -*position.angle()),
+        };
+        if bounce.fence || bounces == max_bounces {
+            points.push((Position::new(x, y, *position.angle()), flown));
+            let end = if bounce.fence {
+                TrajectoryEnd::Fence
+            } else {
+                TrajectoryEnd::Truncated
+            };
+            return (points, end);
+        }
+        position = Position::new(x, y, angle);
+        points.push((position.clone(), flown));
+    }
+    unreachable!("the last bounce returns")
+}
+
+/// The beam of a laser, fired from a position along its angle. It bounces
+/// off walls and map edges up to a number of times and stops at a fence.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::ballistics::LaserPath;
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{EnvironmentInfo, Position};
+///
+/// let map = GameMap::new(&EnvironmentInfo::new(4, Vec::new(), Vec::new(), Vec::new()));
+/// // Fired at 45 degrees, bouncing once off the top edge.
+/// let shooter = Position::new(0.5, 1.0, std::f64::consts::FRAC_PI_4);
+/// let laser = LaserPath::new(&map, &shooter, 1);
+///
+/// assert_eq!(
+///     laser.points(),
+///     &[shooter.clone(), Position::new(3.5, 4.0, 0.0), Position::new(4.0, 3.5, 0.0)]
+/// );
+/// assert!(laser.hits(&Position::new(2.0, 2.5, 0.0), 0.1));
+/// assert!(laser.hits(&Position::new(3.9, 3.6, 0.0), 0.1));
+/// assert!(!laser.hits(&Position::new(0.5, 3.5, 0.0), 0.1));
+/// ```
+#[derive(Debug, Clone, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct LaserPath {
+    /// Where the beam starts, bounces and ends, in order.
+    points: Vec<Position<f64>>,
+    end: TrajectoryEnd,
+}
+
+impl LaserPath {
+    /// The beam fired from `shooter` along `shooter.angle`, bouncing at most
+    /// `max_reflections` times, as set by the rules.
+    pub fn new(map: &GameMap, shooter: &Position<f64>, max_reflections: usize) -> LaserPath {
+        let (points, end) = trace(map, shooter, f64::INFINITY, max_reflections);
+        LaserPath {
+            points: points.into_iter().map(|(point, _)| point).collect(),
+            end,
+        }
+    }
+
+    /// The straight pieces of the beam.
+    pub fn segments(&self) -> impl Iterator<Item = (&Position<f64>, &Position<f64>)> {
+        self.points.iter().zip(self.points.iter().skip(1))
+    }
+
+    /// Whether the beam passes within `radius` of `target`.
+    pub fn hits(&self, target: &Position<f64>, radius: f64) -> bool {
+        self.segments().any(|(start, end)| {
+            let segment = Segment::new(start.clone(), end.clone());
+            geometry::point_segment_distance(target, &segment) <= radius
+        })
+    }
+
+    /// Whether the beam hits `player`, taken as a circle of `radius`.
+    pub fn hits_player(&self, player: &Player, radius: f64) -> bool {
+        self.hits(player.position(), radius)
+    }
+}
+
+/// A wall, fence or map edge met on the way.
+struct Bounce {
+    distance: f64,
+    side: Side,
+    fence: bool,
+}
+
+/// The first edge of a map of side `size` met flying from `position` along
+/// `(cos, sin)`.
+fn edge_hit(size: f64, position: &Position<f64>, cos: f64, sin: f64) -> Option<Bounce> {
+    let along = |start: f64, delta: f64, low: Side, high: Side| {
+        if delta > 0.0 {
+            Some(((size - start) / delta, high))
+        } else if delta < 0.0 {
+            Some((-start / delta, low))
+        } else {
+            None
+        }
+    };
+    let x = along(*position.x(), cos, Side::Left, Side::Right);
+    let y = along(*position.y(), sin, Side::Down, Side::Up);
+    let (distance, side) = match (x, y) {
+        (Some(x), Some(y)) => {
+            if x.0 <= y.0 {
+                x
+            } else {
+                y
+            }
+        }
+        (x, y) => x.or(y)?,
+    };
+    Some(Bounce {
+        distance: distance.max(0.0),
+        side,
+        fence: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{EnvironmentInfo, Fence, Wall};
+    use crate::testing::fixtures::bullet;
+    use std::f64::consts::FRAC_PI_4;
+
+    fn points(trajectory: &Trajectory) -> Vec<(f64, f64, f64)> {
+        trajectory
+            .waypoints()
+            .iter()
+            .map(|waypoint| {
+                let position = waypoint.position();
+                let round = |value: f64| (value * 1000.0).round() / 1000.0;
+                (
+                    round(*position.x()),
+                    round(*position.y()),
+                    round(*waypoint.time()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bounces_off_edges_and_walls() {
+        let open = GameMap::new(&EnvironmentInfo::new(4, Vec::new(), Vec::new(), Vec::new()));
+
+        let trajectory = predict(&open, &bullet(1, 0.5, 0.5, 0.0), 9.0);
+        assert_eq!(
+            points(&trajectory),
+            [
+                (0.5, 0.5, 0.0),
+                (4.0, 0.5, 3.5),
+                (0.0, 0.5, 7.5),
+                (1.5, 0.5, 9.0)
+            ]
+        );
+        assert_eq!(trajectory.remaining_distance(), &9.0);
+        assert_eq!(
+            trajectory.position_at(5.5),
+            Some(Position::new(2.0, 0.5, 0.0))
+        );
+        assert_eq!(trajectory.position_at(9.5), None);
+
+        let walled = GameMap::new(&EnvironmentInfo::new(
+            4,
+            vec![Wall::new(1, 2, Angle::from_degrees(0.0))],
+            vec![Fence::new(
+                Position::new(3, 0, Angle::from_degrees(90.0)),
+                1,
+            )],
+            Vec::new(),
+        ));
+        let trajectory = predict(&walled, &bullet(1, 0.5, 0.5, FRAC_PI_4), 10.0);
+        assert_eq!(
+            points(&trajectory),
+            [(0.5, 0.5, 0.0), (2.0, 2.0, 2.121), (3.0, 1.0, 3.536)]
+        );
+        assert_eq!(trajectory.end(), &TrajectoryEnd::Fence);
+    }
+
+    #[test]
+    fn times_reaching_a_target() {
+        let open = GameMap::new(&EnvironmentInfo::new(4, Vec::new(), Vec::new(), Vec::new()));
+        let trajectory = predict(&open, &bullet(1, 0.5, 0.5, 0.0), 9.0);
+        let close = |time: Option<f64>, expected: f64| (time.unwrap() - expected).abs() < 1e-9;
+
+        // Already inside, on the first leg, and only after the bounce.
+        assert_eq!(
+            trajectory.time_to_reach(&Position::new(0.7, 0.5, 0.0), 0.3),
+            Some(0.0)
+        );
+        assert!(close(
+            trajectory.time_to_reach(&Position::new(2.0, 1.0, 0.0), 0.5),
+            1.5
+        ));
+        assert!(close(
+            trajectory.time_to_reach(&Position::new(0.0, 0.8, 0.0), 0.5),
+            7.1
+        ));
+        // Off the path, and past the end of it.
+        assert_eq!(
+            trajectory.time_to_reach(&Position::new(2.0, 2.0, 0.0), 0.5),
+            None
+        );
+        assert_eq!(
+            trajectory.time_to_reach(&Position::new(2.5, 0.5, 0.0), 0.5),
+            Some(1.5)
+        );
+        let spent = predict(&open, &bullet(1, 0.5, 0.5, 0.0), 0.0);
+        assert_eq!(
+            spent.time_to_reach(&Position::new(2.5, 0.5, 0.0), 0.5),
+            None
+        );
+
+        let still = Bullet::new(1, false, false, Position::new(0.5, 0.5, 0.0), 0.0, 1.0, 0.0);
+        let trajectory = predict(&open, &still, 10.0);
+        assert_eq!(
+            trajectory.time_to_reach(&Position::new(1.0, 0.5, 0.0), 0.5),
+            None
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Collisions of a tank, seen as a circle, with walls, fences and the edge
+//! of the map.
+//!
+//! The server rejects moves into barriers, so [`max_move_distance`] gives
+//! how far a `PerformMove` can actually go along a heading.
+
+use super::map::{GameMap, edge_segment};
+use super::model::{Fence, Position, Wall};
+use super::symmetry::is_vertical;
+use crate::math::geometry::{self, Segment};
+
+/// Tolerance for touching without overlapping.
+const EPSILON: f64 = 1e-9;
+
+/// Whether a circle of `radius` around `center` overlaps the segment from
+/// `start` to `end`. Touching is not overlapping.
+pub fn circle_hits_segment(
+    center: &Position<f64>,
+    radius: f64,
+    start: &Position<f64>,
+    end: &Position<f64>,
+) -> bool {
+    let segment = Segment::new(start.clone(), end.clone());
+    geometry::point_segment_distance(center, &segment) < radius - EPSILON
+}
+
+/// Whether a tank of `radius` at `center` overlaps `wall`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::collision;
+/// use thuai_8_agent_rust::agent::model::{Angle, Position, Wall};
+///
+/// let wall = Wall::new(1, 0, Angle::from_degrees(90.0));
+///
+/// assert!(collision::circle_hits_wall(&Position::new(0.8, 0.5, 0.0), 0.3, &wall));
+/// assert!(!collision::circle_hits_wall(&Position::new(0.5, 0.5, 0.0), 0.3, &wall));
+/// ```
+pub fn circle_hits_wall(center: &Position<f64>, radius: f64, wall: &Wall) -> bool {
+    let segment = edge_segment(*wall.x(), *wall.y(), is_vertical(*wall.angle()));
+    circle_hits_segment(center, radius, segment.start(), segment.end())
+}
+
+/// Whether a tank of `radius` at `center` overlaps `fence`. A fence with no
+/// health left overlaps nothing.
+pub fn circle_hits_fence(center: &Position<f64>, radius: f64, fence: &Fence) -> bool {
+    let position = fence.position();
+    let segment = edge_segment(*position.x(), *position.y(), is_vertical(*position.angle()));
+    *fence.health() > 0 && circle_hits_segment(center, radius, segment.start(), segment.end())
+}
+
+/// Whether a tank of `radius` at `center` overlaps a barrier of `map` or
+/// sticks out of it.
+pub fn collides(map: &GameMap, center: &Position<f64>, radius: f64) -> bool {
+    let size = map.size() as f64;
+    let inside = |value: f64| value >= radius - EPSILON && value <= size - radius + EPSILON;
+    !inside(*center.x())
+        || !inside(*center.y())
+        || map
+            .segments()
+            .any(|(segment, _)| circle_hits_segment(center, radius, segment.start(), segment.end()))
+}
+
+/// Distance along `(cos, sin)` from `center` until a circle of `radius`
+/// touches the point `point`.
+fn until_point(
+    center: &Position<f64>,
+    cos: f64,
+    sin: f64,
+    radius: f64,
+    point: &Position<f64>,
+) -> f64 {
+    let (px, py) = (center.x() - point.x(), center.y() - point.y());
+    let along = px * cos + py * sin;
+    let discriminant = along * along - (px * px + py * py - radius * radius);
+    if discriminant < 0.0 {
+        return f64::INFINITY;
+    }
+    let distance = -along - discriminant.sqrt();
+    if distance >= -EPSILON {
+        distance.max(0.0)
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Distance along `(cos, sin)` from `center` until a circle of `radius`
+/// touches the segment from `start` to `end`.
+fn until_segment(
+    center: &Position<f64>,
+    cos: f64,
+    sin: f64,
+    radius: f64,
+    start: &Position<f64>,
+    end: &Position<f64>,
+) -> f64 {
+    let length = start.distance_to(end);
+    let (ux, uy) = (
+        (end.x() - start.x()) / length,
+        (end.y() - start.y()) / length,
+    );
+    let (nx, ny) = (-uy, ux);
+    let (px, py) = (center.x() - start.x(), center.y() - start.y());
+    let offset = px * nx + py * ny;
+    let closing = cos * nx + sin * ny;
+    let mut best = until_point(center, cos, sin, radius, start)
+        .min(until_point(center, cos, sin, radius, end));
+    // Only moving towards the segment closes in on its body.
+    if closing * offset < 0.0 {
+        let distance = (offset.abs() - radius) / closing.abs();
+        if distance >= -EPSILON {
+            let distance = distance.max(0.0);
+            let along = (px + cos * distance) * ux + (py + sin * distance) * uy;
+            if (0.0..=length).contains(&along) {
+                best = best.min(distance);
+            }
+        }
+    }
+    best
+}
+
+/// How far a tank of `radius` at `center` can move along `heading`, in
+/// radians, before it touches a barrier of `map` or the edge of the map.
+///
+/// Zero if it already overlaps one.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::collision;
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Position, Wall};
+///
+/// let environment = EnvironmentInfo::new(
+///     4,
+///     vec![Wall::new(2, 0, Angle::from_degrees(90.0))],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+/// let tank = Position::new(0.5, 0.5, 0.0);
+///
+/// // To the wall at x = 2.
+/// assert!((collision::max_move_distance(&map, &tank, 0.0, 0.25) - 1.25).abs() < 1e-9);
+/// // To the top edge of the map.
+/// let up = std::f64::consts::FRAC_PI_2;
+/// assert!((collision::max_move_distance(&map, &tank, up, 0.25) - 3.25).abs() < 1e-9);
+/// ```
+pub fn max_move_distance(map: &GameMap, center: &Position<f64>, heading: f64, radius: f64) -> f64 {
+    if collides(map, center, radius) {
+        return 0.0;
+    }
+    let (sin, cos) = heading.sin_cos();
+    let size = map.size() as f64;
+    let edge = |start: f64, delta: f64| {
+        if delta > EPSILON {
+            (size - radius - start) / delta
+        } else if delta < -EPSILON {
+            (radius - start) / delta
+        } else {
+            f64::INFINITY
+        }
+    };
+    map.segments()
+        .map(|(segment, _)| until_segment(center, cos, sin, radius, segment.start(), segment.end()))
+        .fold(edge(*center.x(), cos).min(edge(*center.y(), sin)), f64::min)
+        .max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{Angle, EnvironmentInfo};
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    #[test]
+    fn stops_at_walls_fences_and_corners() {
+        let environment = EnvironmentInfo::new(
+            5,
+            vec![Wall::new(3, 1, Angle::from_degrees(90.0))],
+            vec![
+                Fence::new(Position::new(0, 3, 0.0), 2),
+                Fence::new(Position::new(1, 3, 0.0), 0),
+            ],
+            Vec::new(),
+        );
+        let map = GameMap::new(&environment);
+        let at = |x, y| Position::new(x, y, 0.0);
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        // Into the fence, and through the broken one next to it.
+        assert!(close(
+            max_move_distance(&map, &at(0.5, 0.5), FRAC_PI_2, 0.2),
+            2.3
+        ));
+        assert!(close(
+            max_move_distance(&map, &at(1.5, 0.5), FRAC_PI_2, 0.2),
+            4.3
+        ));
+        // Against the end of the wall at (3, 1).
+        assert!(close(
+            max_move_distance(&map, &at(3.0, 0.5), FRAC_PI_2, 0.2),
+            0.3
+        ));
+        // Backwards, to the left edge.
+        assert!(close(max_move_distance(&map, &at(2.5, 1.5), PI, 0.2), 2.3));
+        // Diagonally into the wall body.
+        let diagonal = max_move_distance(&map, &at(2.0, 1.0), FRAC_PI_4, 0.2);
+        assert!(close(diagonal, 0.8 * 2f64.sqrt()));
+
+        assert!(collides(&map, &at(2.9, 1.5), 0.2));
+        assert!(collides(&map, &at(0.1, 4.0), 0.2));
+        assert!(!collides(&map, &at(2.8, 1.5), 0.2));
+        assert_eq!(max_move_distance(&map, &at(2.9, 1.5), PI, 0.2), 0.0);
+        assert!(circle_hits_fence(
+            &at(0.5, 3.1),
+            0.2,
+            &environment.fences()[0]
+        ));
+        assert!(!circle_hits_fence(
+            &at(1.5, 3.1),
+            0.2,
+            &environment.fences()[1]
+        ));
+    }
+}
@@ -1,85 +1,1328 @@
 /*! Contains struct and method to handle the connection to the server. */
+mod dedup;
+#[cfg(feature = "fault-injection")]
+mod fault;
+mod frame_log;
+mod health;
+mod hooks;
+mod inbox;
+mod init_report;
+mod keepalive;
+mod latency;
+mod protocol;
+mod replay;
+mod retry;
+mod staleness;
+mod stats;
+mod watchdog;
+
 use core::error::Error;
-use std::time::Duration;
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
 
 use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
+use getset::Getters;
 use serde::Serialize;
-use tokio::{net::TcpStream, time::sleep};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{broadcast, watch};
+use tokio::time::{sleep, timeout};
 use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+pub use tokio_util::sync::CancellationToken;
 
 type Connection = WebSocketStream<MaybeTlsStream<TcpStream>>;
 type WriteConnection = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
 type ReadConnection = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
 
-use tokio_tungstenite::connect_async;
-use tracing::{debug, error, info};
+use tokio_tungstenite::connect_async_with_config;
+use tokio_tungstenite::tungstenite::error::CapacityError;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tracing::{debug, error, info, warn};
+
+use super::logging::throttled;
+use super::model::{BuffKind, MoveDirection, PlayerToken, RequestType, SkillKind, TurnDirection};
+use super::report::MatchSummary;
+use dedup::Deduplicator;
+#[cfg(feature = "fault-injection")]
+pub use fault::FaultInjection;
+#[cfg(feature = "fault-injection")]
+use fault::{Fault, FaultInjector};
+use frame_log::FrameLog;
+pub use frame_log::{FrameDirection, RecordedFrame};
+pub use health::ConnectionState;
+use health::{Health, LinkState};
+use hooks::Hooks;
+pub use hooks::{IncomingHook, OutgoingHook};
+pub use inbox::BackpressurePolicy;
+use inbox::{Inbox, Pushed};
+pub use init_report::{InitReport, SubsystemState};
+use keepalive::Activity;
+pub use keepalive::{Keepalive, KeepaliveProbe};
+pub use latency::{LatencyReport, LatencyStage, StageLatency};
+use protocol::Compat;
+pub use protocol::FieldAlias;
+use replay::ReplayHasher;
+pub use retry::{ExponentialBackoff, FixedDelay, JitteredBackoff, RetryPolicy};
+use staleness::{Accepted, TickFilter};
+pub use stats::ConnectionStats;
+use stats::StatsRecorder;
+use watchdog::Watchdog;
+pub use watchdog::{IdleAlarm, IdleEvent, IdleHook};
+
+const INBOX_CAPACITY: usize = 1024;
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+const MAX_FRAME_SIZE: usize = 16 << 20;
+/// Frames a slow raw subscriber may lag behind before missing some.
+const RAW_TAP_CAPACITY: usize = 256;
+/// Panics in a row after which the connection is considered broken.
+const MAX_HANDLER_PANICS: u32 = 8;
+/// Bytes of an offending payload that are logged.
+const PANIC_PAYLOAD_LOG_LEN: usize = 512;
+/// How long closing a broken connection may take before it is abandoned.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Every `messageType` the server is known to send.
+const KNOWN_MESSAGE_TYPES: [&str; 5] = [
+    "PLAYERS_INFO",
+    "ENVIRONMENT_INFO",
+    "GAME_STATISTICS",
+    "AVAILABLE_BUFFS",
+    "ERROR",
+];
+
+/// Options applied when [`AgentClient`] connects to the server.
+///
+/// Should be created with [`ClientConfig::new`] (or [`Default`]) and then
+/// extended with the `with_*` methods.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::connection::ClientConfig;
+///
+/// let config = ClientConfig::new()
+///     .with_header("Authorization", "Bearer 1919810")
+///     .with_query_param("token", "1919810");
+///
+/// assert_eq!(config.query_params().len(), 1);
+/// ```
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct ClientConfig {
+    headers: Vec<(String, String)>,
+    query_params: Vec<(String, String)>,
+    redundant: bool,
+    unknown_sink: Option<UnboundedSender<String>>,
+    inbox_capacity: usize,
+    backpressure: BackpressurePolicy,
+    idle_alarm: Option<IdleAlarm>,
+    keepalive: Option<Keepalive>,
+    frame_log_capacity: usize,
+    match_webhook: Option<String>,
+    protocol_version: Option<String>,
+    field_aliases: Vec<FieldAlias>,
+    hooks: Hooks,
+    retry_policy: Arc<dyn RetryPolicy>,
+    reconnect: bool,
+    max_message_size: usize,
+    max_frame_size: usize,
+    cancellation: CancellationToken,
+    #[cfg(feature = "fault-injection")]
+    fault_injection: Option<FaultInjection>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            headers: Vec::new(),
+            query_params: Vec::new(),
+            redundant: false,
+            unknown_sink: None,
+            inbox_capacity: INBOX_CAPACITY,
+            backpressure: BackpressurePolicy::default(),
+            idle_alarm: None,
+            keepalive: None,
+            frame_log_capacity: 0,
+            match_webhook: None,
+            protocol_version: None,
+            field_aliases: Vec::new(),
+            hooks: Hooks::default(),
+            retry_policy: Arc::new(FixedDelay::default()),
+            reconnect: true,
+            max_message_size: MAX_MESSAGE_SIZE,
+            max_frame_size: MAX_FRAME_SIZE,
+            cancellation: CancellationToken::new(),
+            #[cfg(feature = "fault-injection")]
+            fault_injection: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Constructs a [`ClientConfig`] with no extra options.
+    pub fn new() -> ClientConfig {
+        ClientConfig::default()
+    }
+
+    /// Bound the queue of received messages to `capacity`, applying `policy`
+    /// when messages arrive faster than they are taken.
+    ///
+    /// Defaults to 1024 messages with [`BackpressurePolicy::DropOldest`].
+    pub fn with_backpressure(mut self, capacity: usize, policy: BackpressurePolicy) -> Self {
+        self.inbox_capacity = capacity;
+        self.backpressure = policy;
+        self
+    }
+
+    /// Attach an extra HTTP header to the websocket upgrade request.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Append an extra query parameter to the server url.
+    pub fn with_query_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query_params.push((key.into(), value.into()));
+        self
+    }
+
+    /// Keep two simultaneous connections to the server.
+    ///
+    /// Inbound messages are deduplicated across both connections and
+    /// outbound messages go over whichever connection is still healthy.
+    /// Only useful if the server accepts two connections for one token.
+    pub fn with_redundancy(mut self, redundant: bool) -> Self {
+        self.redundant = redundant;
+        self
+    }
+
+    /// Forward raw frames with an unknown `messageType` to `sink`.
+    ///
+    /// Such frames are never queued for [`AgentClient::recv`]; without a
+    /// sink they are only logged.
+    pub fn with_unknown_sink(mut self, sink: UnboundedSender<String>) -> Self {
+        self.unknown_sink = Some(sink);
+        self
+    }
+
+    /// Raise an [`IdleAlarm`] when the agent stops performing during battle
+    /// or stops hearing from the server.
+    pub fn with_idle_alarm(mut self, alarm: IdleAlarm) -> Self {
+        self.idle_alarm = Some(alarm);
+        self
+    }
+
+    /// Send traffic whenever the connection has been idle for a while, so
+    /// the server or a proxy does not drop it. Disabled by default.
+    pub fn with_keepalive(mut self, keepalive: Keepalive) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Keep the last `capacity` raw frames sent and received in memory.
+    ///
+    /// They are dumped to the log when a connection fails, and can be
+    /// fetched at any time with [`AgentClient::recent_frames`]. Disabled
+    /// (zero) by default.
+    pub fn with_frame_log(mut self, capacity: usize) -> Self {
+        self.frame_log_capacity = capacity;
+        self
+    }
+
+    /// Post the [`MatchSummary`] to the webhook at `url` when the match ends.
+    #[cfg(feature = "webhook")]
+    pub fn with_match_webhook(mut self, url: impl Into<String>) -> Self {
+        self.match_webhook = Some(url.into());
+        self
+    }
+
+    /// Assume the server speaks protocol `version` instead of detecting it
+    /// from the first message that announces one.
+    pub fn with_protocol_version(mut self, version: impl Into<String>) -> Self {
+        self.protocol_version = Some(version.into());
+        self
+    }
+
+    /// Rename a field of incoming messages before they are parsed, to
+    /// support servers whose field names differ from this crate's.
+    pub fn with_field_alias(mut self, alias: FieldAlias) -> Self {
+        self.field_aliases.push(alias);
+        self
+    }
+
+    /// Retry failed connection attempts according to `policy`, both when
+    /// connecting and when reconnecting.
+    ///
+    /// Defaults to [`FixedDelay::default`].
+    pub fn with_retry_policy(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry_policy = Arc::new(policy);
+        self
+    }
+
+    /// Whether to reconnect when a connection breaks without the server
+    /// closing it. Enabled by default.
+    pub fn with_reconnect(mut self, reconnect: bool) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Limit the size of a whole message and of a single frame, in bytes.
+    ///
+    /// Fragmented messages are reassembled before the limit on the whole
+    /// message applies. Larger incoming messages break the connection and
+    /// are reported by [`AgentClient::take_oversized`]; larger outgoing ones
+    /// fail with [`MessageTooLarge`]. Defaults to 64 MiB and 16 MiB.
+    pub fn with_max_sizes(mut self, message: usize, frame: usize) -> Self {
+        self.max_message_size = message;
+        self.max_frame_size = frame;
+        self
+    }
+
+    /// Stop every background task of the client once `token` is cancelled,
+    /// e.g. to shut several agents down from one place.
+    ///
+    /// The client uses a child of `token`, so [`AgentClient::shutdown`] only
+    /// stops that client.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Inject latency, lost frames and disconnects between the client and
+    /// the socket, to exercise reconnection and timing logic locally.
+    #[cfg(feature = "fault-injection")]
+    pub fn with_fault_injection(mut self, faults: FaultInjection) -> Self {
+        self.fault_injection = Some(faults);
+        self
+    }
+
+    /// Rewrite the JSON of every outgoing message before it is sent, e.g.
+    /// to add fields a private server fork expects.
+    ///
+    /// Hooks run in registration order.
+    pub fn with_outgoing_hook(
+        mut self,
+        hook: impl Fn(&mut serde_json::Value) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.outgoing.push(Arc::new(hook));
+        self
+    }
+
+    /// Inspect the JSON of every incoming message before it is parsed, e.g.
+    /// to read fields a private server fork adds.
+    ///
+    /// Hooks run in registration order, after legacy messages have been
+    /// adapted (see [`ClientConfig::with_field_alias`]).
+    pub fn with_incoming_hook(
+        mut self,
+        hook: impl Fn(&serde_json::Value) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.incoming.push(Arc::new(hook));
+        self
+    }
+
+    fn url(&self, server: &str) -> String {
+        if self.query_params.is_empty() {
+            return server.to_string();
+        }
+        let query = self
+            .query_params
+            .iter()
+            .map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let separator = if server.contains('?') { '&' } else { '?' };
+        format!("{server}{separator}{query}")
+    }
+
+    fn websocket_config(&self) -> WebSocketConfig {
+        WebSocketConfig::default()
+            .max_message_size(Some(self.max_message_size))
+            .max_frame_size(Some(self.max_frame_size))
+    }
+
+    fn request(&self, server: &str) -> Result<Request, Box<dyn Error>> {
+        let mut request = self.url(server).into_client_request()?;
+        for (name, value) in &self.headers {
+            request.headers_mut().append(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        Ok(request)
+    }
+}
+
+fn percent_encode(raw: &str) -> String {
+    let mut encoded = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
 
-use super::model::{BuffKind, MoveDirection, RequestType, SkillKind, TurnDirection};
+/// One websocket connection: its write half, and whether its read loop is
+/// still alive. The write half is replaced when reconnecting.
+struct Link {
+    write: tokio::sync::Mutex<WriteConnection>,
+    healthy: AtomicBool,
+}
 
-const TRY_TIME: u32 = 3;
-const CONNECT_SLEEP_SEC: u64 = 3;
+/// State shared by the read loops of every connection.
+struct ReadShared {
+    inbox: Arc<Inbox>,
+    dedup: Mutex<Deduplicator>,
+    ticks: Mutex<TickFilter>,
+    unknown_sink: Option<UnboundedSender<String>>,
+    stats: Arc<Mutex<StatsRecorder>>,
+    replay: Arc<Mutex<ReplayHasher>>,
+    watchdog: Option<Arc<Mutex<Watchdog>>>,
+    frame_log: Arc<Mutex<FrameLog>>,
+    token: PlayerToken,
+    #[cfg(feature = "webhook")]
+    match_webhook: Option<String>,
+    summary: Arc<Mutex<Option<MatchSummary>>>,
+    compat: Arc<Mutex<Compat>>,
+    activity: Arc<Activity>,
+    hooks: Hooks,
+    server: String,
+    config: ClientConfig,
+    oversized: Arc<Mutex<Option<MessageTooLarge>>>,
+    tick: watch::Sender<Option<u64>>,
+    raw: broadcast::Sender<String>,
+    health: Health,
+    latency: Arc<Mutex<LatencyReport>>,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<Arc<FaultInjector>>,
+}
+
+impl ReadShared {
+    /// Make every lock usable again after a panic while one was held.
+    fn clear_poison(&self) {
+        self.inbox.clear_poison();
+        self.dedup.clear_poison();
+        self.ticks.clear_poison();
+        self.stats.clear_poison();
+        self.replay.clear_poison();
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.clear_poison();
+        }
+        self.frame_log.clear_poison();
+        self.summary.clear_poison();
+        self.compat.clear_poison();
+        self.oversized.clear_poison();
+        self.health.clear_poison();
+        self.latency.clear_poison();
+    }
+}
 
 /// Hold the connection to the server.
 ///
-/// Should be created with [`AgentClient::new`].
+/// Each connection is read by a background task, and incoming text frames
+/// are queued until taken with [`AgentClient::recv`] or
+/// [`AgentClient::try_recv`]. The queue is bounded, see
+/// [`ClientConfig::with_backpressure`].
+///
+/// Should be created with [`AgentClient::new`], or [`AgentClient::with_config`]
+/// when the handshake needs extra options.
 pub struct AgentClient {
-    // ws_stream: Connection,
-    write: WriteConnection,
-    read: ReadConnection,
-    token: String,
+    links: Arc<Vec<Arc<Link>>>,
+    inbox: Arc<Inbox>,
+    token: PlayerToken,
+    stats: Arc<Mutex<StatsRecorder>>,
+    replay: Arc<Mutex<ReplayHasher>>,
+    watchdog: Option<Arc<Mutex<Watchdog>>>,
+    frame_log: Arc<Mutex<FrameLog>>,
+    summary: Arc<Mutex<Option<MatchSummary>>>,
+    compat: Arc<Mutex<Compat>>,
+    activity: Arc<Activity>,
+    hooks: Hooks,
+    max_message_size: usize,
+    oversized: Arc<Mutex<Option<MessageTooLarge>>>,
+    tick: watch::Receiver<Option<u64>>,
+    raw: broadcast::Sender<String>,
+    cancellation: CancellationToken,
+    state: watch::Receiver<ConnectionState>,
+    report: InitReport,
+    latency: Arc<Mutex<LatencyReport>>,
+    /// When the last message was taken, or applied, until the next send.
+    last_taken: Option<Instant>,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<Arc<FaultInjector>>,
 }
 
 impl AgentClient {
-    async fn try_connect(server: &String, mut try_count: u32) -> Option<Connection> {
-        while try_count > 0 {
+    async fn try_connect(server: &str, config: &ClientConfig) -> Option<Connection> {
+        let mut failures = 0;
+        loop {
             debug!("Trying to connect to {server}");
-            if let Ok((ws_stream, _)) = connect_async(server).await {
+            let request = match config.request(server) {
+                Ok(request) => request,
+                Err(err) => {
+                    error!("Invalid handshake request for {server}: {}", err);
+                    return None;
+                }
+            };
+            let websocket_config = Some(config.websocket_config());
+            if let Ok((ws_stream, _)) =
+                connect_async_with_config(request, websocket_config, false).await
+            {
                 return Some(ws_stream);
             }
-            debug!("Connect failed! Sleeping...");
-            sleep(Duration::from_secs(CONNECT_SLEEP_SEC)).await;
-            try_count -= 1;
+            failures += 1;
+            let Some(delay) = config.retry_policy.next_delay(failures) else {
+                debug!("Connection failed too many times!");
+                return None;
+            };
+            debug!("Connect failed! Sleeping {:?}...", delay);
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = config.cancellation.cancelled() => return None,
+            }
         }
-        debug!("Connection failed too many times!");
-        None
     }
 
     /// Create a new [`AgentClient`] connecting to `server` for agent with `token`.
     ///
-    /// If connect fails, it will sleep and then retry for some times before panic.
+    /// If connect fails, it will sleep and then retry for some times before
+    /// panic, see [`ClientConfig::with_retry_policy`]. A connection that
+    /// breaks later is reconnected the same way.
     ///
     /// # Panics
     ///
     /// Panics if connecting to server always fail.
-    pub async fn new(server: String, token: String) -> AgentClient {
-        info!("Connecting to {server} with token {token}");
-        let ws_stream = Self::try_connect(&server, TRY_TIME)
-            .await
-            .unwrap_or_else(|| {
-                error!("Cannot connect to {server}!");
+    pub async fn new(server: String, token: impl Into<PlayerToken>) -> AgentClient {
+        Self::with_config(server, token, ClientConfig::default()).await
+    }
+
+    /// Create a new [`AgentClient`] like [`AgentClient::new`], applying the
+    /// extra handshake options in `config`.
+    ///
+    /// If `config`'s cancellation token is cancelled while connecting, the
+    /// client is returned already closed: [`AgentClient::recv`] returns
+    /// [`None`] and every send fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if connecting to server always fail, or if a header in `config`
+    /// is not a valid HTTP header, see [`AgentClient::try_with_config`].
+    pub async fn with_config(
+        server: String,
+        token: impl Into<PlayerToken>,
+        config: ClientConfig,
+    ) -> AgentClient {
+        match Self::try_with_config(server, token, config).await {
+            Ok(client) => client,
+            Err(err) => {
+                error!("{}", err);
                 panic!("Connection Error!");
+            }
+        }
+    }
+
+    /// Create a new [`AgentClient`] like [`AgentClient::with_config`], but
+    /// return a [`ConnectError`] where it panics.
+    pub async fn try_with_config(
+        server: String,
+        token: impl Into<PlayerToken>,
+        mut config: ClientConfig,
+    ) -> Result<AgentClient, ConnectError> {
+        if let Err(err) = config.request(&server) {
+            return Err(ConnectError::InvalidRequest(err.to_string()));
+        }
+        let token = token.into();
+        info!("Connecting to {server} with token {token}");
+        config.cancellation = config.cancellation.child_token();
+        let mut report = InitReport::default();
+        #[cfg(feature = "webhook")]
+        let match_webhook = match config.match_webhook.clone() {
+            None => {
+                report.add("match webhook", SubsystemState::Off);
+                None
+            }
+            Some(url) => match reqwest::Url::parse(&url) {
+                Ok(_) => {
+                    report.add("match webhook", SubsystemState::Active);
+                    Some(url)
+                }
+                Err(err) => {
+                    let reason = format!("invalid url {url}: {err}");
+                    report.add("match webhook", SubsystemState::Degraded(reason));
+                    None
+                }
+            },
+        };
+        let link_count = if config.redundant { 2 } else { 1 };
+        let inbox = Arc::new(Inbox::new(config.inbox_capacity, config.backpressure));
+        let stats = Arc::new(Mutex::new(StatsRecorder::default()));
+        let replay = Arc::new(Mutex::new(ReplayHasher::default()));
+        let frame_log = Arc::new(Mutex::new(FrameLog::new(config.frame_log_capacity)));
+        let summary = Arc::new(Mutex::new(None));
+        let compat = Arc::new(Mutex::new(Compat::new(
+            config.protocol_version.clone(),
+            config.field_aliases.clone(),
+        )));
+        let watchdog = config.idle_alarm.clone().map(|alarm| {
+            let watchdog = Arc::new(Mutex::new(Watchdog::new(alarm)));
+            let period = watchdog.lock().unwrap().check_period();
+            tokio::spawn(Watchdog::run(
+                Arc::downgrade(&watchdog),
+                period,
+                config.cancellation.clone(),
+            ));
+            watchdog
+        });
+        let activity = Arc::new(Activity::new());
+        let oversized = Arc::new(Mutex::new(None));
+        let (tick_sender, tick) = watch::channel(None);
+        let (raw, _) = broadcast::channel(RAW_TAP_CAPACITY);
+        let (health, state) = Health::new();
+        let latency = Arc::new(Mutex::new(LatencyReport::default()));
+        #[cfg(feature = "fault-injection")]
+        let faults = config
+            .fault_injection
+            .map(|faults| Arc::new(FaultInjector::new(faults)));
+        let shared = Arc::new(ReadShared {
+            inbox: inbox.clone(),
+            dedup: Mutex::new(Deduplicator::new(link_count)),
+            ticks: Mutex::new(TickFilter::default()),
+            unknown_sink: config.unknown_sink.clone(),
+            stats: stats.clone(),
+            replay: replay.clone(),
+            watchdog: watchdog.clone(),
+            frame_log: frame_log.clone(),
+            token: token.clone(),
+            #[cfg(feature = "webhook")]
+            match_webhook,
+            summary: summary.clone(),
+            compat: compat.clone(),
+            activity: activity.clone(),
+            hooks: config.hooks.clone(),
+            server: server.clone(),
+            config: config.clone(),
+            oversized: oversized.clone(),
+            tick: tick_sender,
+            raw: raw.clone(),
+            health,
+            latency: latency.clone(),
+            #[cfg(feature = "fault-injection")]
+            faults: faults.clone(),
+        });
+        let mut links = Vec::with_capacity(link_count);
+        for index in 0..link_count {
+            let Some(ws_stream) = Self::try_connect(&server, &config).await else {
+                if config.cancellation.is_cancelled() {
+                    info!("Connecting to {server} cancelled");
+                    break;
+                }
+                if index > 0 {
+                    warn!("Cannot open redundant connection to {server}, using a single one");
+                    break;
+                }
+                config.cancellation.cancel();
+                return Err(ConnectError::Unreachable(server));
+            };
+            info!("Connected to {server} successfully!");
+            let (write, read) = ws_stream.split();
+            let link = Arc::new(Link {
+                write: tokio::sync::Mutex::new(write),
+                healthy: AtomicBool::new(true),
             });
-        info!("Connected to {server} successfully!");
-        let (write, read) = ws_stream.split();
-        AgentClient { write, read, token }
+            inbox.open();
+            shared.health.set(index, LinkState::Up);
+            tokio::spawn(read_loop(
+                index,
+                read,
+                shared.clone(),
+                Arc::downgrade(&link),
+            ));
+            links.push(link);
+        }
+        report.add(
+            "redundancy",
+            match (config.redundant, links.len()) {
+                (false, _) => SubsystemState::Off,
+                (true, 2) => SubsystemState::Active,
+                (true, _) => SubsystemState::Degraded("second connection failed".to_string()),
+            },
+        );
+        report.add("keepalive", active_if(config.keepalive.is_some()));
+        report.add("idle alarm", active_if(watchdog.is_some()));
+        report.add("frame log", active_if(config.frame_log_capacity > 0));
+        report.add("unknown sink", active_if(config.unknown_sink.is_some()));
+        #[cfg(feature = "fault-injection")]
+        report.add("fault injection", active_if(faults.is_some()));
+        report.log();
+        let links = Arc::new(links);
+        if let Some(keepalive) = config.keepalive {
+            tokio::spawn(keepalive::run(
+                keepalive,
+                Arc::downgrade(&links),
+                activity.clone(),
+                stats.clone(),
+                config.hooks.clone(),
+                token.clone(),
+                config.cancellation.clone(),
+            ));
+        }
+        Ok(AgentClient {
+            links,
+            inbox,
+            token,
+            stats,
+            replay,
+            watchdog,
+            frame_log,
+            summary,
+            compat,
+            activity,
+            hooks: config.hooks,
+            max_message_size: config.max_message_size,
+            oversized,
+            tick,
+            raw,
+            cancellation: config.cancellation,
+            state,
+            report,
+            latency,
+            last_taken: None,
+            #[cfg(feature = "fault-injection")]
+            faults,
+        })
+    }
+
+    /// The token this client connected with.
+    pub fn token(&self) -> &PlayerToken {
+        &self.token
+    }
+
+    /// Stop every background task of this client and close its connections.
+    ///
+    /// Messages already queued can still be taken, then [`AgentClient::recv`]
+    /// returns [`None`]. See [`ClientConfig::with_cancellation`] to stop
+    /// several clients at once.
+    pub fn shutdown(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Cancelled once this client shuts down, e.g. to stop tasks driving it.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Which optional subsystems started when connecting. A subsystem that
+    /// failed to start is reported as degraded instead of aborting.
+    pub fn init_report(&self) -> &InitReport {
+        &self.report
+    }
+
+    /// The current state of the connection to the server.
+    pub fn connection_state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// Watch the state of the connection to the server, e.g. to stop
+    /// issuing moves while reconnecting.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(client: thuai_8_agent_rust::agent::connection::AgentClient) {
+    /// use thuai_8_agent_rust::agent::connection::ConnectionState;
+    ///
+    /// let mut state = client.watch_connection_state();
+    /// while state.changed().await.is_ok() {
+    ///     if *state.borrow() == ConnectionState::Reconnecting {
+    ///         println!("Connection lost, waiting");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn watch_connection_state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.clone()
+    }
+
+    /// A snapshot of the traffic statistics of this client.
+    pub fn stats(&self) -> ConnectionStats {
+        self.stats.lock().unwrap().snapshot()
+    }
+
+    /// Subscribe to every text frame received, on every connection, before
+    /// deduplication or any other processing.
+    ///
+    /// Meant for external tools such as loggers or visualizers; taking
+    /// frames from the receiver does not affect [`AgentClient::recv`]. A
+    /// subscriber lagging more than 256 frames behind misses the oldest.
+    pub fn subscribe_raw(&self) -> broadcast::Receiver<String> {
+        self.raw.subscribe()
+    }
+
+    /// The latest tick announced by the server, if any yet. Starts over with
+    /// a new game and after a reconnect.
+    pub fn current_tick(&self) -> Option<u64> {
+        *self.tick.borrow()
+    }
+
+    /// Resolves with the current tick once the game reaches `tick`, or with
+    /// [`None`] if every connection closed before.
+    ///
+    /// The future does not borrow the client, so it can be spawned or
+    /// raced against other work.
+    pub fn at_tick(&self, tick: u64) -> impl Future<Output = Option<u64>> + Send + 'static {
+        let mut receiver = self.tick.clone();
+        async move {
+            let reached = receiver
+                .wait_for(|current| current.is_some_and(|current| current >= tick))
+                .await
+                .ok()?;
+            *reached
+        }
+    }
+
+    /// Resolves like [`AgentClient::at_tick`], `ticks` ticks after the
+    /// current one (or after the first one, if none was announced yet).
+    pub fn after_ticks(&self, ticks: u64) -> impl Future<Output = Option<u64>> + Send + 'static {
+        let mut receiver = self.tick.clone();
+        async move {
+            let target = (*receiver.wait_for(Option::is_some).await.ok()?)? + ticks;
+            let reached = receiver
+                .wait_for(|current| current.is_some_and(|current| current >= target))
+                .await
+                .ok()?;
+            *reached
+        }
+    }
+
+    /// The last completed tick and the rolling replay hash after it.
+    ///
+    /// The hash covers every state message received and every perform sent
+    /// up to that tick, so two runs of the same strategy can be compared
+    /// tick by tick. It is also logged at DEBUG level after each tick.
+    pub fn replay_hash(&self) -> Option<(u64, u64)> {
+        self.replay.lock().unwrap().last()
+    }
+
+    /// Summary of the match, available once the server reported the `END`
+    /// stage.
+    pub fn match_summary(&self) -> Option<MatchSummary> {
+        self.summary.lock().unwrap().clone()
+    }
+
+    /// The protocol version the server announced, or the one configured
+    /// with [`ClientConfig::with_protocol_version`].
+    pub fn protocol_version(&self) -> Option<String> {
+        self.compat.lock().unwrap().version().map(str::to_string)
+    }
+
+    /// The raw frames kept by the frame log, oldest first.
+    ///
+    /// Empty unless enabled with [`ClientConfig::with_frame_log`].
+    pub fn recent_frames(&self) -> Vec<RecordedFrame> {
+        self.frame_log.lock().unwrap().frames()
+    }
+
+    /// Write the raw frames kept by the frame log to the log at ERROR level.
+    pub fn dump_recent_frames(&self) {
+        self.frame_log
+            .lock()
+            .unwrap()
+            .dump("Frame log dump requested");
+    }
+
+    /// Number of connections whose read loop is still running.
+    pub fn healthy_links(&self) -> usize {
+        self.links
+            .iter()
+            .filter(|link| link.healthy.load(Ordering::Relaxed))
+            .count()
+    }
+
+    /// The last incoming message that exceeded the size limit since the
+    /// last call, see [`ClientConfig::with_max_sizes`].
+    pub fn take_oversized(&self) -> Option<MessageTooLarge> {
+        self.oversized.lock().unwrap().take()
+    }
+
+    /// Wait for the next message from the server.
+    ///
+    /// Returns [`None`] once every connection has been closed.
+    pub async fn recv(&mut self) -> Option<String> {
+        let (frame, queued_at) = self.inbox.pop().await?;
+        self.taken(queued_at);
+        Some(frame)
+    }
+
+    /// Take the next queued message from the server without waiting.
+    pub fn try_recv(&mut self) -> Option<String> {
+        let (frame, queued_at) = self.inbox.try_pop()?;
+        self.taken(queued_at);
+        Some(frame)
+    }
+
+    fn taken(&mut self, queued_at: Instant) {
+        let now = Instant::now();
+        self.latency
+            .lock()
+            .unwrap()
+            .record(LatencyStage::Queue, now - queued_at);
+        self.last_taken = Some(now);
+    }
+
+    /// Record the message last taken as applied to the cached state, see
+    /// [`LatencyStage::Apply`].
+    pub fn record_applied(&mut self) {
+        if let Some(taken) = self.last_taken {
+            let now = Instant::now();
+            self.latency
+                .lock()
+                .unwrap()
+                .record(LatencyStage::Apply, now - taken);
+            self.last_taken = Some(now);
+        }
+    }
+
+    /// Per-stage timings from messages received to commands flushed, to
+    /// see where the time of each tick goes. Also part of the
+    /// [`MatchSummary`].
+    pub fn latency_report(&self) -> LatencyReport {
+        self.latency.lock().unwrap().clone()
     }
 
-    async fn on_message(&self, msg: String) -> Option<AgentMessage> {
-        debug!("Received Message: {}", msg);
-        unimplemented!()
+    /// Validate `msg` with [`PerformMessage::validate`], then send it.
+    pub async fn send_perform(&mut self, msg: PerformMessage) -> Result<(), Box<dyn Error>> {
+        msg.validate()?;
+        self.send(msg).await
     }
 
+    /// Send `msg` over the first healthy connection, falling back to the
+    /// next one if sending fails.
     pub async fn send(&mut self, msg: impl Serialize) -> Result<(), Box<dyn Error>> {
-        let to_send = serde_json::to_string(&msg)?;
+        let start = Instant::now();
+        if let Some(taken) = self.last_taken.take() {
+            self.latency
+                .lock()
+                .unwrap()
+                .record(LatencyStage::Decide, start - taken);
+        }
+        let mut value = serde_json::to_value(&msg)?;
+        self.hooks.rewrite(&mut value);
+        let message_type = value
+            .get("messageType")
+            .and_then(|kind| kind.as_str())
+            .unwrap_or("<missing>")
+            .to_string();
+        let to_send = value.to_string();
+        if to_send.len() > self.max_message_size {
+            return Err(MessageTooLarge {
+                size: to_send.len(),
+                max_size: self.max_message_size,
+            }
+            .into());
+        }
+        self.latency
+            .lock()
+            .unwrap()
+            .record(LatencyStage::Serialize, start.elapsed());
+        #[cfg(feature = "fault-injection")]
+        if let Some(faults) = &self.faults {
+            let Some(delay) = faults.outbound() else {
+                debug!("Injected loss of message: {}", to_send);
+                return Ok(());
+            };
+            sleep(delay).await;
+        }
         debug!("Sending Message: {}", to_send);
-        self.write.send(to_send.into()).await?;
-        Ok(())
+        self.frame_log
+            .lock()
+            .unwrap()
+            .record(FrameDirection::Sent, &to_send);
+        let write_start = Instant::now();
+        match write_first_healthy(&self.links, to_send.clone().into()).await {
+            Ok(()) => {
+                self.latency
+                    .lock()
+                    .unwrap()
+                    .record(LatencyStage::Flush, write_start.elapsed());
+                self.activity.touch();
+                self.stats
+                    .lock()
+                    .unwrap()
+                    .record_sent(&message_type, to_send.len());
+                if message_type.starts_with("PERFORM_") {
+                    self.replay.lock().unwrap().record_action(&to_send);
+                    if let Some(watchdog) = &self.watchdog {
+                        watchdog.lock().unwrap().on_perform();
+                    }
+                }
+                Ok(())
+            }
+            Err(err) => {
+                self.frame_log
+                    .lock()
+                    .unwrap()
+                    .dump("Sending failed on every connection");
+                match err {
+                    Some(err) => Err(err.into()),
+                    None => Err("No healthy connection to the server".into()),
+                }
+            }
+        }
+    }
+}
+
+/// Write `message` to the first healthy link, marking links that fail as
+/// unhealthy. Returns the last error, if any, when no link accepted it.
+async fn write_first_healthy(
+    links: &[Arc<Link>],
+    message: Message,
+) -> Result<(), Option<tokio_tungstenite::tungstenite::Error>> {
+    let mut last_err = None;
+    for (index, link) in links.iter().enumerate() {
+        if !link.healthy.load(Ordering::Relaxed) {
+            continue;
+        }
+        match link.write.lock().await.send(message.clone()).await {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                warn!("Sending over connection {index} failed: {}", err);
+                link.healthy.store(false, Ordering::Relaxed);
+                last_err = Some(err);
+            }
+        }
+    }
+    Err(last_err)
+}
+
+/// Extract the `messageType` of a parsed frame, if it has one.
+fn message_type(value: &serde_json::Value) -> Option<String> {
+    value.get("messageType")?.as_str().map(str::to_string)
+}
+
+/// Key under which a state message may be coalesced with older ones.
+///
+/// Player info is keyed by the tokens it describes, so the info of one
+/// player never replaces the info of another.
+fn coalesce_key(kind: &str, value: &serde_json::Value) -> Option<String> {
+    match kind {
+        "ENVIRONMENT_INFO" | "GAME_STATISTICS" | "AVAILABLE_BUFFS" => Some(kind.to_string()),
+        "PLAYERS_INFO" => {
+            let tokens = value
+                .get("players")?
+                .as_array()?
+                .iter()
+                .filter_map(|player| player.get("token")?.as_str())
+                .collect::<Vec<_>>()
+                .join(",");
+            Some(format!("{kind}:{tokens}"))
+        }
+        _ => None,
+    }
+}
+
+fn active_if(configured: bool) -> SubsystemState {
+    if configured {
+        SubsystemState::Active
+    } else {
+        SubsystemState::Off
+    }
+}
+
+/// Record the match summary the first time the `END` stage is seen, and
+/// post it to the webhook if one is configured.
+fn on_match_end(shared: &ReadShared, statistics: &serde_json::Value) {
+    let mut summary = shared.summary.lock().unwrap();
+    if summary.is_some() {
+        return;
+    }
+    let errors = shared
+        .stats
+        .lock()
+        .unwrap()
+        .snapshot()
+        .messages_received()
+        .get("ERROR")
+        .copied()
+        .unwrap_or_default();
+    let latency = shared.latency.lock().unwrap().clone();
+    let new_summary =
+        MatchSummary::from_statistics(&shared.token, statistics, errors).with_latency(latency);
+    info!("{}", new_summary);
+    #[cfg(feature = "webhook")]
+    if let Some(url) = shared.match_webhook.clone() {
+        let to_post = new_summary.clone();
+        tokio::spawn(async move {
+            if let Err(err) = super::report::post_summary(&url, &to_post).await {
+                error!("Posting match summary to webhook failed: {}", err);
+            }
+        });
+    }
+    *summary = Some(new_summary);
+}
+
+/// Handle one text frame received on connection `index`.
+fn on_frame(shared: &ReadShared, index: usize, text: String) {
+    shared
+        .frame_log
+        .lock()
+        .unwrap()
+        .record(FrameDirection::Received, &text);
+    if shared.raw.receiver_count() > 0 {
+        // Failing only means every subscriber left in the meantime.
+        let _ = shared.raw.send(text.clone());
+    }
+    if !shared.dedup.lock().unwrap().accept(index, &text) {
+        return;
+    }
+    let mut value: Option<serde_json::Value> = serde_json::from_str(&text).ok();
+    let adapted = value
+        .as_mut()
+        .is_some_and(|value| shared.compat.lock().unwrap().adapt(value));
+    let text = match &value {
+        Some(value) if adapted => value.to_string(),
+        _ => text,
+    };
+    if let Some(value) = &value {
+        shared.hooks.inspect(value);
+    }
+    let kind = value.as_ref().and_then(message_type);
+    shared
+        .stats
+        .lock()
+        .unwrap()
+        .record_received(kind.as_deref().unwrap_or("<missing>"), text.len());
+    if let Some(watchdog) = &shared.watchdog {
+        watchdog.lock().unwrap().on_received();
+    }
+    match kind {
+        Some(kind) if KNOWN_MESSAGE_TYPES.contains(&kind.as_str()) => {
+            let key = value.as_ref().and_then(|value| coalesce_key(&kind, value));
+            if let Some(key) = &key {
+                let own_tick = value.as_ref().and_then(staleness::own_tick);
+                let stage = value.as_ref().and_then(staleness::stage);
+                let accepted = shared.ticks.lock().unwrap().accept(key, own_tick, stage);
+                match accepted {
+                    Ok(Accepted::Fresh) => {}
+                    Ok(Accepted::Restarted) => {
+                        debug!("Ticks started over with {kind}");
+                        shared.tick.send_replace(None);
+                    }
+                    Err((tick, delivered)) => {
+                        debug!(
+                            "Discarding stale {kind} of tick {tick}, already have tick {delivered}"
+                        );
+                        shared.stats.lock().unwrap().record_stale();
+                        return;
+                    }
+                }
+            }
+            let tick = value
+                .as_ref()
+                .filter(|_| kind == "GAME_STATISTICS")
+                .and_then(|value| value.get("ticks")?.as_u64());
+            shared.replay.lock().unwrap().record_state(tick, &text);
+            if let Some(tick) = tick {
+                shared.tick.send_if_modified(|current| {
+                    let newer = current.is_none_or(|current| tick > current);
+                    if newer {
+                        *current = Some(tick);
+                    }
+                    newer
+                });
+            }
+            if let Some(value) = &value
+                && kind == "GAME_STATISTICS"
+                && value.get("currentStage").and_then(|stage| stage.as_str()) == Some("END")
+            {
+                on_match_end(shared, value);
+            }
+            if let (Some(watchdog), Some(tick)) = (&shared.watchdog, tick) {
+                let battle = value
+                    .as_ref()
+                    .and_then(|value| value.get("currentStage")?.as_str())
+                    == Some("BATTLE");
+                watchdog.lock().unwrap().on_statistics(battle, tick);
+            }
+            match shared.inbox.push(key, text) {
+                Pushed::Queued => {}
+                Pushed::Coalesced => shared.stats.lock().unwrap().record_coalesced(),
+                Pushed::DroppedOldest => {
+                    throttled("inbox full", |suppressed| {
+                        warn!(
+                            "Message queue full, dropped the oldest message \
+                            ({suppressed} similar lines suppressed)"
+                        )
+                    });
+                    shared.stats.lock().unwrap().record_dropped();
+                }
+            }
+        }
+        kind => {
+            let kind = kind.as_deref().unwrap_or("<missing>");
+            throttled(&format!("unknown type {kind}"), |suppressed| {
+                warn!(
+                    "Received message of unknown type {kind} \
+                    ({suppressed} similar lines suppressed)"
+                )
+            });
+            if let Some(sink) = &shared.unknown_sink {
+                // The sink being dropped only means nobody listens anymore.
+                let _ = sink.send(text);
+            }
+        }
+    }
+}
+
+/// Read frames until the server closes the connection, returning whether
+/// it did so cleanly.
+async fn read_frames(index: usize, read: &mut ReadConnection, shared: &ReadShared) -> bool {
+    let mut panics = 0;
+    while let Some(frame) = read.next().await {
+        shared.activity.touch();
+        match frame {
+            Ok(Message::Text(text)) => {
+                #[cfg(feature = "fault-injection")]
+                if let Some(faults) = &shared.faults {
+                    match faults.inbound() {
+                        Fault::Deliver(delay) => sleep(delay).await,
+                        Fault::Drop => {
+                            debug!("Injected loss of message: {}", text);
+                            continue;
+                        }
+                        Fault::Disconnect => {
+                            warn!("Injected disconnect on connection {index}");
+                            return false;
+                        }
+                    }
+                }
+                debug!("Received Message: {}", text);
+                let received = Instant::now();
+                let handled = panic::catch_unwind(AssertUnwindSafe(|| {
+                    on_frame(shared, index, text.to_string())
+                }));
+                shared
+                    .latency
+                    .lock()
+                    .unwrap()
+                    .record(LatencyStage::Handle, received.elapsed());
+                if handled.is_ok() {
+                    panics = 0;
+                    continue;
+                }
+                // The panic message itself was already printed by the hook.
+                let payload: String = text.chars().take(PANIC_PAYLOAD_LOG_LEN).collect();
+                error!("Handling a message on connection {index} panicked: {payload}");
+                shared.clear_poison();
+                panics += 1;
+                if panics >= MAX_HANDLER_PANICS {
+                    error!("Connection {index} keeps panicking, dropping it");
+                    return false;
+                }
+            }
+            Ok(Message::Close(_)) => return true,
+            Ok(_) => {}
+            Err(tokio_tungstenite::tungstenite::Error::Capacity(
+                CapacityError::MessageTooLong { size, max_size },
+            )) => {
+                let err = MessageTooLarge { size, max_size };
+                error!("Reading from connection {index} failed: {}", err);
+                *shared.oversized.lock().unwrap() = Some(err);
+                return false;
+            }
+            Err(err) => {
+                error!("Reading from connection {index} failed: {}", err);
+                shared
+                    .frame_log
+                    .lock()
+                    .unwrap()
+                    .dump(&format!("Connection {index} failed"));
+                return false;
+            }
+        }
+    }
+    false
+}
+
+async fn read_loop(
+    index: usize,
+    mut read: ReadConnection,
+    shared: Arc<ReadShared>,
+    link: Weak<Link>,
+) {
+    let cancellation = &shared.config.cancellation;
+    loop {
+        let closed = tokio::select! {
+            closed = read_frames(index, &mut read, &shared) => closed,
+            _ = cancellation.cancelled() => true,
+        };
+        let Some(link) = link.upgrade() else {
+            break;
+        };
+        link.healthy.store(false, Ordering::Relaxed);
+        if cancellation.is_cancelled() {
+            debug!("Connection {index} shutting down");
+            let _ = link.write.lock().await.send(Message::Close(None)).await;
+            break;
+        }
+        if closed || !shared.config.reconnect {
+            break;
+        }
+        warn!("Connection {index} broke, reconnecting");
+        shared.health.set(index, LinkState::Reconnecting);
+        // Release the old socket first, in case only reading from it failed.
+        let _ = timeout(CLOSE_TIMEOUT, link.write.lock().await.close()).await;
+        let Some(ws_stream) = AgentClient::try_connect(&shared.server, &shared.config).await else {
+            if !cancellation.is_cancelled() {
+                error!("Cannot reconnect connection {index}");
+            }
+            break;
+        };
+        let (write, new_read) = ws_stream.split();
+        *link.write.lock().await = write;
+        link.healthy.store(true, Ordering::Relaxed);
+        shared.health.set(index, LinkState::Up);
+        read = new_read;
+        // The server may have restarted, so no tick seen before holds.
+        shared.ticks.lock().unwrap().reset();
+        shared.tick.send_replace(None);
+        shared.stats.lock().unwrap().record_reconnect();
+        info!("Reconnected connection {index} to {}", shared.server);
     }
+    shared.health.set(index, LinkState::Down);
+    shared.inbox.close();
+    warn!("Connection {index} to the server closed");
 }
 
-pub trait ConnectionAPI {
+/// Requests that only read the game state. Spectators are limited to these.
+pub trait QueryAPI {
+    fn send_get_player_info(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
+    fn send_get_environment_info(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
+    fn send_get_game_statistics(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
+    fn send_get_available_buffs(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
+}
+
+/// Requests that act in the game, on top of the read-only [`QueryAPI`].
+pub trait ConnectionAPI: QueryAPI {
     fn send_perform_turn(
         &mut self,
         direction: TurnDirection,
@@ -101,78 +1344,252 @@ pub trait ConnectionAPI {
         &mut self,
         buff_name: BuffKind,
     ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
-    fn send_get_player_info(
-        &mut self,
-    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
-    fn send_get_environment_info(
-        &mut self,
-    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
-    fn send_get_game_statistics(
-        &mut self,
-    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
-    fn send_get_available_buffs(
-        &mut self,
-    ) -> impl std::future::Future<Output = Result<(), Box<dyn Error>>> + Send;
-}
-
-// TODO: definition of messages
-
-enum AgentMessage {}
-
-#[derive(Debug, Serialize)]
-struct PlayerPerform {
-    #[serde(rename = "messageType")]
-    message_type: &'static str,
-    perform: PerformMessage,
 }
 
-#[derive(Debug, Serialize)]
+/// A message to the server, tagged with its `messageType`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(tag = "messageType")]
 pub enum PerformMessage {
     #[serde(rename = "PERFORM_MOVE")]
     PerformMove {
-        token: String,
+        token: PlayerToken,
         direction: MoveDirection,
         distance: f64,
     },
     #[serde(rename = "PERFORM_TURN")]
     PerformTurn {
-        token: String,
+        token: PlayerToken,
         direction: TurnDirection,
-        angle: u32, // TODO: confirm, is it really integer?
+        /// Whole degrees, at most a full turn.
+        angle: u32,
     },
     #[serde(rename = "PERFORM_ATTACK")]
-    PerformAttack { token: String },
+    PerformAttack { token: PlayerToken },
     #[serde(rename = "PERFORM_SKILL")]
     PerformSkill {
-        token: String,
+        token: PlayerToken,
         #[serde(rename = "skillName")]
         skill_name: SkillKind,
     },
     #[serde(rename = "PERFORM_SELECT")]
     PerformSelect {
-        token: String,
+        token: PlayerToken,
         #[serde(rename = "buffName")]
         buff_name: BuffKind,
     },
     #[serde(rename = "GET_PLAYER_INFO")]
-    GetPlayerInfo { token: String, request: RequestType },
+    GetPlayerInfo {
+        token: PlayerToken,
+        request: RequestType,
+    },
     #[serde(rename = "GET_ENVIRONMENT_INFO")]
-    GetEnvironmentInfo { token: String },
+    GetEnvironmentInfo { token: PlayerToken },
     #[serde(rename = "GET_GAME_STATISTICS")]
-    GetGameStatistics { token: String },
+    GetGameStatistics { token: PlayerToken },
     #[serde(rename = "GET_AVAILABLE_BUFFS")]
-    GetAvailableBuffs { token: String },
+    GetAvailableBuffs { token: PlayerToken },
+}
+
+/// Why [`AgentClient::try_with_config`] could not connect.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectError {
+    /// No handshake request can be built from the server address and the
+    /// [`ClientConfig`], for the reason given.
+    InvalidRequest(String),
+    /// Every attempt the retry policy allowed to reach the server failed.
+    Unreachable(String),
+}
+
+impl Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectError::InvalidRequest(reason) => {
+                write!(f, "invalid handshake request: {}", reason)
+            }
+            ConnectError::Unreachable(server) => write!(f, "cannot connect to {}", server),
+        }
+    }
+}
+
+impl Error for ConnectError {}
+
+/// A message exceeding the size limit set with
+/// [`ClientConfig::with_max_sizes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageTooLarge {
+    pub size: usize,
+    pub max_size: usize,
+}
+
+impl Display for MessageTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "message of {} bytes is larger than the limit of {} bytes",
+            self.size, self.max_size
+        )
+    }
+}
+
+impl Error for MessageTooLarge {}
+
+/// Largest turn accepted by [`PerformMessage::validate`], in degrees.
+const MAX_TURN_ANGLE: u32 = 360;
+
+/// Why a [`PerformMessage`] was rejected before being sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidPerform {
+    /// The token is empty.
+    EmptyToken,
+    /// The move distance is NaN, infinite or negative.
+    InvalidDistance(f64),
+    /// The turn angle is more than a full turn.
+    InvalidAngle(u32),
+}
+
+impl Display for InvalidPerform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidPerform::EmptyToken => write!(f, "token is empty"),
+            InvalidPerform::InvalidDistance(distance) => {
+                write!(f, "invalid move distance {}", distance)
+            }
+            InvalidPerform::InvalidAngle(angle) => write!(
+                f,
+                "invalid turn angle {} (at most {})",
+                angle, MAX_TURN_ANGLE
+            ),
+        }
+    }
+}
+
+impl Error for InvalidPerform {}
+
+impl PerformMessage {
+    /// The `messageType` the message is serialized with.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            PerformMessage::PerformMove { .. } => "PERFORM_MOVE",
+            PerformMessage::PerformTurn { .. } => "PERFORM_TURN",
+            PerformMessage::PerformAttack { .. } => "PERFORM_ATTACK",
+            PerformMessage::PerformSkill { .. } => "PERFORM_SKILL",
+            PerformMessage::PerformSelect { .. } => "PERFORM_SELECT",
+            PerformMessage::GetPlayerInfo { .. } => "GET_PLAYER_INFO",
+            PerformMessage::GetEnvironmentInfo { .. } => "GET_ENVIRONMENT_INFO",
+            PerformMessage::GetGameStatistics { .. } => "GET_GAME_STATISTICS",
+            PerformMessage::GetAvailableBuffs { .. } => "GET_AVAILABLE_BUFFS",
+        }
+    }
+
+    /// The token the message is sent with.
+    pub fn token(&self) -> &PlayerToken {
+        match self {
+            PerformMessage::PerformMove { token, .. }
+            | PerformMessage::PerformTurn { token, .. }
+            | PerformMessage::PerformAttack { token }
+            | PerformMessage::PerformSkill { token, .. }
+            | PerformMessage::PerformSelect { token, .. }
+            | PerformMessage::GetPlayerInfo { token, .. }
+            | PerformMessage::GetEnvironmentInfo { token }
+            | PerformMessage::GetGameStatistics { token }
+            | PerformMessage::GetAvailableBuffs { token } => token,
+        }
+    }
+
+    /// Check the parameters before sending, so malformed commands fail
+    /// locally instead of being silently ignored by the server.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::connection::{InvalidPerform, PerformMessage};
+    /// use thuai_8_agent_rust::agent::model::MoveDirection;
+    ///
+    /// let msg = PerformMessage::PerformMove {
+    ///     token: "1919810".into(),
+    ///     direction: MoveDirection::Forth,
+    ///     distance: f64::NAN,
+    /// };
+    ///
+    /// assert!(matches!(msg.validate(), Err(InvalidPerform::InvalidDistance(_))));
+    /// ```
+    pub fn validate(&self) -> Result<(), InvalidPerform> {
+        if self.token().is_empty() {
+            return Err(InvalidPerform::EmptyToken);
+        }
+        match self {
+            PerformMessage::PerformMove { distance, .. }
+                if !distance.is_finite() || *distance < 0.0 =>
+            {
+                Err(InvalidPerform::InvalidDistance(*distance))
+            }
+            PerformMessage::PerformTurn { angle, .. } if *angle > MAX_TURN_ANGLE => {
+                Err(InvalidPerform::InvalidAngle(*angle))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn handshake_query_params() {
+        let config = ClientConfig::new()
+            .with_query_param("token", "19 19/810")
+            .with_query_param("mode", "play");
+
+        assert_eq!(
+            config.url("ws://127.0.0.1:14514"),
+            "ws://127.0.0.1:14514?token=19%2019%2F810&mode=play"
+        );
+        assert_eq!(
+            config.url("ws://127.0.0.1:14514/?a=b"),
+            "ws://127.0.0.1:14514/?a=b&token=19%2019%2F810&mode=play"
+        );
+    }
+
+    #[test]
+    fn message_type_of_frame() {
+        let frame = serde_json::json!({"messageType": "GAME_STATISTICS", "ticks": 3});
+        assert_eq!(message_type(&frame).as_deref(), Some("GAME_STATISTICS"));
+        assert_eq!(message_type(&serde_json::json!({"ticks": 3})), None);
+    }
+
+    #[test]
+    fn validate_perform() {
+        let turn = |angle| PerformMessage::PerformTurn {
+            token: "1919810".into(),
+            direction: TurnDirection::Clockwise,
+            angle,
+        };
+
+        assert_eq!(turn(90).validate(), Ok(()));
+        assert_eq!(turn(720).validate(), Err(InvalidPerform::InvalidAngle(720)));
+        assert_eq!(
+            PerformMessage::PerformAttack {
+                token: PlayerToken::default()
+            }
+            .validate(),
+            Err(InvalidPerform::EmptyToken)
+        );
+    }
+
+    #[test]
+    fn handshake_headers() {
+        let config = ClientConfig::new().with_header("Authorization", "Bearer 1919810");
+
+        let request = config.request("ws://127.0.0.1:14514").unwrap();
+
+        assert_eq!(request.headers()["Authorization"], "Bearer 1919810");
+    }
+
     #[test]
     fn perform_skill_serialize() {
         let msg = PerformMessage::PerformSkill {
-            token: "1919810".to_string(),
+            token: "1919810".into(),
             skill_name: SkillKind::Flash,
         };
 
@@ -187,7 +1604,7 @@ mod tests {
     #[test]
     fn get_player_info_serialize() {
         let msg = PerformMessage::GetPlayerInfo {
-            token: "1919810".to_string(),
+            token: "1919810".into(),
             request: RequestType::TheSelf,
         };
 
@@ -198,4 +1615,25 @@ mod tests {
             r#"{"messageType":"GET_PLAYER_INFO","token":"1919810","request":"SELF"}"#
         )
     }
+
+    #[test]
+    fn cancelled_connect_returns_a_closed_client() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let config = ClientConfig::default().with_cancellation(cancellation);
+        runtime.block_on(async {
+            let mut client =
+                AgentClient::with_config("ws://127.0.0.1:1".to_string(), "1919810", config).await;
+            assert_eq!(client.recv().await, None);
+            let msg = PerformMessage::GetPlayerInfo {
+                token: "1919810".into(),
+                request: RequestType::TheSelf,
+            };
+            assert!(client.send_perform(msg).await.is_err());
+        });
+    }
 }
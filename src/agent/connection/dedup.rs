@@ -0,0 +1,78 @@
+/*! Deduplication of frames received over redundant connections. */
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+/// How many distinct recent frames are remembered.
+const WINDOW: usize = 256;
+
+/// Drops frames that were already delivered by another connection.
+///
+/// Every link is expected to deliver the same sequence of frames. A frame is
+/// forwarded when the link receiving it has now seen it more often than any
+/// other link, so legitimately repeated frames (e.g. two identical replies)
+/// still come through once per repetition.
+pub(super) struct Deduplicator {
+    links: usize,
+    order: VecDeque<u64>,
+    seen: HashMap<u64, Vec<u32>>,
+}
+
+impl Deduplicator {
+    pub(super) fn new(links: usize) -> Deduplicator {
+        Deduplicator {
+            links,
+            order: VecDeque::new(),
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Record `frame` as received on `link`, returning whether it is new.
+    pub(super) fn accept(&mut self, link: usize, frame: &str) -> bool {
+        if self.links < 2 {
+            return true;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        frame.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let links = self.links;
+        let order = &mut self.order;
+        let counts = self.seen.entry(hash).or_insert_with(|| {
+            order.push_back(hash);
+            vec![0; links]
+        });
+        counts[link] += 1;
+        let accepted = counts
+            .iter()
+            .enumerate()
+            .all(|(other, count)| other == link || *count < counts[link]);
+
+        while self.order.len() > WINDOW {
+            if let Some(old) = self.order.pop_front() {
+                self.seen.remove(&old);
+            }
+        }
+        accepted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_frames_seen_on_other_link() {
+        let mut dedup = Deduplicator::new(2);
+
+        assert!(dedup.accept(0, "a"));
+        assert!(!dedup.accept(1, "a"));
+        assert!(dedup.accept(1, "b"));
+        assert!(!dedup.accept(0, "b"));
+
+        // The same frame sent twice by the server is delivered twice.
+        assert!(dedup.accept(0, "a"));
+        assert!(!dedup.accept(1, "a"));
+    }
+}
@@ -0,0 +1,137 @@
+/*! Simulated network faults, to exercise reconnection and timing locally. */
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Faults injected between [`AgentClient`](super::AgentClient) and the
+/// socket, see [`ClientConfig::with_fault_injection`](super::ClientConfig::with_fault_injection).
+///
+/// Delayed frames stay in order, so latency on incoming frames adds up while
+/// the agent is busy, as on a congested link.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use thuai_8_agent_rust::agent::connection::{ClientConfig, FaultInjection};
+///
+/// let config = ClientConfig::new().with_fault_injection(
+///     FaultInjection::new()
+///         .with_latency(Duration::from_millis(50), Duration::from_millis(20))
+///         .with_drop_rate(0.01)
+///         .with_disconnect_rate(0.001),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FaultInjection {
+    latency: Duration,
+    jitter: Duration,
+    drop_rate: f64,
+    disconnect_rate: f64,
+}
+
+impl FaultInjection {
+    /// Constructs a [`FaultInjection`] injecting nothing.
+    pub fn new() -> FaultInjection {
+        FaultInjection::default()
+    }
+
+    /// Delay every frame in both directions by `latency` plus up to `jitter`.
+    pub fn with_latency(mut self, latency: Duration, jitter: Duration) -> Self {
+        self.latency = latency;
+        self.jitter = jitter;
+        self
+    }
+
+    /// Silently lose each frame, in both directions, with probability `rate`.
+    pub fn with_drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate;
+        self
+    }
+
+    /// Break the connection, as if the server crashed, with probability
+    /// `rate` on each incoming frame.
+    pub fn with_disconnect_rate(mut self, rate: f64) -> Self {
+        self.disconnect_rate = rate;
+        self
+    }
+}
+
+/// What happens to an incoming frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Fault {
+    Deliver(Duration),
+    Drop,
+    Disconnect,
+}
+
+/// Draws the faults of a [`FaultInjection`].
+#[derive(Debug)]
+pub(super) struct FaultInjector {
+    faults: FaultInjection,
+    random: RandomState,
+    rolls: AtomicU64,
+}
+
+impl FaultInjector {
+    pub(super) fn new(faults: FaultInjection) -> FaultInjector {
+        FaultInjector {
+            faults,
+            random: RandomState::new(),
+            rolls: AtomicU64::new(0),
+        }
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn roll(&self) -> f64 {
+        let roll = self.rolls.fetch_add(1, Ordering::Relaxed);
+        (self.random.hash_one(roll) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn delay(&self) -> Duration {
+        self.faults.latency + self.faults.jitter.mul_f64(self.roll())
+    }
+
+    /// The fault for an incoming frame.
+    pub(super) fn inbound(&self) -> Fault {
+        if self.roll() < self.faults.disconnect_rate {
+            Fault::Disconnect
+        } else if self.roll() < self.faults.drop_rate {
+            Fault::Drop
+        } else {
+            Fault::Deliver(self.delay())
+        }
+    }
+
+    /// The delay of an outgoing frame, or [`None`] to lose it.
+    pub(super) fn outbound(&self) -> Option<Duration> {
+        (self.roll() >= self.faults.drop_rate).then(|| self.delay())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_configured_faults() {
+        let latency = FaultInjector::new(
+            FaultInjection::new().with_latency(Duration::from_millis(10), Duration::from_millis(5)),
+        );
+        for _ in 0..100 {
+            let Fault::Deliver(delay) = latency.inbound() else {
+                panic!("Only latency was configured");
+            };
+            assert!(delay >= Duration::from_millis(10) && delay < Duration::from_millis(15));
+        }
+
+        let lossy = FaultInjector::new(FaultInjection::new().with_drop_rate(1.0));
+        assert_eq!(lossy.inbound(), Fault::Drop);
+        assert_eq!(lossy.outbound(), None);
+
+        let broken = FaultInjector::new(FaultInjection::new().with_disconnect_rate(1.0));
+        assert_eq!(broken.inbound(), Fault::Disconnect);
+        assert_eq!(broken.outbound(), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,99 @@
+/*! Ring buffer of the most recent raw frames, kept for debugging. */
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+/// Whether a frame was sent to or received from the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameDirection {
+    #[serde(rename = "SENT")]
+    Sent,
+    #[serde(rename = "RECEIVED")]
+    Received,
+}
+
+/// One raw frame as it went over the wire.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct RecordedFrame {
+    direction: FrameDirection,
+    /// Milliseconds since the Unix epoch.
+    #[serde(rename = "timestampMs")]
+    timestamp_ms: u64,
+    frame: String,
+}
+
+/// Keeps the last `capacity` frames in both directions.
+pub(super) struct FrameLog {
+    capacity: usize,
+    frames: VecDeque<RecordedFrame>,
+}
+
+impl FrameLog {
+    pub(super) fn new(capacity: usize) -> FrameLog {
+        FrameLog {
+            capacity,
+            frames: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(super) fn record(&mut self, direction: FrameDirection, frame: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        self.frames.push_back(RecordedFrame {
+            direction,
+            timestamp_ms,
+            frame: frame.to_string(),
+        });
+    }
+
+    pub(super) fn frames(&self) -> Vec<RecordedFrame> {
+        self.frames.iter().cloned().collect()
+    }
+
+    /// Write every kept frame to the log at ERROR level.
+    pub(super) fn dump(&self, reason: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        error!("{reason}; last {} frames follow", self.frames.len());
+        for frame in &self.frames {
+            error!(
+                "  [{}] {:?}: {}",
+                frame.timestamp_ms, frame.direction, frame.frame
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_last_frames() {
+        let mut log = FrameLog::new(2);
+
+        log.record(FrameDirection::Sent, "a");
+        log.record(FrameDirection::Received, "b");
+        log.record(FrameDirection::Received, "c");
+
+        let frames = log.frames();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame(), "b");
+        assert_eq!(frames[1].direction(), &FrameDirection::Received);
+    }
+}
@@ -0,0 +1,102 @@
+/*! Overall state of the connections, published as they come and go. */
+use std::sync::Mutex;
+
+use tokio::sync::watch;
+
+/// State of the connection to the server as a whole, see
+/// [`AgentClient::watch_connection_state`](super::AgentClient::watch_connection_state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display)]
+pub enum ConnectionState {
+    /// No connection was opened yet.
+    #[default]
+    Connecting,
+    /// Every connection is up.
+    Connected,
+    /// Some, but not all, redundant connections are up.
+    Degraded,
+    /// No connection is up, but one is being reopened.
+    Reconnecting,
+    /// Every connection closed for good.
+    Closed,
+}
+
+/// State of one connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LinkState {
+    Up,
+    Reconnecting,
+    Down,
+}
+
+/// Tracks every connection and publishes their [`ConnectionState`].
+pub(super) struct Health {
+    links: Mutex<Vec<LinkState>>,
+    state: watch::Sender<ConnectionState>,
+}
+
+impl Health {
+    pub(super) fn new() -> (Health, watch::Receiver<ConnectionState>) {
+        let (state, receiver) = watch::channel(ConnectionState::Connecting);
+        let health = Health {
+            links: Mutex::new(Vec::new()),
+            state,
+        };
+        (health, receiver)
+    }
+
+    /// Set the state of connection `index`, opened in order from zero.
+    pub(super) fn set(&self, index: usize, link: LinkState) {
+        let mut links = self.links.lock().unwrap();
+        if index == links.len() {
+            links.push(link);
+        } else {
+            links[index] = link;
+        }
+        let state = aggregate(&links);
+        self.state.send_if_modified(|current| {
+            let changed = *current != state;
+            *current = state;
+            changed
+        });
+    }
+
+    pub(super) fn clear_poison(&self) {
+        self.links.clear_poison();
+    }
+}
+
+fn aggregate(links: &[LinkState]) -> ConnectionState {
+    let up = links.iter().filter(|link| **link == LinkState::Up).count();
+    if links.is_empty() {
+        ConnectionState::Connecting
+    } else if up == links.len() {
+        ConnectionState::Connected
+    } else if up > 0 {
+        ConnectionState::Degraded
+    } else if links.contains(&LinkState::Reconnecting) {
+        ConnectionState::Reconnecting
+    } else {
+        ConnectionState::Closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn publishes_overall_state() {
+        let (health, state) = Health::new();
+        assert_eq!(*state.borrow(), ConnectionState::Connecting);
+
+        health.set(0, LinkState::Up);
+        health.set(1, LinkState::Up);
+        assert_eq!(*state.borrow(), ConnectionState::Connected);
+        health.set(0, LinkState::Reconnecting);
+        assert_eq!(*state.borrow(), ConnectionState::Degraded);
+        health.set(1, LinkState::Down);
+        assert_eq!(*state.borrow(), ConnectionState::Reconnecting);
+        health.set(0, LinkState::Down);
+        assert_eq!(*state.borrow(), ConnectionState::Closed);
+    }
+}
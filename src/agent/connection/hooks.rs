@@ -0,0 +1,70 @@
+/*! User hooks on the JSON of every message, for protocol extensions. */
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+/// Hook rewriting every outgoing message before it is sent.
+pub type OutgoingHook = Arc<dyn Fn(&mut Value) + Send + Sync>;
+
+/// Hook inspecting every incoming message before it is parsed.
+pub type IncomingHook = Arc<dyn Fn(&Value) + Send + Sync>;
+
+/// The hooks registered on a [`ClientConfig`](super::ClientConfig), run in
+/// registration order.
+#[derive(Clone, Default)]
+pub(super) struct Hooks {
+    pub(super) outgoing: Vec<OutgoingHook>,
+    pub(super) incoming: Vec<IncomingHook>,
+}
+
+impl Hooks {
+    pub(super) fn rewrite(&self, value: &mut Value) {
+        for hook in &self.outgoing {
+            hook(value);
+        }
+    }
+
+    pub(super) fn inspect(&self, value: &Value) {
+        for hook in &self.incoming {
+            hook(value);
+        }
+    }
+}
+
+impl Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("outgoing", &self.outgoing.len())
+            .field("incoming", &self.incoming.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn hooks_run_in_order() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        let hooks = Hooks {
+            outgoing: vec![
+                Arc::new(|value: &mut Value| value["room"] = "a".into()),
+                Arc::new(|value: &mut Value| value["room"] = "b".into()),
+            ],
+            incoming: vec![Arc::new(move |_: &Value| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })],
+        };
+
+        let mut value = serde_json::json!({ "messageType": "PERFORM_ATTACK" });
+        hooks.rewrite(&mut value);
+        hooks.inspect(&value);
+
+        assert_eq!(value["room"], "b");
+        assert_eq!(seen.load(Ordering::Relaxed), 1);
+    }
+}
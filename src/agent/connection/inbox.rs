@@ -0,0 +1,146 @@
+/*! Bounded queue between the read loops and the consumer of messages. */
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use tokio::sync::Notify;
+
+/// What to do when messages arrive faster than they are consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackpressurePolicy {
+    /// When the queue is full, drop the oldest queued message.
+    #[default]
+    DropOldest,
+    /// Replace a queued state message (player info, environment info,
+    /// statistics, buffs) by a newer one of the same kind, so only the latest
+    /// state waits in the queue. Falls back to dropping the oldest message
+    /// if the queue is still full.
+    CoalesceLatest,
+}
+
+/// What happened to the queue when a message was pushed.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Pushed {
+    Queued,
+    Coalesced,
+    DroppedOldest,
+}
+
+struct Entry {
+    key: Option<String>,
+    frame: String,
+    queued_at: Instant,
+}
+
+/// A bounded multi-producer queue of raw frames, closed once every producer
+/// is gone.
+pub(super) struct Inbox {
+    queue: Mutex<VecDeque<Entry>>,
+    notify: Notify,
+    capacity: usize,
+    policy: BackpressurePolicy,
+    producers: AtomicUsize,
+}
+
+impl Inbox {
+    pub(super) fn new(capacity: usize, policy: BackpressurePolicy) -> Inbox {
+        Inbox {
+            queue: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            capacity: capacity.max(1),
+            policy,
+            producers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Register a producer; the inbox stays open while any is registered.
+    pub(super) fn open(&self) {
+        self.producers.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Unregister a producer.
+    pub(super) fn close(&self) {
+        self.producers.fetch_sub(1, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    /// Make the queue usable again after a thread panicked while holding
+    /// its lock. The queued frames are kept.
+    pub(super) fn clear_poison(&self) {
+        self.queue.clear_poison();
+    }
+
+    /// Queue `frame`. `key` identifies state messages that may be coalesced.
+    pub(super) fn push(&self, key: Option<String>, frame: String) -> Pushed {
+        let mut queue = self.queue.lock().unwrap();
+        let mut pushed = Pushed::Queued;
+        if self.policy == BackpressurePolicy::CoalesceLatest
+            && key.is_some()
+            && let Some(index) = queue.iter().position(|entry| entry.key == key)
+        {
+            queue.remove(index);
+            pushed = Pushed::Coalesced;
+        }
+        if queue.len() >= self.capacity {
+            queue.pop_front();
+            pushed = Pushed::DroppedOldest;
+        }
+        queue.push_back(Entry {
+            key,
+            frame,
+            queued_at: Instant::now(),
+        });
+        drop(queue);
+        self.notify.notify_one();
+        pushed
+    }
+
+    /// Take the next frame and when it was queued.
+    pub(super) fn try_pop(&self) -> Option<(String, Instant)> {
+        self.queue
+            .lock()
+            .unwrap()
+            .pop_front()
+            .map(|entry| (entry.frame, entry.queued_at))
+    }
+
+    /// Wait for the next frame; [`None`] once empty and closed.
+    pub(super) async fn pop(&self) -> Option<(String, Instant)> {
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if let Some(frame) = self.try_pop() {
+                return Some(frame);
+            }
+            if self.producers.load(Ordering::SeqCst) == 0 {
+                return None;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesces_state_and_bounds_queue() {
+        let inbox = Inbox::new(2, BackpressurePolicy::CoalesceLatest);
+
+        assert_eq!(inbox.push(Some("STATS".into()), "1".into()), Pushed::Queued);
+        assert_eq!(inbox.push(None, "error".into()), Pushed::Queued);
+        assert_eq!(
+            inbox.push(Some("STATS".into()), "2".into()),
+            Pushed::Coalesced
+        );
+        assert_eq!(inbox.push(None, "error2".into()), Pushed::DroppedOldest);
+
+        let pop = || inbox.try_pop().map(|(frame, _)| frame);
+        assert_eq!(pop().as_deref(), Some("2"));
+        assert_eq!(pop().as_deref(), Some("error2"));
+        assert_eq!(pop(), None);
+    }
+}
@@ -0,0 +1,106 @@
+/*! Which optional subsystems of a client started, and which did not. */
+use std::fmt::Display;
+
+use tracing::{info, warn};
+
+/// Whether an optional subsystem is running.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubsystemState {
+    /// Running as configured.
+    Active,
+    /// Configured, but failed to start fully; the reason is given. The client
+    /// carries on without it.
+    Degraded(String),
+    /// Not configured.
+    Off,
+}
+
+impl Display for SubsystemState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubsystemState::Active => write!(f, "active"),
+            SubsystemState::Degraded(reason) => write!(f, "degraded ({reason})"),
+            SubsystemState::Off => write!(f, "off"),
+        }
+    }
+}
+
+/// The state of every optional subsystem once the client connected, see
+/// [`AgentClient::init_report`](super::AgentClient::init_report).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InitReport {
+    subsystems: Vec<(&'static str, SubsystemState)>,
+}
+
+impl InitReport {
+    pub(super) fn add(&mut self, name: &'static str, state: SubsystemState) {
+        self.subsystems.push((name, state));
+    }
+
+    /// The state of subsystem `name`, if the client has one by that name.
+    pub fn state(&self, name: &str) -> Option<&SubsystemState> {
+        self.subsystems
+            .iter()
+            .find(|(subsystem, _)| *subsystem == name)
+            .map(|(_, state)| state)
+    }
+
+    /// Every subsystem with its state, in start order.
+    pub fn subsystems(&self) -> &[(&'static str, SubsystemState)] {
+        &self.subsystems
+    }
+
+    /// Whether any subsystem is degraded.
+    pub fn is_degraded(&self) -> bool {
+        self.subsystems
+            .iter()
+            .any(|(_, state)| matches!(state, SubsystemState::Degraded(_)))
+    }
+
+    /// Log the report, degraded subsystems at WARN level.
+    pub(super) fn log(&self) {
+        info!("Subsystems: {self}");
+        for (name, state) in &self.subsystems {
+            if let SubsystemState::Degraded(reason) = state {
+                warn!("Subsystem {name} is degraded: {reason}");
+            }
+        }
+    }
+}
+
+impl Display for InitReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, (name, state)) in self.subsystems.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name} {state}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_subsystems() {
+        let mut report = InitReport::default();
+        report.add("keepalive", SubsystemState::Active);
+        report.add("frame log", SubsystemState::Off);
+        assert!(!report.is_degraded());
+
+        report.add(
+            "redundancy",
+            SubsystemState::Degraded("second connection failed".to_string()),
+        );
+
+        assert!(report.is_degraded());
+        assert_eq!(report.state("frame log"), Some(&SubsystemState::Off));
+        assert_eq!(
+            report.to_string(),
+            "keepalive active, frame log off, redundancy degraded (second connection failed)"
+        );
+    }
+}
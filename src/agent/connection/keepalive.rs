@@ -0,0 +1,118 @@
+/*! Keepalive traffic sent while the connection is otherwise idle. */
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use tokio::time::interval;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use super::super::model::PlayerToken;
+use super::hooks::Hooks;
+use super::stats::StatsRecorder;
+use super::{Link, PerformMessage, write_first_healthy};
+
+/// What is sent to keep an idle connection alive.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum KeepaliveProbe {
+    /// A WebSocket ping frame.
+    #[default]
+    Ping,
+    /// A `GET_GAME_STATISTICS` request, for servers that ignore pings.
+    GameStatistics,
+}
+
+/// Keepalive settings, e.g. for long Rest stages where nothing is sent.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use thuai_8_agent_rust::agent::connection::{ClientConfig, Keepalive, KeepaliveProbe};
+///
+/// let config = ClientConfig::new()
+///     .with_keepalive(Keepalive::new(Duration::from_secs(15), KeepaliveProbe::GameStatistics));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Keepalive {
+    idle: Duration,
+    probe: KeepaliveProbe,
+}
+
+impl Keepalive {
+    /// Send `probe` whenever nothing was sent or received for `idle`.
+    pub fn new(idle: Duration, probe: KeepaliveProbe) -> Keepalive {
+        Keepalive { idle, probe }
+    }
+}
+
+/// Time of the last frame sent or received on any connection.
+pub(super) struct Activity(Mutex<Instant>);
+
+impl Activity {
+    pub(super) fn new() -> Activity {
+        Activity(Mutex::new(Instant::now()))
+    }
+
+    pub(super) fn touch(&self) {
+        *self.0.lock().unwrap() = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.0.lock().unwrap().elapsed()
+    }
+}
+
+/// Send a probe whenever the connection is idle, until the client is dropped
+/// or shut down.
+pub(super) async fn run(
+    keepalive: Keepalive,
+    links: Weak<Vec<Arc<Link>>>,
+    activity: Arc<Activity>,
+    stats: Arc<Mutex<StatsRecorder>>,
+    hooks: Hooks,
+    token: PlayerToken,
+    cancellation: CancellationToken,
+) {
+    let mut ticker = interval((keepalive.idle / 4).max(Duration::from_millis(10)));
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = cancellation.cancelled() => return,
+        }
+        let Some(links) = links.upgrade() else {
+            return;
+        };
+        if activity.idle_for() < keepalive.idle {
+            continue;
+        }
+        let (message, message_type) = match keepalive.probe {
+            KeepaliveProbe::Ping => (Message::Ping(Vec::new().into()), None),
+            KeepaliveProbe::GameStatistics => {
+                let request = PerformMessage::GetGameStatistics {
+                    token: token.clone(),
+                };
+                let mut value = serde_json::to_value(&request).unwrap();
+                hooks.rewrite(&mut value);
+                (
+                    Message::Text(value.to_string().into()),
+                    Some("GET_GAME_STATISTICS"),
+                )
+            }
+        };
+        let len = message.len();
+        debug!("Connection idle, sending keepalive {:?}", keepalive.probe);
+        match write_first_healthy(&links, message).await {
+            Ok(_) => {
+                activity.touch();
+                if let Some(message_type) = message_type {
+                    stats.lock().unwrap().record_sent(message_type, len);
+                }
+            }
+            Err(_) => {
+                warn!("Sending keepalive failed, no healthy connection");
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+/*! Where the time between receiving a message and answering it goes. */
+use std::fmt::Display;
+use std::time::Duration;
+
+use getset::CopyGetters;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+/// A step on the way from a message received to a command on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyStage {
+    /// From the frame being read to it being parsed, checked and queued.
+    Handle,
+    /// Waiting in the queue until taken with
+    /// [`AgentClient::recv`](super::AgentClient::recv).
+    Queue,
+    /// From the message taken to the cached state being updated with it by
+    /// [`Agent::apply`](crate::agent::Agent::apply).
+    Apply,
+    /// From the last message taken, or applied, to the next command being
+    /// sent, i.e. the time the logic took to decide.
+    Decide,
+    /// Turning the command into JSON, hooks included.
+    Serialize,
+    /// Writing the command to the socket and flushing it.
+    Flush,
+}
+
+impl LatencyStage {
+    /// Every stage, in pipeline order.
+    pub const ALL: [LatencyStage; 6] = [
+        LatencyStage::Handle,
+        LatencyStage::Queue,
+        LatencyStage::Apply,
+        LatencyStage::Decide,
+        LatencyStage::Serialize,
+        LatencyStage::Flush,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            LatencyStage::Handle => "handle",
+            LatencyStage::Queue => "queue",
+            LatencyStage::Apply => "apply",
+            LatencyStage::Decide => "decide",
+            LatencyStage::Serialize => "serialize",
+            LatencyStage::Flush => "flush",
+        }
+    }
+}
+
+/// Timings of one [`LatencyStage`].
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct StageLatency {
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl StageLatency {
+    /// Average time spent in the stage.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        self.total / self.count as u32
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+}
+
+/// Per-stage timings of the path from a message received to a command
+/// flushed, see [`AgentClient::latency_report`](super::AgentClient::latency_report).
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::connection::{LatencyReport, LatencyStage};
+///
+/// let report = LatencyReport::default();
+///
+/// assert_eq!(report.stage(LatencyStage::Decide).count(), 0);
+/// assert!(report.is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyReport {
+    stages: [StageLatency; 6],
+}
+
+impl LatencyReport {
+    /// Timings of `stage`.
+    pub fn stage(&self, stage: LatencyStage) -> &StageLatency {
+        &self.stages[stage as usize]
+    }
+
+    /// Whether nothing was measured yet.
+    pub fn is_empty(&self) -> bool {
+        self.stages.iter().all(|stage| stage.count == 0)
+    }
+
+    pub(super) fn record(&mut self, stage: LatencyStage, elapsed: Duration) {
+        self.stages[stage as usize].record(elapsed);
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Display for LatencyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, stage) in LatencyStage::ALL.into_iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            let timings = self.stage(stage);
+            write!(
+                f,
+                "{} {:.2}ms avg / {:.2}ms max",
+                stage.name(),
+                millis(timings.mean()),
+                millis(timings.max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialized as a map from stage name to count, mean and max in
+/// milliseconds.
+impl Serialize for LatencyReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.stages.len()))?;
+        for stage in LatencyStage::ALL {
+            let timings = self.stage(stage);
+            map.serialize_entry(
+                stage.name(),
+                &serde_json::json!({
+                    "count": timings.count,
+                    "meanMs": millis(timings.mean()),
+                    "maxMs": millis(timings.max),
+                }),
+            )?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_stages() {
+        let mut report = LatencyReport::default();
+        report.record(LatencyStage::Decide, Duration::from_millis(2));
+        report.record(LatencyStage::Decide, Duration::from_millis(4));
+        report.record(LatencyStage::Flush, Duration::from_micros(500));
+
+        let decide = report.stage(LatencyStage::Decide);
+        assert_eq!(decide.count(), 2);
+        assert_eq!(decide.mean(), Duration::from_millis(3));
+        assert_eq!(decide.max(), Duration::from_millis(4));
+        assert_eq!(
+            report.to_string(),
+            "handle 0.00ms avg / 0.00ms max, queue 0.00ms avg / 0.00ms max, \
+            apply 0.00ms avg / 0.00ms max, decide 3.00ms avg / 4.00ms max, serialize 0.00ms avg / 0.00ms max, \
+            flush 0.50ms avg / 0.50ms max"
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["decide"]["meanMs"], 3.0);
+    }
+}
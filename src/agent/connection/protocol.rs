@@ -0,0 +1,171 @@
+/*! Protocol version detection and compatibility shims for incoming messages. */
+use serde_json::{Map, Value};
+use tracing::info;
+
+/// A field rename applied to incoming messages, optionally only for one
+/// protocol version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldAlias {
+    version: Option<String>,
+    from: String,
+    to: String,
+}
+
+impl FieldAlias {
+    /// Rename every object key `from` to `to`. With `version` set, only
+    /// applies when the server reported that protocol version.
+    pub fn new(version: Option<&str>, from: &str, to: &str) -> FieldAlias {
+        FieldAlias {
+            version: version.map(str::to_string),
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+}
+
+/// `messageType` values older servers are known to use for current ones.
+const MESSAGE_TYPE_ALIASES: [(&str, &str); 1] = [("PLAYER_INFO", "PLAYERS_INFO")];
+
+/// Keys the server may announce its protocol version with.
+const VERSION_KEYS: [&str; 2] = ["protocolVersion", "version"];
+
+/// Rewrites incoming messages into the shape this crate parses.
+///
+/// The protocol version is taken from the configuration, or detected from
+/// the first message carrying a `protocolVersion` (or `version`) field.
+/// Then, for every message:
+/// - known legacy `messageType` names are mapped to the current ones;
+/// - `snake_case` keys are renamed to the `camelCase` used by the protocol;
+/// - the configured [`FieldAlias`]es matching the version are applied.
+pub(super) struct Compat {
+    version: Option<String>,
+    aliases: Vec<FieldAlias>,
+}
+
+impl Compat {
+    pub(super) fn new(version: Option<String>, aliases: Vec<FieldAlias>) -> Compat {
+        Compat { version, aliases }
+    }
+
+    pub(super) fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Adapt `value` in place, returning whether anything changed.
+    pub(super) fn adapt(&mut self, value: &mut Value) -> bool {
+        if self.version.is_none()
+            && let Some(version) = detect_version(value)
+        {
+            info!("Server speaks protocol version {}", version);
+            self.version = Some(version);
+        }
+
+        let mut changed = false;
+        if let Some(Value::String(kind)) = value.get_mut("messageType")
+            && let Some((_, current)) = MESSAGE_TYPE_ALIASES
+                .iter()
+                .find(|(legacy, _)| legacy == kind)
+        {
+            *kind = current.to_string();
+            changed = true;
+        }
+        let aliases: Vec<&FieldAlias> = self
+            .aliases
+            .iter()
+            .filter(|alias| alias.version.is_none() || alias.version == self.version)
+            .collect();
+        changed |= rename_keys(value, &aliases);
+        changed
+    }
+}
+
+fn detect_version(value: &Value) -> Option<String> {
+    VERSION_KEYS.iter().find_map(|key| match value.get(*key)? {
+        Value::String(version) => Some(version.clone()),
+        Value::Number(version) => Some(version.to_string()),
+        _ => None,
+    })
+}
+
+fn camel_case(key: &str) -> String {
+    let mut camel = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper = !camel.is_empty();
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+fn rename_keys(value: &mut Value, aliases: &[&FieldAlias]) -> bool {
+    match value {
+        Value::Object(object) => {
+            let mut changed = false;
+            let renamed: Map<String, Value> = std::mem::take(object)
+                .into_iter()
+                .map(|(key, mut field)| {
+                    changed |= rename_keys(&mut field, aliases);
+                    let mut new_key = match aliases.iter().find(|alias| alias.from == key) {
+                        Some(alias) => alias.to.clone(),
+                        None => key.clone(),
+                    };
+                    if new_key.contains('_') {
+                        new_key = camel_case(&new_key);
+                    }
+                    changed |= new_key != key;
+                    (new_key, field)
+                })
+                .collect();
+            *object = renamed;
+            changed
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .fold(false, |changed, item| rename_keys(item, aliases) | changed),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn adapts_legacy_message() {
+        let mut compat = Compat::new(
+            None,
+            vec![FieldAlias::new(Some("1"), "stage", "currentStage")],
+        );
+        let mut value = json!({
+            "messageType": "GAME_STATISTICS",
+            "protocolVersion": 1,
+            "stage": "REST",
+            "count_down": 10,
+            "scores": [{"token": "a", "score_value": 1}],
+        });
+
+        assert!(compat.adapt(&mut value));
+
+        assert_eq!(compat.version(), Some("1"));
+        assert_eq!(value["currentStage"], "REST");
+        assert_eq!(value["countDown"], 10);
+        assert_eq!(value["scores"][0]["scoreValue"], 1);
+    }
+
+    #[test]
+    fn maps_legacy_message_type_once() {
+        let mut compat = Compat::new(None, Vec::new());
+        let mut value = json!({"messageType": "PLAYER_INFO", "players": []});
+
+        assert!(compat.adapt(&mut value));
+        assert_eq!(value["messageType"], "PLAYERS_INFO");
+        assert!(!compat.adapt(&mut value));
+    }
+}
@@ -0,0 +1,94 @@
+/*! Rolling per-tick hash of the traffic, to spot diverging runs. */
+use tracing::debug;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Hashes the state received and the actions sent during each tick, and
+/// chains the per-tick hashes into a rolling hash.
+///
+/// FNV-1a is used so the value is identical across platforms and builds.
+/// Two runs that received the same states and chose the same actions report
+/// the same hash for every tick; the first tick whose hash differs is where
+/// they diverged.
+pub(super) struct ReplayHasher {
+    tick: Option<u64>,
+    current: u64,
+    rolling: u64,
+    last: Option<(u64, u64)>,
+}
+
+impl Default for ReplayHasher {
+    fn default() -> Self {
+        ReplayHasher {
+            tick: None,
+            current: FNV_OFFSET,
+            rolling: FNV_OFFSET,
+            last: None,
+        }
+    }
+}
+
+impl ReplayHasher {
+    /// The last completed tick and the rolling hash after it.
+    pub(super) fn last(&self) -> Option<(u64, u64)> {
+        self.last
+    }
+
+    /// Record a state frame. `tick` is set when the frame tells the current
+    /// tick, which closes the previous tick if it changed.
+    pub(super) fn record_state(&mut self, tick: Option<u64>, frame: &str) {
+        if let Some(tick) = tick
+            && self.tick != Some(tick)
+        {
+            self.finish_tick();
+            self.tick = Some(tick);
+        }
+        self.current = fnv1a(fnv1a(self.current, b"<"), frame.as_bytes());
+    }
+
+    /// Record an action sent to the server during the current tick.
+    pub(super) fn record_action(&mut self, frame: &str) {
+        self.current = fnv1a(fnv1a(self.current, b">"), frame.as_bytes());
+    }
+
+    fn finish_tick(&mut self) {
+        if let Some(tick) = self.tick {
+            self.rolling = fnv1a(self.rolling, &tick.to_le_bytes());
+            self.rolling = fnv1a(self.rolling, &self.current.to_le_bytes());
+            self.last = Some((tick, self.rolling));
+            debug!("Replay hash after tick {}: {:016x}", tick, self.rolling);
+        }
+        self.current = FNV_OFFSET;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(actions: &[&str]) -> Option<(u64, u64)> {
+        let mut hasher = ReplayHasher::default();
+        for (tick, action) in actions.iter().enumerate() {
+            hasher.record_state(Some(tick as u64), "{}");
+            hasher.record_action(action);
+        }
+        hasher.record_state(Some(actions.len() as u64), "{}");
+        hasher.last()
+    }
+
+    #[test]
+    fn same_run_same_hash() {
+        assert_eq!(run(&["a", "b"]), run(&["a", "b"]));
+        assert_ne!(run(&["a", "b"]), run(&["a", "c"]));
+        assert_eq!(run(&["a", "b"]).map(|(tick, _)| tick), Some(1));
+    }
+}
@@ -0,0 +1,136 @@
+/*! Policies deciding how often and how fast to retry connecting. */
+use std::collections::hash_map::RandomState;
+use std::fmt::Debug;
+use std::hash::BuildHasher;
+use std::time::Duration;
+
+/// Decides whether, and after how long, a failed connection attempt is
+/// retried. Governs both the initial connect and reconnects.
+pub trait RetryPolicy: Debug + Send + Sync {
+    /// The delay before retrying after `failures` failed attempts in a row
+    /// (starting at 1), or [`None`] to give up.
+    fn next_delay(&self, failures: u32) -> Option<Duration>;
+}
+
+/// Retry up to a fixed number of attempts, always waiting the same delay.
+///
+/// The default, 3 attempts 3 seconds apart.
+#[derive(Debug, Clone)]
+pub struct FixedDelay {
+    attempts: u32,
+    delay: Duration,
+}
+
+impl FixedDelay {
+    /// Make at most `attempts` attempts, `delay` apart.
+    pub fn new(attempts: u32, delay: Duration) -> FixedDelay {
+        FixedDelay { attempts, delay }
+    }
+}
+
+impl Default for FixedDelay {
+    fn default() -> Self {
+        FixedDelay::new(3, Duration::from_secs(3))
+    }
+}
+
+impl RetryPolicy for FixedDelay {
+    fn next_delay(&self, failures: u32) -> Option<Duration> {
+        (failures < self.attempts).then_some(self.delay)
+    }
+}
+
+/// Retry up to a fixed number of attempts, doubling the delay after each
+/// failure up to a maximum.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use thuai_8_agent_rust::agent::connection::{ExponentialBackoff, RetryPolicy};
+///
+/// let policy = ExponentialBackoff::new(6, Duration::from_millis(100), Duration::from_secs(1));
+///
+/// assert_eq!(policy.next_delay(1), Some(Duration::from_millis(100)));
+/// assert_eq!(policy.next_delay(3), Some(Duration::from_millis(400)));
+/// assert_eq!(policy.next_delay(5), Some(Duration::from_secs(1)));
+/// assert_eq!(policy.next_delay(6), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    attempts: u32,
+    initial: Duration,
+    max: Duration,
+}
+
+impl ExponentialBackoff {
+    /// Make at most `attempts` attempts, waiting `initial` after the first
+    /// failure and at most `max`.
+    pub fn new(attempts: u32, initial: Duration, max: Duration) -> ExponentialBackoff {
+        ExponentialBackoff {
+            attempts,
+            initial,
+            max,
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn next_delay(&self, failures: u32) -> Option<Duration> {
+        if failures >= self.attempts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        Some(self.initial.saturating_mul(factor).min(self.max))
+    }
+}
+
+/// [`ExponentialBackoff`] with each delay drawn uniformly between zero and
+/// the exponential delay, so that many agents reconnecting at once spread
+/// out.
+#[derive(Debug, Clone)]
+pub struct JitteredBackoff {
+    backoff: ExponentialBackoff,
+    random: RandomState,
+}
+
+impl JitteredBackoff {
+    /// Jitter the delays of `backoff`.
+    pub fn new(backoff: ExponentialBackoff) -> JitteredBackoff {
+        JitteredBackoff {
+            backoff,
+            random: RandomState::new(),
+        }
+    }
+}
+
+impl RetryPolicy for JitteredBackoff {
+    fn next_delay(&self, failures: u32) -> Option<Duration> {
+        let delay = self.backoff.next_delay(failures)?;
+        let random = self.random.hash_one(failures) as f64 / u64::MAX as f64;
+        Some(delay.mul_f64(random))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_and_jittered_delays() {
+        let fixed = FixedDelay::default();
+        assert_eq!(fixed.next_delay(2), Some(Duration::from_secs(3)));
+        assert_eq!(fixed.next_delay(3), None);
+
+        let jittered = JitteredBackoff::new(ExponentialBackoff::new(
+            4,
+            Duration::from_secs(1),
+            Duration::from_secs(10),
+        ));
+        for failures in 1..4 {
+            let delay = jittered.next_delay(failures).unwrap();
+            assert!(delay <= Duration::from_secs(1 << (failures - 1)));
+        }
+        assert_eq!(jittered.next_delay(4), None);
+    }
+}
@@ -0,0 +1,162 @@
+/*! Discarding of state messages older than what was already delivered. */
+use std::collections::HashMap;
+
+/// Tags each state message with a tick and drops those older than the last
+/// delivered message of the same kind.
+///
+/// A message is tagged with its own `ticks` (or `tick`) field if it has one,
+/// and otherwise with the latest tick announced by any message. After a
+/// reconnect the server may replay old state, e.g. a late `PLAYERS_INFO`,
+/// which must not overwrite newer data.
+///
+/// The ticks start over when the game does: on a change of stage, on a
+/// reconnect (see [`TickFilter::reset`]), and when two messages in a row
+/// carry a tick older than the latest one, which a single late message
+/// does not.
+#[derive(Default)]
+pub(super) struct TickFilter {
+    latest: Option<u64>,
+    stage: Option<String>,
+    /// Whether the last message with an own tick was older than `latest`.
+    behind: bool,
+    delivered: HashMap<String, u64>,
+}
+
+/// The tick a message carries itself, if any.
+pub(super) fn own_tick(value: &serde_json::Value) -> Option<u64> {
+    value
+        .get("ticks")
+        .or_else(|| value.get("tick"))
+        .and_then(serde_json::Value::as_u64)
+}
+
+/// The stage a message announces, if any.
+pub(super) fn stage(value: &serde_json::Value) -> Option<&str> {
+    value.get("currentStage")?.as_str()
+}
+
+/// What [`TickFilter::accept`] did with a message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Accepted {
+    Fresh,
+    /// Fresh, after the ticks started over.
+    Restarted,
+}
+
+impl TickFilter {
+    /// Forget every tick, so the next messages are fresh whatever their
+    /// tick.
+    pub(super) fn reset(&mut self) {
+        *self = TickFilter::default();
+    }
+
+    /// Tag a message of kind `key` carrying `own_tick` and announcing
+    /// `stage`, returning `Err` with its tick and the newer delivered one if
+    /// it is stale.
+    pub(super) fn accept(
+        &mut self,
+        key: &str,
+        own_tick: Option<u64>,
+        stage: Option<&str>,
+    ) -> Result<Accepted, (u64, u64)> {
+        let mut accepted = Accepted::Fresh;
+        if let Some(stage) = stage {
+            if self.stage.as_deref().is_some_and(|last| last != stage) {
+                self.reset();
+                accepted = Accepted::Restarted;
+            }
+            self.stage = Some(stage.to_string());
+        }
+        if let (Some(own_tick), Some(latest)) = (own_tick, self.latest) {
+            let behind = own_tick < latest;
+            if behind && self.behind {
+                let stage = self.stage.take();
+                self.reset();
+                self.stage = stage;
+                accepted = Accepted::Restarted;
+            } else {
+                self.behind = behind;
+            }
+        }
+        let Some(tick) = own_tick.or(self.latest) else {
+            return Ok(accepted);
+        };
+        if let Some(&delivered) = self.delivered.get(key)
+            && tick < delivered
+        {
+            return Err((tick, delivered));
+        }
+        self.delivered.insert(key.to_string(), tick);
+        if let Some(own_tick) = own_tick {
+            self.latest = Some(self.latest.map_or(own_tick, |latest| latest.max(own_tick)));
+        }
+        Ok(accepted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::coalesce_key;
+    use super::*;
+    use serde_json::json;
+
+    fn statistics(ticks: u64, stage: &str) -> serde_json::Value {
+        json!({"messageType": "GAME_STATISTICS", "ticks": ticks, "currentStage": stage})
+    }
+
+    fn accept(filter: &mut TickFilter, value: &serde_json::Value) -> Result<Accepted, (u64, u64)> {
+        let kind = value["messageType"].as_str().unwrap();
+        let key = coalesce_key(kind, value).unwrap();
+        filter.accept(&key, own_tick(value), stage(value))
+    }
+
+    #[test]
+    fn drops_stale_state() {
+        let mut filter = TickFilter::default();
+        let players = json!({"messageType": "PLAYERS_INFO", "players": [{"token": "a"}]});
+
+        assert_eq!(accept(&mut filter, &players), Ok(Accepted::Fresh));
+        assert_eq!(
+            accept(&mut filter, &statistics(5, "BATTLE")),
+            Ok(Accepted::Fresh)
+        );
+        assert_eq!(accept(&mut filter, &players), Ok(Accepted::Fresh));
+        assert_eq!(accept(&mut filter, &statistics(3, "BATTLE")), Err((3, 5)));
+        assert_eq!(
+            accept(&mut filter, &statistics(6, "BATTLE")),
+            Ok(Accepted::Fresh)
+        );
+        assert_eq!(filter.accept("PLAYERS_INFO:a", Some(4), None), Err((4, 5)));
+    }
+
+    #[test]
+    fn starts_over_with_the_game() {
+        let mut filter = TickFilter::default();
+        let players = json!({"messageType": "PLAYERS_INFO", "players": [{"token": "a"}]});
+        accept(&mut filter, &statistics(50, "BATTLE")).unwrap();
+        accept(&mut filter, &players).unwrap();
+
+        // A new match announces a new stage.
+        assert_eq!(
+            accept(&mut filter, &statistics(1, "REST")),
+            Ok(Accepted::Restarted)
+        );
+        assert_eq!(accept(&mut filter, &players), Ok(Accepted::Fresh));
+
+        // A restarted server keeps the stage but stays behind.
+        accept(&mut filter, &statistics(40, "REST")).unwrap();
+        assert!(accept(&mut filter, &statistics(2, "REST")).is_err());
+        assert_eq!(
+            accept(&mut filter, &statistics(3, "REST")),
+            Ok(Accepted::Restarted)
+        );
+        assert_eq!(accept(&mut filter, &players), Ok(Accepted::Fresh));
+
+        filter.accept("PLAYERS_INFO:a", Some(9), None).unwrap();
+        filter.reset();
+        assert_eq!(
+            filter.accept("PLAYERS_INFO:a", Some(1), None),
+            Ok(Accepted::Fresh)
+        );
+    }
+}
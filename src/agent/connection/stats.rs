@@ -0,0 +1,144 @@
+/*! Counters describing the traffic of an [`AgentClient`](super::AgentClient). */
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use getset::Getters;
+
+/// Weight of a new sample in the smoothed round-trip time.
+const RTT_SAMPLE_WEIGHT: f64 = 0.125;
+/// Outstanding requests remembered per reply type.
+const MAX_PENDING: usize = 64;
+
+/// Traffic statistics of one [`AgentClient`](super::AgentClient).
+///
+/// Message counts are keyed by `messageType`. Round-trip time is estimated
+/// by matching each `GET_*` request with the next reply of the corresponding
+/// type.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Default, Getters)]
+#[getset(get = "pub")]
+pub struct ConnectionStats {
+    messages_sent: HashMap<String, u64>,
+    messages_received: HashMap<String, u64>,
+    bytes_sent: u64,
+    bytes_received: u64,
+    last_rtt: Option<Duration>,
+    smoothed_rtt: Option<Duration>,
+    reconnects: u32,
+    coalesced_messages: u64,
+    dropped_messages: u64,
+    stale_messages: u64,
+}
+
+impl ConnectionStats {
+    /// Total number of messages sent, over all types.
+    pub fn total_sent(&self) -> u64 {
+        self.messages_sent.values().sum()
+    }
+
+    /// Total number of messages received, over all types.
+    pub fn total_received(&self) -> u64 {
+        self.messages_received.values().sum()
+    }
+}
+
+/// The reply type the server answers a request with, if it answers at all.
+fn reply_type(request: &str) -> Option<&'static str> {
+    match request {
+        "GET_PLAYER_INFO" => Some("PLAYERS_INFO"),
+        "GET_ENVIRONMENT_INFO" => Some("ENVIRONMENT_INFO"),
+        "GET_GAME_STATISTICS" => Some("GAME_STATISTICS"),
+        "GET_AVAILABLE_BUFFS" => Some("AVAILABLE_BUFFS"),
+        _ => None,
+    }
+}
+
+/// Updates [`ConnectionStats`] and keeps the requests awaiting a reply.
+#[derive(Default)]
+pub(super) struct StatsRecorder {
+    stats: ConnectionStats,
+    pending: HashMap<&'static str, VecDeque<Instant>>,
+}
+
+impl StatsRecorder {
+    pub(super) fn snapshot(&self) -> ConnectionStats {
+        self.stats.clone()
+    }
+
+    pub(super) fn record_sent(&mut self, message_type: &str, bytes: usize) {
+        *self
+            .stats
+            .messages_sent
+            .entry(message_type.to_string())
+            .or_default() += 1;
+        self.stats.bytes_sent += bytes as u64;
+        if let Some(reply) = reply_type(message_type) {
+            let pending = self.pending.entry(reply).or_default();
+            if pending.len() >= MAX_PENDING {
+                pending.pop_front();
+            }
+            pending.push_back(Instant::now());
+        }
+    }
+
+    pub(super) fn record_coalesced(&mut self) {
+        self.stats.coalesced_messages += 1;
+    }
+
+    pub(super) fn record_dropped(&mut self) {
+        self.stats.dropped_messages += 1;
+    }
+
+    pub(super) fn record_reconnect(&mut self) {
+        self.stats.reconnects += 1;
+    }
+
+    pub(super) fn record_stale(&mut self) {
+        self.stats.stale_messages += 1;
+    }
+
+    pub(super) fn record_received(&mut self, message_type: &str, bytes: usize) {
+        *self
+            .stats
+            .messages_received
+            .entry(message_type.to_string())
+            .or_default() += 1;
+        self.stats.bytes_received += bytes as u64;
+        let sent_at = self
+            .pending
+            .get_mut(message_type)
+            .and_then(VecDeque::pop_front);
+        if let Some(sent_at) = sent_at {
+            let sample = sent_at.elapsed();
+            self.stats.last_rtt = Some(sample);
+            self.stats.smoothed_rtt = Some(match self.stats.smoothed_rtt {
+                Some(rtt) => {
+                    rtt.mul_f64(1.0 - RTT_SAMPLE_WEIGHT) + sample.mul_f64(RTT_SAMPLE_WEIGHT)
+                }
+                None => sample,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_messages_and_rtt() {
+        let mut recorder = StatsRecorder::default();
+
+        recorder.record_sent("GET_GAME_STATISTICS", 10);
+        recorder.record_sent("PERFORM_ATTACK", 5);
+        recorder.record_received("GAME_STATISTICS", 100);
+        recorder.record_received("GAME_STATISTICS", 100);
+
+        let stats = recorder.snapshot();
+        assert_eq!(stats.total_sent(), 2);
+        assert_eq!(stats.messages_received()["GAME_STATISTICS"], 2);
+        assert_eq!(stats.bytes_received(), &200);
+        assert!(stats.smoothed_rtt().is_some());
+    }
+}
@@ -0,0 +1,190 @@
+/*! Alarm raised when the agent silently stops acting or hearing from the server. */
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use tokio::time::interval;
+use tokio_util::sync::CancellationToken;
+use tracing::error;
+
+/// Why the [`IdleAlarm`] went off.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdleEvent {
+    /// No perform was sent during this many consecutive battle ticks.
+    NoPerforms { ticks: u64 },
+    /// No message was received from the server for this long.
+    NoMessages { silence: Duration },
+}
+
+/// Callback invoked when the [`IdleAlarm`] goes off, e.g. to send a desktop
+/// notification or call a webhook.
+pub type IdleHook = Arc<dyn Fn(&IdleEvent) + Send + Sync>;
+
+/// Watchdog settings catching silent failures during unattended runs.
+///
+/// The alarm logs at ERROR level and calls the optional hook. It goes off
+/// once per idle period and re-arms when activity resumes.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use thuai_8_agent_rust::agent::connection::{ClientConfig, IdleAlarm};
+///
+/// let alarm = IdleAlarm::new(50, Duration::from_secs(10))
+///     .with_hook(|event| eprintln!("Agent idle: {:?}", event));
+/// let config = ClientConfig::new().with_idle_alarm(alarm);
+/// ```
+#[derive(Clone)]
+pub struct IdleAlarm {
+    max_idle_ticks: u64,
+    max_silence: Duration,
+    hook: Option<IdleHook>,
+}
+
+impl IdleAlarm {
+    /// Go off after `max_idle_ticks` battle ticks without a perform, or after
+    /// `max_silence` without any message from the server.
+    pub fn new(max_idle_ticks: u64, max_silence: Duration) -> IdleAlarm {
+        IdleAlarm {
+            max_idle_ticks,
+            max_silence,
+            hook: None,
+        }
+    }
+
+    /// Call `hook` whenever the alarm goes off.
+    pub fn with_hook(mut self, hook: impl Fn(&IdleEvent) + Send + Sync + 'static) -> Self {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+}
+
+impl Debug for IdleAlarm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdleAlarm")
+            .field("max_idle_ticks", &self.max_idle_ticks)
+            .field("max_silence", &self.max_silence)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+pub(super) struct Watchdog {
+    alarm: IdleAlarm,
+    last_received: Instant,
+    silence_reported: bool,
+    tick: Option<u64>,
+    idle_ticks: u64,
+    ticks_reported: bool,
+}
+
+impl Watchdog {
+    pub(super) fn new(alarm: IdleAlarm) -> Watchdog {
+        Watchdog {
+            alarm,
+            last_received: Instant::now(),
+            silence_reported: false,
+            tick: None,
+            idle_ticks: 0,
+            ticks_reported: false,
+        }
+    }
+
+    fn raise(&self, event: IdleEvent) {
+        error!("Agent looks idle: {:?}", event);
+        if let Some(hook) = &self.alarm.hook {
+            hook(&event);
+        }
+    }
+
+    pub(super) fn on_received(&mut self) {
+        self.last_received = Instant::now();
+        self.silence_reported = false;
+    }
+
+    /// Called with the stage and tick of every statistics message.
+    pub(super) fn on_statistics(&mut self, battle: bool, tick: u64) {
+        if self.tick == Some(tick) {
+            return;
+        }
+        self.tick = Some(tick);
+        if !battle {
+            self.idle_ticks = 0;
+            return;
+        }
+        self.idle_ticks += 1;
+        if self.idle_ticks >= self.alarm.max_idle_ticks && !self.ticks_reported {
+            self.ticks_reported = true;
+            self.raise(IdleEvent::NoPerforms {
+                ticks: self.idle_ticks,
+            });
+        }
+    }
+
+    pub(super) fn on_perform(&mut self) {
+        self.idle_ticks = 0;
+        self.ticks_reported = false;
+    }
+
+    fn check_silence(&mut self) {
+        let silence = self.last_received.elapsed();
+        if silence >= self.alarm.max_silence && !self.silence_reported {
+            self.silence_reported = true;
+            self.raise(IdleEvent::NoMessages { silence });
+        }
+    }
+
+    /// Periodically check for silence until the client is dropped or shut
+    /// down.
+    pub(super) async fn run(
+        watchdog: Weak<Mutex<Watchdog>>,
+        period: Duration,
+        cancellation: CancellationToken,
+    ) {
+        let mut ticker = interval(period);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = cancellation.cancelled() => return,
+            }
+            let Some(watchdog) = watchdog.upgrade() else {
+                return;
+            };
+            watchdog.lock().unwrap().check_silence();
+        }
+    }
+
+    pub(super) fn check_period(&self) -> Duration {
+        (self.alarm.max_silence / 4).max(Duration::from_millis(100))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn alarms_once_per_idle_period() {
+        let raised = Arc::new(AtomicU32::new(0));
+        let counter = raised.clone();
+        let alarm = IdleAlarm::new(3, Duration::from_secs(60)).with_hook(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let mut watchdog = Watchdog::new(alarm);
+
+        for tick in 0..10 {
+            watchdog.on_statistics(true, tick);
+        }
+        assert_eq!(raised.load(Ordering::SeqCst), 1);
+
+        watchdog.on_perform();
+        for tick in 10..12 {
+            watchdog.on_statistics(true, tick);
+        }
+        assert_eq!(raised.load(Ordering::SeqCst), 1);
+        watchdog.on_statistics(true, 12);
+        assert_eq!(raised.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,122 @@
+//! Pausing and stopping a running agent from outside.
+//!
+//! [`run_agent`](crate::run_agent) drives the [`Agent`](super::Agent) on its
+//! own, so a larger controller or a GUI steers it through an
+//! [`AgentControl`], a handle shared with the agent: while paused, the
+//! strategy and the polling are suspended but messages are still received,
+//! and once stopped, the run ends and the connection is closed.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Whether an agent runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Running,
+    Paused,
+    /// Final; a stopped agent cannot be resumed.
+    Stopped,
+}
+
+/// A handle to pause, resume and stop an agent. Clones control the same
+/// agent.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::control::{AgentControl, RunState};
+///
+/// let control = AgentControl::new();
+/// let handle = control.clone();
+///
+/// handle.pause();
+/// assert!(control.is_paused());
+/// handle.resume();
+/// assert_eq!(control.state(), RunState::Running);
+/// handle.stop();
+/// handle.resume();
+/// assert!(control.is_stopped());
+/// ```
+#[derive(Debug, Clone)]
+pub struct AgentControl {
+    state: Arc<watch::Sender<RunState>>,
+}
+
+impl Default for AgentControl {
+    fn default() -> Self {
+        AgentControl {
+            state: Arc::new(watch::Sender::new(RunState::Running)),
+        }
+    }
+}
+
+impl AgentControl {
+    /// Constructs an [`AgentControl`] of a running agent.
+    pub fn new() -> AgentControl {
+        AgentControl::default()
+    }
+
+    /// Suspend the strategy and the polling, if running.
+    pub fn pause(&self) {
+        self.transition(RunState::Running, RunState::Paused);
+    }
+
+    /// Continue after [`AgentControl::pause`].
+    pub fn resume(&self) {
+        self.transition(RunState::Paused, RunState::Running);
+    }
+
+    /// End the run for good.
+    pub fn stop(&self) {
+        self.state.send_replace(RunState::Stopped);
+    }
+
+    pub fn state(&self) -> RunState {
+        *self.state.borrow()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state() == RunState::Paused
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.state() == RunState::Stopped
+    }
+
+    /// Resolves once [`AgentControl::stop`] was called.
+    pub async fn stopped(&self) {
+        let mut receiver = self.state.subscribe();
+        // The sender lives as long as `self`, so waiting cannot fail.
+        let _ = receiver.wait_for(|state| *state == RunState::Stopped).await;
+    }
+
+    fn transition(&self, from: RunState, to: RunState) {
+        self.state.send_if_modified(|state| {
+            let matches = *state == from;
+            if matches {
+                *state = to;
+            }
+            matches
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::future::{FutureExt, join};
+
+    #[test]
+    fn stopped_resolves_on_stop_only() {
+        let control = AgentControl::new();
+        control.pause();
+        assert!(control.stopped().now_or_never().is_none());
+
+        let handle = control.clone();
+        block_on(join(control.stopped(), async move { handle.stop() }));
+        control.pause();
+        assert!(control.is_stopped());
+    }
+}
@@ -0,0 +1,145 @@
+//! Expected damage of an incoming hit.
+//!
+//! Whether a hit lands is random when the target can dodge, so movement
+//! logic weighs hits by their [`ExpectedDamage`] against the cost of
+//! repositioning instead of by their raw damage.
+//!
+//! The server does not publish the exact rules, so these assume that an
+//! active KAMUI or an active knife blocks a hit entirely, that `dodge_rate`
+//! is the probability of dodging, capped at [`MAX_DODGE_RATE`], and that the
+//! armor value absorbs damage before health unless the hit is anti-armor.
+//! Reflection is not counted.
+
+use super::model::{ArmorKnifeState, Bullet, MAX_DODGE_RATE, Player, SkillKind, Weapon};
+use getset::CopyGetters;
+
+/// Expected loss of a player from one hit.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct ExpectedDamage {
+    /// Expected health lost.
+    health: f64,
+    /// Expected armor value lost.
+    armor: f64,
+}
+
+impl ExpectedDamage {
+    /// Health and armor value lost together.
+    pub fn total(&self) -> f64 {
+        self.health + self.armor
+    }
+}
+
+/// Whether `target` cannot be hit at all right now.
+pub fn is_invulnerable(target: &Player) -> bool {
+    *target.armor().knife() == ArmorKnifeState::Active
+        || target
+            .skill(SkillKind::Kamui)
+            .is_some_and(|skill| *skill.is_active())
+}
+
+/// Probability that a hit aimed at `target` lands.
+pub fn hit_probability(target: &Player) -> f64 {
+    if is_invulnerable(target) {
+        0.0
+    } else {
+        1.0 - target.armor().dodge_rate().clamp(0.0, MAX_DODGE_RATE)
+    }
+}
+
+/// Expected loss of `target` from a hit of `damage`, which ignores armor if
+/// `anti_armor`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::damage;
+/// use thuai_8_agent_rust::agent::model::{
+///     Armor, ArmorKnifeState, Player, Position, Skill, SkillKind, Weapon,
+/// };
+///
+/// let me = |kamui_active: bool| {
+///     Player::new(
+///         "1919810",
+///         Position::new(1.0, 1.0, 0.0),
+///         Weapon::new(1.0, 1.0, false, false, 10, 10, 10),
+///         Armor::new(false, false, 4, 20, 0.5, ArmorKnifeState::NotOwned),
+///         vec![Skill::new(SkillKind::Kamui, 30, 0, kamui_active)],
+///     )
+/// };
+///
+/// // Half the hits are dodged; armor takes the first 4 of the rest.
+/// let expected = damage::expected_damage(10.0, false, &me(false));
+/// assert_eq!((expected.armor(), expected.health()), (2.0, 3.0));
+/// assert_eq!(damage::expected_damage(10.0, true, &me(false)).health(), 5.0);
+/// assert_eq!(damage::expected_damage(10.0, false, &me(true)).total(), 0.0);
+/// ```
+pub fn expected_damage(damage: f64, anti_armor: bool, target: &Player) -> ExpectedDamage {
+    let probability = hit_probability(target);
+    let damage = damage.max(0.0);
+    let absorbed = if anti_armor {
+        0.0
+    } else {
+        damage.min(*target.armor().armor_value() as f64)
+    };
+    ExpectedDamage {
+        health: (damage - absorbed) * probability,
+        armor: absorbed * probability,
+    }
+}
+
+/// Expected loss of `target` if `bullet` hits it.
+pub fn expected_bullet_damage(bullet: &Bullet, target: &Player) -> ExpectedDamage {
+    expected_damage(*bullet.damage(), *bullet.is_anti_armor(), target)
+}
+
+/// Expected loss of `target` from one shot of `weapon`.
+pub fn expected_shot_damage(weapon: &Weapon, target: &Player) -> ExpectedDamage {
+    expected_damage(*weapon.damage() as f64, *weapon.anti_armor(), target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::Position;
+    use crate::testing::fixtures::PlayerBuilder;
+
+    #[test]
+    fn weighs_dodge_armor_and_knife() {
+        let player = |armor_value, dodge_rate, knife| {
+            PlayerBuilder::new("1919810")
+                .with_weapon(Weapon::new(1.0, 1.0, false, true, 6, 10, 10))
+                .with_health(20)
+                .with_armor_value(armor_value)
+                .with_dodge_rate(dodge_rate)
+                .with_knife(knife)
+                .build()
+        };
+        let plain = player(10, 0.0, ArmorKnifeState::NotOwned);
+        let bullet = Bullet::new(1, false, false, Position::new(0.0, 0.0, 0.0), 1.0, 4.0, 0.0);
+        assert_eq!(
+            expected_bullet_damage(&bullet, &plain),
+            ExpectedDamage {
+                health: 0.0,
+                armor: 4.0
+            }
+        );
+        // Anti-armor weapons go straight to health.
+        assert_eq!(expected_shot_damage(plain.weapon(), &plain).health(), 6.0);
+
+        // A dodge rate past the cap still lets some hits through.
+        let dodging = player(0, 2.0, ArmorKnifeState::Broken);
+        assert!((hit_probability(&dodging) - (1.0 - MAX_DODGE_RATE)).abs() < 1e-9);
+        assert!((expected_damage(10.0, false, &dodging).health() - 1.0).abs() < 1e-9);
+
+        let knife = player(0, 0.0, ArmorKnifeState::Active);
+        assert!(is_invulnerable(&knife));
+        assert_eq!(
+            expected_damage(10.0, true, &knife),
+            ExpectedDamage::default()
+        );
+        assert_eq!(expected_damage(-3.0, false, &plain).total(), 0.0);
+    }
+}
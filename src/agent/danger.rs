@@ -0,0 +1,243 @@
+//! How dangerous each cell is over the next ticks.
+//!
+//! A [`DangerMap`] rasterizes the predicted paths of bullets, see
+//! [`ballistics`], and the lines lasers would fire along,
+//! so movement logic can look up the threat to a cell instead of tracing
+//! every bullet itself. [`DangerMap::safe_cells`] lists the cells worth
+//! retreating to.
+
+use super::ballistics::{self, LaserPath};
+use super::map::GameMap;
+use super::model::{Bullet, Player, PlayerToken, Position};
+use super::pathfinding::{self, PathOptions};
+use getset::CopyGetters;
+use std::collections::{HashMap, HashSet};
+
+/// Distance between the points of a path checked for the cells it crosses.
+const SAMPLE_STEP: f64 = 0.1;
+
+/// The threat to one cell.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct Threat {
+    /// Damage of everything passing through the cell, summed.
+    damage: f64,
+    /// Time from now until the first bullet or laser passes through.
+    earliest: f64,
+}
+
+/// Threat per cell from bullets and lasers, within a time horizon.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::danger::DangerMap;
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Bullet, EnvironmentInfo, Position};
+///
+/// let map = GameMap::new(&EnvironmentInfo::new(4, Vec::new(), Vec::new(), Vec::new()));
+/// // Flying along +x at 1 per tick.
+/// let bullet = Bullet::new(1, false, false, Position::new(0.5, 0.5, 0.0), 1.0, 2.0, 0.0);
+///
+/// let danger = DangerMap::new(&map, &[bullet], 10.0, 2.0);
+///
+/// assert_eq!(danger.threat(&Position::new(2, 0, 0.0)), 2.0);
+/// assert_eq!(danger.earliest(&Position::new(2, 0, 0.0)), Some(1.5));
+/// assert!(danger.is_safe(&Position::new(3, 0, 0.0)));
+/// assert!(danger.is_safe(&Position::new(0, 1, 0.0)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DangerMap {
+    cells: HashMap<Position<i32>, Threat>,
+}
+
+impl DangerMap {
+    /// Rasterize the paths of `bullets` over the next `horizon`, in the unit
+    /// of bullet speeds, given that bullets fly `bullet_range` in total.
+    pub fn new(map: &GameMap, bullets: &[Bullet], bullet_range: f64, horizon: f64) -> DangerMap {
+        let mut danger = DangerMap::default();
+        for bullet in bullets {
+            let trajectory = ballistics::predict(map, bullet, bullet_range);
+            let mut cells = HashMap::new();
+            for pair in trajectory.waypoints().windows(2) {
+                let (start, end) = (&pair[0], &pair[1]);
+                if *start.time() > horizon {
+                    break;
+                }
+                let length = start.position().distance_to(end.position());
+                let steps = (length / SAMPLE_STEP).ceil().max(1.0) as usize;
+                for step in 0..=steps {
+                    let fraction = step as f64 / steps as f64;
+                    let time = start.time() + (end.time() - start.time()) * fraction;
+                    if time > horizon {
+                        break;
+                    }
+                    let cell = GameMap::cell_of(&start.position().lerp(end.position(), fraction));
+                    cells.entry(cell).or_insert(time);
+                }
+            }
+            for (cell, time) in cells {
+                danger.add(map, cell, *bullet.damage(), time);
+            }
+        }
+        danger
+    }
+
+    /// Also rasterize the beams the lasers of `players` other than `own`
+    /// would fire right now, bouncing at most `max_reflections` times.
+    pub fn with_lasers(
+        mut self,
+        map: &GameMap,
+        players: &[Player],
+        own: &PlayerToken,
+        max_reflections: usize,
+    ) -> Self {
+        for player in players {
+            if player.token() == own || !player.weapon().is_laser() {
+                continue;
+            }
+            let laser = LaserPath::new(map, player.position(), max_reflections);
+            let mut cells = HashSet::new();
+            for (from, to) in laser.segments() {
+                let length = from.distance_to(to);
+                let steps = (length / SAMPLE_STEP).ceil().max(1.0) as usize;
+                cells.extend(
+                    (0..=steps)
+                        .map(|step| GameMap::cell_of(&from.lerp(to, step as f64 / steps as f64))),
+                );
+            }
+            for cell in cells {
+                self.add(map, cell, *player.weapon().damage() as f64, 0.0);
+            }
+        }
+        self
+    }
+
+    fn add(&mut self, map: &GameMap, cell: Position<i32>, damage: f64, time: f64) {
+        if !map.contains(&cell) {
+            return;
+        }
+        let cell = Position::new(*cell.x(), *cell.y(), 0.0);
+        let threat = self.cells.entry(cell).or_insert(Threat {
+            damage: 0.0,
+            earliest: time,
+        });
+        threat.damage += damage;
+        threat.earliest = threat.earliest.min(time);
+    }
+
+    /// The threat to `cell`, if any.
+    pub fn get(&self, cell: &Position<i32>) -> Option<Threat> {
+        self.cells.get(cell).copied()
+    }
+
+    /// Damage of everything passing through `cell`, zero if nothing does.
+    pub fn threat(&self, cell: &Position<i32>) -> f64 {
+        self.get(cell).map_or(0.0, |threat| threat.damage)
+    }
+
+    /// Time until something first passes through `cell`.
+    pub fn earliest(&self, cell: &Position<i32>) -> Option<f64> {
+        self.get(cell).map(|threat| threat.earliest)
+    }
+
+    /// Whether nothing passes through `cell`.
+    pub fn is_safe(&self, cell: &Position<i32>) -> bool {
+        !self.cells.contains_key(cell)
+    }
+
+    /// Safe cells that can be walked to from `from`, with the cost of the
+    /// path there, closest first.
+    ///
+    /// Safe means nothing passes through within the horizon the map was
+    /// built for.
+    pub fn safe_cells(
+        &self,
+        map: &GameMap,
+        from: &Position<f64>,
+        options: &PathOptions,
+    ) -> Vec<(Position<i32>, f64)> {
+        let mut cells: Vec<_> = pathfinding::reachable(map, from, options)
+            .into_iter()
+            .filter(|(cell, _)| self.is_safe(cell))
+            .collect();
+        cells.sort_by(|(a, a_cost), (b, b_cost)| {
+            a_cost
+                .total_cmp(b_cost)
+                .then_with(|| (a.y(), a.x()).cmp(&(b.y(), b.x())))
+        });
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{Angle, EnvironmentInfo, Wall, Weapon};
+    use crate::testing::fixtures::PlayerBuilder;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn sums_bullets_and_lasers() {
+        let environment = EnvironmentInfo::new(
+            4,
+            vec![Wall::new(0, 3, Angle::from_degrees(0.0))],
+            Vec::new(),
+            Vec::new(),
+        );
+        let map = GameMap::new(&environment);
+        let bullets = [
+            Bullet::new(1, false, false, Position::new(0.5, 0.5, 0.0), 1.0, 2.0, 0.0),
+            Bullet::new(
+                2,
+                false,
+                false,
+                Position::new(0.5, 3.5, -FRAC_PI_2),
+                1.0,
+                3.0,
+                0.0,
+            ),
+        ];
+        let laser = Weapon::new(1.0, 1.0, true, false, 5, 1, 1);
+        let players = [
+            PlayerBuilder::new("enemy")
+                .at(3.5, 1.5, FRAC_PI_2)
+                .with_weapon(laser.clone())
+                .build(),
+            PlayerBuilder::new("self")
+                .at(0.5, 1.5, 0.0)
+                .with_weapon(laser)
+                .build(),
+        ];
+
+        let danger = DangerMap::new(&map, &bullets, 10.0, 4.0).with_lasers(
+            &map,
+            &players,
+            &"self".into(),
+            0,
+        );
+
+        let cell = |x, y| Position::new(x, y, 0.0);
+        assert_eq!(danger.threat(&cell(0, 0)), 2.0);
+        assert_eq!(danger.earliest(&cell(0, 0)), Some(0.0));
+        assert_eq!(danger.earliest(&cell(2, 0)), Some(1.5));
+        assert_eq!(danger.threat(&cell(0, 3)), 3.0);
+        assert_eq!(danger.threat(&cell(3, 1)), 5.0);
+        assert_eq!(danger.threat(&cell(3, 3)), 5.0);
+        // Own laser is no threat.
+        assert!(danger.is_safe(&cell(1, 1)));
+
+        let soon = DangerMap::new(&map, &bullets, 10.0, 1.0);
+        assert!(soon.is_safe(&cell(2, 0)));
+
+        let safe = danger.safe_cells(&map, &cell(1, 2).center(), &PathOptions::new());
+        assert_eq!(safe[0], (cell(1, 2), 0.0));
+        assert_eq!(safe[1], (cell(1, 1), 1.0));
+        // All but the bottom row, the laser's column and (0, 3).
+        assert_eq!(safe.len(), 8);
+        assert!(safe.iter().all(|(cell, _)| danger.is_safe(cell)));
+        assert!(safe.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+}
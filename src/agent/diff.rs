@@ -0,0 +1,298 @@
+//! What changed between two consecutive snapshots of the game.
+//!
+//! A [`StateDiff`] compares two [`Players`] snapshots, and optionally two
+//! [`EnvironmentInfo`] snapshots, so event detection and opponent modeling
+//! work on changes instead of comparing whole snapshots themselves.
+
+use super::model::{Angle, Bullet, EnvironmentInfo, Fence, Player, PlayerToken, Players};
+use getset::{CopyGetters, Getters};
+use std::fmt::Display;
+
+/// How one player changed.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters, CopyGetters)]
+pub struct PlayerChange {
+    #[getset(get = "pub")]
+    token: PlayerToken,
+    /// Health gained, negative when the player was hit.
+    #[getset(get_copy = "pub")]
+    health_delta: i32,
+    /// Armor value gained, negative when the armor absorbed damage.
+    #[getset(get_copy = "pub")]
+    armor_delta: i32,
+    #[getset(get_copy = "pub")]
+    dx: f64,
+    #[getset(get_copy = "pub")]
+    dy: f64,
+    /// Angle turned the short way, counterclockwise positive, within ±π.
+    #[getset(get_copy = "pub")]
+    turned: Angle,
+}
+
+impl PlayerChange {
+    fn between(previous: &Player, current: &Player) -> PlayerChange {
+        let turned = current
+            .position()
+            .angle()
+            .difference(*previous.position().angle());
+        PlayerChange {
+            token: current.token().clone(),
+            health_delta: current.armor().health() - previous.armor().health(),
+            armor_delta: *current.armor().armor_value() as i32
+                - *previous.armor().armor_value() as i32,
+            dx: current.position().x() - previous.position().x(),
+            dy: current.position().y() - previous.position().y(),
+            turned,
+        }
+    }
+
+    /// Distance moved.
+    pub fn distance(&self) -> f64 {
+        self.dx.hypot(self.dy)
+    }
+
+    /// Whether the player neither moved, turned nor lost or gained health.
+    pub fn is_unchanged(&self) -> bool {
+        self.health_delta == 0
+            && self.armor_delta == 0
+            && self.dx == 0.0
+            && self.dy == 0.0
+            && self.turned == Angle::ZERO
+    }
+}
+
+/// Changes between two consecutive game snapshots.
+///
+/// Players are matched by token, fences by position and bullets by id.
+/// Players only in one of the snapshots are listed as joined or left.
+///
+/// Fields should be get through getter method `field()`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::diff::StateDiff;
+/// use thuai_8_agent_rust::agent::model::{
+///     Armor, ArmorKnifeState, EnvironmentInfo, Fence, Player, Position, Weapon,
+/// };
+///
+/// let player = |x: f64, health: i32| {
+///     Player::new(
+///         "1919810",
+///         Position::new(x, 1.0, 0.0),
+///         Weapon::new(1.0, 1.0, false, false, 10, 10, 10),
+///         Armor::new(false, false, 0, health, 0.0, ArmorKnifeState::NotOwned),
+///         Vec::new(),
+///     )
+/// };
+/// let previous = vec![player(1.0, 20)];
+/// let current = vec![player(1.5, 15)];
+/// let fence = |health| vec![Fence::new(Position::new(2, 2, 0.0), health)];
+/// let before = EnvironmentInfo::new(10, Vec::new(), fence(1), Vec::new());
+/// let after = EnvironmentInfo::new(10, Vec::new(), Vec::new(), Vec::new());
+///
+/// let diff = StateDiff::between(&previous, &current).with_environment(&before, &after);
+///
+/// let change = diff.player(&"1919810".into()).unwrap();
+/// assert_eq!(change.health_delta(), -5);
+/// assert_eq!(change.distance(), 0.5);
+/// assert_eq!(diff.fences_destroyed().len(), 1);
+/// ```
+#[derive(Debug, Default, Getters)]
+#[getset(get = "pub")]
+pub struct StateDiff<'a> {
+    players: Vec<PlayerChange>,
+    players_joined: Vec<&'a Player>,
+    players_left: Vec<&'a Player>,
+    fences_damaged: Vec<&'a Fence>,
+    fences_destroyed: Vec<&'a Fence>,
+    bullets_appeared: Vec<&'a Bullet>,
+    bullets_vanished: Vec<&'a Bullet>,
+}
+
+impl<'a> StateDiff<'a> {
+    /// Compare the `current` players against the `previous` ones.
+    pub fn between(previous: &'a Players, current: &'a Players) -> Self {
+        let find = |players: &'a Players, token: &PlayerToken| {
+            players.iter().find(|player| player.token() == token)
+        };
+        StateDiff {
+            players: current
+                .iter()
+                .filter_map(|player| {
+                    let old = find(previous, player.token())?;
+                    Some(PlayerChange::between(old, player))
+                })
+                .collect(),
+            players_joined: current
+                .iter()
+                .filter(|player| find(previous, player.token()).is_none())
+                .collect(),
+            players_left: previous
+                .iter()
+                .filter(|player| find(current, player.token()).is_none())
+                .collect(),
+            ..StateDiff::default()
+        }
+    }
+
+    /// Also compare the `current` environment against the `previous` one.
+    ///
+    /// A fence is destroyed when it had health left and now has none or is
+    /// gone.
+    pub fn with_environment(
+        mut self,
+        previous: &'a EnvironmentInfo,
+        current: &'a EnvironmentInfo,
+    ) -> Self {
+        let find = |environment: &'a EnvironmentInfo, fence: &Fence| {
+            environment.fences().iter().find(|other| {
+                other.position() == fence.position()
+                    && other.position().angle() == fence.position().angle()
+            })
+        };
+        for fence in previous.fences().iter().filter(|fence| *fence.health() > 0) {
+            match find(current, fence) {
+                Some(now) if *now.health() == 0 => self.fences_destroyed.push(fence),
+                Some(now) if now.health() < fence.health() => self.fences_damaged.push(now),
+                Some(_) => {}
+                None => self.fences_destroyed.push(fence),
+            }
+        }
+        self.bullets_appeared = current
+            .bullets()
+            .iter()
+            .filter(|bullet| !previous.bullets().iter().any(|old| old.id() == bullet.id()))
+            .collect();
+        self.bullets_vanished = previous
+            .bullets()
+            .iter()
+            .filter(|bullet| !current.bullets().iter().any(|new| new.id() == bullet.id()))
+            .collect();
+        self
+    }
+
+    /// How the player with `token` changed, if it is in both snapshots.
+    pub fn player(&self, token: &PlayerToken) -> Option<&PlayerChange> {
+        self.players.iter().find(|change| change.token() == token)
+    }
+
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.players.iter().all(PlayerChange::is_unchanged)
+            && self.players_joined.is_empty()
+            && self.players_left.is_empty()
+            && self.fences_damaged.is_empty()
+            && self.fences_destroyed.is_empty()
+            && self.bullets_appeared.is_empty()
+            && self.bullets_vanished.is_empty()
+    }
+}
+
+fn write_list<T: Display>(
+    f: &mut std::fmt::Formatter<'_>,
+    label: &str,
+    items: impl IntoIterator<Item = T>,
+) -> std::fmt::Result {
+    let mut items = items.into_iter().peekable();
+    if items.peek().is_none() {
+        return Ok(());
+    }
+    write!(f, " {label}: [")?;
+    for item in items {
+        write!(f, "{}, ", item)?;
+    }
+    write!(f, "]")
+}
+
+/// Lists what changed, players by token.
+impl Display for StateDiff<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let changed = self
+            .players
+            .iter()
+            .filter(|change| !change.is_unchanged())
+            .map(PlayerChange::token);
+        write!(f, "StateDiff {{")?;
+        write_list(f, "~Players", changed)?;
+        write_list(f, "+Players", self.players_joined.iter().map(|p| p.token()))?;
+        write_list(f, "-Players", self.players_left.iter().map(|p| p.token()))?;
+        write_list(f, "~Fences", &self.fences_damaged)?;
+        write_list(f, "-Fences", &self.fences_destroyed)?;
+        write_list(f, "+Bullets", &self.bullets_appeared)?;
+        write_list(f, "-Bullets", &self.bullets_vanished)?;
+        write!(f, " }}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::Position;
+    use crate::testing::fixtures::{PlayerBuilder, bullet};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn reports_players_fences_and_bullets() {
+        let previous = vec![
+            PlayerBuilder::new("a").at(0.0, 0.0, 0.1).build(),
+            PlayerBuilder::new("b").build(),
+        ];
+        let current = vec![
+            PlayerBuilder::new("a").at(0.0, 0.0, 2.0 * PI - 0.1).build(),
+            PlayerBuilder::new("c").build(),
+        ];
+        let diff = StateDiff::between(&previous, &current);
+        assert!(
+            diff.player(&"a".into())
+                .unwrap()
+                .turned()
+                .approx_eq(Angle::from(-0.2), 1e-9)
+        );
+        assert_eq!(diff.players_joined()[0].token(), "c");
+        assert_eq!(diff.players_left()[0].token(), "b");
+
+        let fences = vec![
+            Fence::new(Position::new(1, 1, 0.0), 3),
+            Fence::new(Position::new(1, 1, Angle::from_degrees(90.0)), 3),
+            Fence::new(Position::new(2, 2, 0.0), 3),
+        ];
+        let before = EnvironmentInfo::new(
+            5,
+            Vec::new(),
+            fences,
+            vec![bullet(1, 1.0, 1.0, 0.0), bullet(2, 1.0, 1.0, 0.0)],
+        );
+        let after = EnvironmentInfo::new(
+            5,
+            Vec::new(),
+            vec![
+                Fence::new(Position::new(1, 1, Angle::from_degrees(90.0)), 0),
+                Fence::new(Position::new(2, 2, 0.0), 1),
+            ],
+            vec![bullet(2, 1.0, 1.0, 0.0), bullet(3, 1.0, 1.0, 0.0)],
+        );
+        let diff = StateDiff::between(&current, &current).with_environment(&before, &after);
+        assert!(diff.players().iter().all(PlayerChange::is_unchanged));
+        assert_eq!(diff.fences_destroyed().len(), 2);
+        assert_eq!(diff.fences_damaged().len(), 1);
+        assert_eq!(*diff.bullets_appeared()[0].id(), 3.into());
+        assert_eq!(*diff.bullets_vanished()[0].id(), 1.into());
+        assert!(!diff.is_empty());
+
+        assert!(diff.to_string().contains("-Bullets: [Bullet: { No: 1,"));
+        assert_eq!(
+            StateDiff::between(&previous, &current).to_string(),
+            "StateDiff { ~Players: [a, ] +Players: [c, ] -Players: [b, ] }"
+        );
+
+        let still = StateDiff::between(&current, &current).with_environment(&after, &after);
+        assert!(still.is_empty());
+        assert_eq!(still.to_string(), "StateDiff { }");
+        assert_eq!(
+            StateDiff::between(&previous, &previous).players()[0].turned(),
+            Angle::ZERO
+        );
+    }
+}
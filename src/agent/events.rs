@@ -0,0 +1,280 @@
+//! Callbacks on changes of the game state.
+//!
+//! Instead of polling the getters of [`Agent`](super::Agent) in a loop, a
+//! strategy can register async callbacks for the stage changing, new player
+//! info, a new environment and buffs to choose from, e.g. with
+//! [`Agent::on_stage_change`](super::Agent::on_stage_change). Each callback
+//! gets its own copy of the new state and runs as a task of its own.
+//!
+//! For a reactive style, [`Agent::events`](super::Agent::events) streams
+//! [`GameEvent`]s, which are derived from the differences between the
+//! cached state and each update, see [`StateDiff`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use getset::CopyGetters;
+
+use super::diff::StateDiff;
+use super::model::{
+    AvailableBuffs, BuffKind, Bullet, EnvironmentInfo, Fence, PlayerToken, Players, Stage,
+};
+
+/// Events a lagging [`Agent::events`](super::Agent::events) stream may
+/// fall behind before missing some.
+pub(crate) const EVENT_CAPACITY: usize = 256;
+
+/// The future a callback returns, boxed.
+pub type EventFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+type Callback<T> = Arc<dyn Fn(T) -> EventFuture + Send + Sync>;
+
+/// The stage of the game changed.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct StageChange {
+    /// [`None`] when the stage was not known before.
+    from: Option<Stage>,
+    to: Stage,
+}
+
+impl StageChange {
+    /// Constructs a new [`StageChange`].
+    pub fn new(from: Option<Stage>, to: Stage) -> StageChange {
+        StageChange { from, to }
+    }
+}
+
+/// Something that happened in the game.
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    StageChanged(StageChange),
+    /// A player lost health or armor value; both are given as positive
+    /// amounts lost.
+    DamageTaken {
+        token: PlayerToken,
+        health: u32,
+        armor: u32,
+    },
+    /// A fence was hit but still stands, with the health it has left.
+    FenceDamaged(Fence),
+    /// A fence was broken, as it was before.
+    FenceDestroyed(Fence),
+    BulletSpawned(Bullet),
+    /// This agent selected a buff.
+    BuffSelected(BuffKind),
+    BuffsAvailable(AvailableBuffs),
+}
+
+/// Damage taken between the `previous` and `current` players.
+pub fn player_events(previous: &Players, current: &Players) -> Vec<GameEvent> {
+    StateDiff::between(previous, current)
+        .players()
+        .iter()
+        .filter(|change| change.health_delta() < 0 || change.armor_delta() < 0)
+        .map(|change| GameEvent::DamageTaken {
+            health: change.health_delta().min(0).unsigned_abs(),
+            armor: change.armor_delta().min(0).unsigned_abs(),
+            token: change.token().clone(),
+        })
+        .collect()
+}
+
+/// Fences hit and bullets fired between the `previous` and `current`
+/// environment.
+pub fn environment_events(previous: &EnvironmentInfo, current: &EnvironmentInfo) -> Vec<GameEvent> {
+    let diff = StateDiff::default().with_environment(previous, current);
+    let damaged = diff
+        .fences_damaged()
+        .iter()
+        .map(|&fence| GameEvent::FenceDamaged(fence.clone()));
+    let destroyed = diff
+        .fences_destroyed()
+        .iter()
+        .map(|&fence| GameEvent::FenceDestroyed(fence.clone()));
+    let spawned = diff
+        .bullets_appeared()
+        .iter()
+        .map(|&bullet| GameEvent::BulletSpawned(bullet.clone()));
+    damaged.chain(destroyed).chain(spawned).collect()
+}
+
+/// Box `callback` so its futures can be stored together.
+fn boxed<T, F, Fut>(callback: F) -> Callback<T>
+where
+    F: Fn(T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    Arc::new(move |value| Box::pin(callback(value)))
+}
+
+/// Run `callbacks` one after the other, each once the one before is done.
+pub(crate) async fn in_order(callbacks: Vec<EventFuture>) {
+    for callback in callbacks {
+        callback.await;
+    }
+}
+
+/// Every registered callback, by event.
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers {
+    stage: Vec<Callback<StageChange>>,
+    players: Vec<Callback<Players>>,
+    environment: Vec<Callback<EnvironmentInfo>>,
+    buffs: Vec<Callback<AvailableBuffs>>,
+}
+
+impl Subscribers {
+    pub(crate) fn on_stage_change<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(StageChange) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.stage.push(boxed(callback));
+    }
+
+    pub(crate) fn on_player_update<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(Players) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.players.push(boxed(callback));
+    }
+
+    pub(crate) fn on_environment_update<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(EnvironmentInfo) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.environment.push(boxed(callback));
+    }
+
+    pub(crate) fn on_buffs_available<F, Fut>(&mut self, callback: F)
+    where
+        F: Fn(AvailableBuffs) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.buffs.push(boxed(callback));
+    }
+
+    /// Futures of the stage change callbacks, in registration order.
+    pub(crate) fn stage_changed(&self, change: StageChange) -> Vec<EventFuture> {
+        self.stage.iter().map(|callback| callback(change)).collect()
+    }
+
+    /// Futures of the player update callbacks, in registration order.
+    pub(crate) fn players_updated(&self, players: &Players) -> Vec<EventFuture> {
+        self.players
+            .iter()
+            .map(|callback| callback(players.clone()))
+            .collect()
+    }
+
+    /// Futures of the environment update callbacks, in registration order.
+    pub(crate) fn environment_updated(&self, environment: &EnvironmentInfo) -> Vec<EventFuture> {
+        self.environment
+            .iter()
+            .map(|callback| callback(environment.clone()))
+            .collect()
+    }
+
+    /// Futures of the buffs available callbacks, in registration order.
+    pub(crate) fn buffs_available(&self, buffs: &AvailableBuffs) -> Vec<EventFuture> {
+        self.buffs
+            .iter()
+            .map(|callback| callback(buffs.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::Position;
+    use crate::testing::fixtures::{PlayerBuilder, bullet};
+    use futures::executor::block_on;
+    use std::sync::Mutex;
+    use std::task::Poll;
+
+    /// Let other futures run once.
+    async fn yield_once() {
+        let mut yielded = false;
+        std::future::poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+
+    #[test]
+    fn runs_callbacks_of_the_event_only() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut subscribers = Subscribers::default();
+        for name in ["first", "second"] {
+            let seen = seen.clone();
+            subscribers.on_stage_change(move |change: StageChange| {
+                let seen = seen.clone();
+                async move {
+                    if name == "first" {
+                        yield_once().await;
+                    }
+                    seen.lock()
+                        .unwrap()
+                        .push(format!("{name}: {:?}", change.to()));
+                }
+            });
+        }
+        let buffs_seen = seen.clone();
+        subscribers.on_buffs_available(move |buffs: AvailableBuffs| {
+            let seen = buffs_seen.clone();
+            async move { seen.lock().unwrap().push(format!("{} buffs", buffs.len())) }
+        });
+
+        let change = StageChange::new(Some(Stage::Rest), Stage::Battle);
+        block_on(in_order(subscribers.stage_changed(change)));
+        // The second waits for the first, though it yields.
+        assert_eq!(*seen.lock().unwrap(), ["first: Battle", "second: Battle"]);
+
+        assert!(subscribers.players_updated(&Vec::new()).is_empty());
+        block_on(in_order(
+            subscribers.buffs_available(&vec![BuffKind::Knife, BuffKind::Dodge]),
+        ));
+        assert_eq!(seen.lock().unwrap().last().unwrap(), "2 buffs");
+    }
+
+    #[test]
+    fn derives_damage_fences_and_bullets() {
+        let player = |health, armor| {
+            PlayerBuilder::new("a")
+                .with_health(health)
+                .with_armor_value(armor)
+                .build()
+        };
+        let events = player_events(&vec![player(20, 5)], &vec![player(18, 0)]);
+        assert!(matches!(
+            &events[..],
+            [GameEvent::DamageTaken { token, health: 2, armor: 5 }] if token == "a"
+        ));
+        assert!(player_events(&vec![player(18, 0)], &vec![player(20, 5)]).is_empty());
+
+        let fence = |x, health| Fence::new(Position::new(x, 1, 0.0), health);
+        let bullet = bullet(4, 1.0, 1.0, 0.0);
+        let before =
+            EnvironmentInfo::new(5, Vec::new(), vec![fence(1, 3), fence(2, 1)], Vec::new());
+        let after = EnvironmentInfo::new(5, Vec::new(), vec![fence(1, 2)], vec![bullet]);
+        let events = environment_events(&before, &after);
+        assert!(matches!(&events[0], GameEvent::FenceDamaged(fence) if *fence.health() == 2));
+        assert!(
+            matches!(&events[1], GameEvent::FenceDestroyed(fence) if *fence.position().x() == 2)
+        );
+        assert!(matches!(&events[2], GameEvent::BulletSpawned(bullet) if bullet.id().get() == 4));
+        assert_eq!(events.len(), 3);
+    }
+}
@@ -0,0 +1,118 @@
+//! When each part of the cached game state was last updated.
+//!
+//! The game state cached by [`Agent`](super::Agent) is only as fresh as the
+//! last reply of the server, which can be late or lost. Each part records
+//! when it was updated, so a strategy can tell whether it is acting on
+//! outdated information, see [`Agent::is_stale`](super::Agent::is_stale).
+
+use std::time::{Duration, Instant};
+
+use getset::CopyGetters;
+
+/// A part of the game state cached by [`Agent`](super::Agent).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CachedState {
+    Players,
+    Environment,
+    Statistics,
+    Buffs,
+}
+
+/// When a part of the cached state was last updated.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct Updated {
+    /// The tick of the game then, if the server announced one yet.
+    tick: Option<u64>,
+    at: Instant,
+}
+
+impl Updated {
+    /// Constructs an [`Updated`] for an update right now, at `tick`.
+    pub fn now(tick: Option<u64>) -> Updated {
+        Updated {
+            tick,
+            at: Instant::now(),
+        }
+    }
+
+    /// Time since the update.
+    pub fn age(&self) -> Duration {
+        self.at.elapsed()
+    }
+
+    /// Whether the update is older than `max_age`.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.age() > max_age
+    }
+
+    /// Ticks from the update to `current`, if both are known.
+    pub fn ticks_behind(&self, current: Option<u64>) -> Option<u64> {
+        Some(current?.saturating_sub(self.tick?))
+    }
+}
+
+/// The last update of every part of the cached state.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Freshness {
+    players: Option<Updated>,
+    environment: Option<Updated>,
+    statistics: Option<Updated>,
+    buffs: Option<Updated>,
+}
+
+impl Freshness {
+    fn slot(&mut self, state: CachedState) -> &mut Option<Updated> {
+        match state {
+            CachedState::Players => &mut self.players,
+            CachedState::Environment => &mut self.environment,
+            CachedState::Statistics => &mut self.statistics,
+            CachedState::Buffs => &mut self.buffs,
+        }
+    }
+
+    /// Record that `state` was just updated, at `tick`.
+    pub(crate) fn touch(&mut self, state: CachedState, tick: Option<u64>) {
+        *self.slot(state) = Some(Updated::now(tick));
+    }
+
+    /// The last update of `state`, [`None`] if it never was.
+    pub(crate) fn get(&self, state: CachedState) -> Option<Updated> {
+        match state {
+            CachedState::Players => self.players,
+            CachedState::Environment => self.environment,
+            CachedState::Statistics => self.statistics,
+            CachedState::Buffs => self.buffs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_updates_per_state() {
+        let mut freshness = Freshness::default();
+        assert_eq!(freshness.get(CachedState::Players), None);
+
+        freshness.touch(CachedState::Players, Some(40));
+        let updated = freshness.get(CachedState::Players).unwrap();
+        assert_eq!(updated.tick(), Some(40));
+        assert_eq!(updated.ticks_behind(Some(45)), Some(5));
+        assert_eq!(updated.ticks_behind(None), None);
+        assert!(!updated.is_stale(Duration::from_secs(60)));
+        assert_eq!(freshness.get(CachedState::Environment), None);
+
+        freshness.touch(CachedState::Buffs, None);
+        assert_eq!(
+            freshness
+                .get(CachedState::Buffs)
+                .unwrap()
+                .ticks_behind(Some(3)),
+            None
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! The last few states of the game.
+//!
+//! A [`StateHistory`] keeps a bounded number of [`Snapshot`]s, oldest first,
+//! so logic can look at how players moved and how much damage they took
+//! over the last ticks.
+
+use super::messages::ServerMessage;
+use super::model::{EnvironmentInfo, GameStatistics, Player, PlayerToken, Players, Stage};
+use getset::Getters;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Default number of snapshots a [`StateHistory`] keeps.
+pub const DEFAULT_HISTORY_DEPTH: usize = 64;
+
+/// The state of the game at one tick.
+///
+/// Should be created with [`Snapshot::new`] and then extended with the
+/// `with_*` methods.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct Snapshot {
+    tick: u32,
+    /// When the snapshot was taken.
+    taken_at: Instant,
+    players: Players,
+    environment: Option<EnvironmentInfo>,
+    statistics: Option<GameStatistics>,
+}
+
+impl Snapshot {
+    /// Constructs a [`Snapshot`] of `players` at `tick`, taken now.
+    pub fn new(tick: u32, players: Players) -> Snapshot {
+        Snapshot {
+            tick,
+            taken_at: Instant::now(),
+            players,
+            environment: None,
+            statistics: None,
+        }
+    }
+
+    pub fn with_environment(mut self, environment: EnvironmentInfo) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    pub fn with_statistics(mut self, statistics: GameStatistics) -> Self {
+        self.statistics = Some(statistics);
+        self
+    }
+
+    /// The player with `token`, if in the snapshot.
+    pub fn player(&self, token: &PlayerToken) -> Option<&Player> {
+        self.players.iter().find(|player| player.token() == token)
+    }
+
+    /// The stage of the game, if the snapshot has statistics.
+    pub fn stage(&self) -> Option<Stage> {
+        self.statistics
+            .as_ref()
+            .map(|statistics| *statistics.current_stage())
+    }
+}
+
+/// Ring buffer of the most recent [`Snapshot`]s.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::history::{Snapshot, StateHistory};
+/// use thuai_8_agent_rust::agent::model::{Armor, ArmorKnifeState, Player, Position, Weapon};
+///
+/// let player = |x: f64, health: i32| {
+///     Player::new(
+///         "1919810",
+///         Position::new(x, 0.0, 0.0),
+///         Weapon::new(1.0, 1.0, false, false, 10, 10, 10),
+///         Armor::new(false, false, 0, health, 0.0, ArmorKnifeState::NotOwned),
+///         Vec::new(),
+///     )
+/// };
+///
+/// let mut history = StateHistory::new(2);
+/// history.push(Snapshot::new(1, vec![player(0.0, 20)]));
+/// history.push(Snapshot::new(2, vec![player(1.0, 15)]));
+/// history.push(Snapshot::new(4, vec![player(2.0, 12)]));
+///
+/// // The first snapshot was dropped.
+/// assert_eq!(history.len(), 2);
+/// assert_eq!(history.player_velocity(&"1919810".into()), Some((0.5, 0.0)));
+/// assert_eq!(history.damage_taken_since(&"1919810".into(), 0), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StateHistory {
+    depth: usize,
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl Default for StateHistory {
+    fn default() -> Self {
+        StateHistory::new(DEFAULT_HISTORY_DEPTH)
+    }
+}
+
+impl StateHistory {
+    /// Constructs an empty [`StateHistory`] keeping at most `depth`
+    /// snapshots, and at least one.
+    pub fn new(depth: usize) -> StateHistory {
+        let depth = depth.max(1);
+        StateHistory {
+            depth,
+            snapshots: VecDeque::with_capacity(depth),
+        }
+    }
+
+    /// Add `snapshot` as the latest, dropping the oldest one when full.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.snapshots.len() == self.depth {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Like [`StateHistory::push`], but replaces the latest snapshot if it
+    /// is of the same tick, as a tick can be reported in several replies.
+    pub fn record(&mut self, snapshot: Snapshot) {
+        if self
+            .latest()
+            .is_some_and(|latest| latest.tick == snapshot.tick)
+        {
+            self.snapshots.pop_back();
+        }
+        self.push(snapshot);
+    }
+
+    /// Rebuild the history of a recorded match from its messages, keeping
+    /// at most `depth` snapshots.
+    ///
+    /// Every `PLAYERS_INFO` after the first `GAME_STATISTICS` is recorded
+    /// at the tick last announced, with the environment and statistics
+    /// last received.
+    pub fn replay(depth: usize, messages: impl IntoIterator<Item = ServerMessage>) -> StateHistory {
+        let mut history = StateHistory::new(depth);
+        let mut environment = None;
+        let mut statistics: Option<GameStatistics> = None;
+        for message in messages {
+            match message {
+                ServerMessage::EnvironmentInfo(info) => environment = Some(info),
+                ServerMessage::GameStatistics(info) => statistics = Some(info),
+                ServerMessage::PlayersInfo(info) => {
+                    let Some(statistics) = &statistics else {
+                        continue;
+                    };
+                    let mut snapshot = Snapshot::new(*statistics.ticks(), info.players().clone())
+                        .with_statistics(statistics.clone());
+                    if let Some(environment) = &environment {
+                        snapshot = snapshot.with_environment(environment.clone());
+                    }
+                    history.record(snapshot);
+                }
+                ServerMessage::AvailableBuffs(_) | ServerMessage::Error(_) => {}
+            }
+        }
+        history
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// The snapshots, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Snapshot> {
+        self.snapshots.iter()
+    }
+
+    pub fn latest(&self) -> Option<&Snapshot> {
+        self.snapshots.back()
+    }
+
+    /// The snapshot at `tick`, if it is still kept.
+    pub fn at_tick(&self, tick: u32) -> Option<&Snapshot> {
+        self.snapshots.iter().find(|snapshot| snapshot.tick == tick)
+    }
+
+    /// Velocity of the player with `token` per tick, between the last two
+    /// snapshots of different ticks it is in.
+    pub fn player_velocity(&self, token: &PlayerToken) -> Option<(f64, f64)> {
+        let mut seen = self.snapshots.iter().rev().filter_map(|snapshot| {
+            let player = snapshot
+                .players
+                .iter()
+                .find(|player| player.token() == token)?;
+            Some((snapshot.tick, player.position()))
+        });
+        let (tick, current) = seen.next()?;
+        let (previous_tick, previous) = seen.find(|(previous, _)| *previous < tick)?;
+        let ticks = (tick - previous_tick) as f64;
+        Some((
+            (current.x() - previous.x()) / ticks,
+            (current.y() - previous.y()) / ticks,
+        ))
+    }
+
+    /// Health and armor the player with `token` lost from the snapshots at
+    /// or after `tick`. Healing does not offset earlier damage.
+    pub fn damage_taken_since(&self, token: &PlayerToken, tick: u32) -> i32 {
+        self.snapshots
+            .iter()
+            .filter(|snapshot| snapshot.tick >= tick)
+            .filter_map(|snapshot| {
+                snapshot
+                    .players
+                    .iter()
+                    .find(|player| player.token() == token)
+                    .map(|player| player.effective_health())
+            })
+            .collect::<Vec<_>>()
+            .windows(2)
+            .map(|pair| (pair[0] - pair[1]).max(0))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::PlayerBuilder;
+
+    #[test]
+    fn sums_damage_and_ignores_healing() {
+        let mut history = StateHistory::new(8);
+        for (tick, health, armor) in [(1, 20, 5), (2, 20, 2), (3, 18, 0), (4, 20, 0), (5, 19, 0)] {
+            history.push(Snapshot::new(
+                tick,
+                vec![
+                    PlayerBuilder::new("a")
+                        .with_health(health)
+                        .with_armor_value(armor)
+                        .build(),
+                    PlayerBuilder::new("b").build(),
+                ],
+            ));
+        }
+
+        assert_eq!(history.damage_taken_since(&"a".into(), 1), 8);
+        assert_eq!(history.damage_taken_since(&"a".into(), 3), 1);
+        assert_eq!(history.damage_taken_since(&"b".into(), 1), 0);
+        assert_eq!(history.damage_taken_since(&"c".into(), 1), 0);
+        assert_eq!(history.player_velocity(&"a".into()), Some((0.0, 0.0)));
+        assert_eq!(history.at_tick(2).map(|snapshot| *snapshot.tick()), Some(2));
+        assert!(history.at_tick(6).is_none());
+
+        let mut single = StateHistory::new(0);
+        single.push(Snapshot::new(1, Vec::new()));
+        single.push(Snapshot::new(2, vec![PlayerBuilder::new("a").build()]));
+        assert_eq!(single.len(), 1);
+        assert_eq!(single.player_velocity(&"a".into()), None);
+
+        history.record(Snapshot::new(5, Vec::new()));
+        assert_eq!(history.len(), 5);
+        assert!(history.latest().unwrap().players().is_empty());
+    }
+
+    #[test]
+    fn replays_recorded_messages() {
+        let frames = [
+            r#"{"messageType":"PLAYERS_INFO","players":[]}"#,
+            r#"{"messageType":"GAME_STATISTICS","currentStage":"BATTLE","countDown":0,"ticks":7,"scores":[]}"#,
+            r#"{"messageType":"PLAYERS_INFO","players":[]}"#,
+            r#"{"messageType":"ENVIRONMENT_INFO","mapSize":10,"walls":[],"fences":[],"bullets":[]}"#,
+            r#"{"messageType":"PLAYERS_INFO","players":[]}"#,
+            r#"{"messageType":"GAME_STATISTICS","currentStage":"END","countDown":0,"ticks":8,"scores":[]}"#,
+            r#"{"messageType":"PLAYERS_INFO","players":[]}"#,
+        ];
+        let messages = frames
+            .iter()
+            .map(|frame| serde_json::from_str(frame).unwrap());
+
+        let history = StateHistory::replay(8, messages);
+
+        assert_eq!(history.len(), 2);
+        let battle = history.at_tick(7).unwrap();
+        assert_eq!(battle.stage(), Some(Stage::Battle));
+        assert!(battle.environment().is_some());
+        assert_eq!(history.latest().unwrap().stage(), Some(Stage::End));
+    }
+}
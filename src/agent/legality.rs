@@ -0,0 +1,103 @@
+//! Which actions the rules allow in which stage of the game.
+//!
+//! - During [`Stage::Rest`] only buffs may be selected.
+//! - During [`Stage::Battle`] everything but selecting buffs is allowed.
+//! - After [`Stage::End`] nothing may be performed.
+//!
+//! Queries (`GET_*` messages) are always allowed.
+
+use super::connection::PerformMessage;
+use super::model::Stage;
+use std::{error::Error, fmt::Display};
+
+/// An action that the rules forbid in the current stage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IllegalAction {
+    pub message_type: &'static str,
+    pub stage: Stage,
+}
+
+impl Display for IllegalAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not allowed in the {:?} stage",
+            self.message_type, self.stage
+        )
+    }
+}
+
+impl Error for IllegalAction {}
+
+/// Whether the rules allow sending `message` during `stage`.
+///
+/// An unknown stage (`None`, e.g. before the first statistics arrived)
+/// allows everything and leaves the decision to the server.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::connection::PerformMessage;
+/// use thuai_8_agent_rust::agent::legality;
+/// use thuai_8_agent_rust::agent::model::Stage;
+///
+/// let attack = PerformMessage::PerformAttack {
+///     token: "1919810".into(),
+/// };
+///
+/// assert!(legality::check(Some(&Stage::Battle), &attack).is_ok());
+/// assert!(legality::check(Some(&Stage::Rest), &attack).is_err());
+/// ```
+pub fn check(stage: Option<&Stage>, message: &PerformMessage) -> Result<(), IllegalAction> {
+    let Some(stage) = stage else {
+        return Ok(());
+    };
+    let allowed = match message {
+        PerformMessage::GetPlayerInfo { .. }
+        | PerformMessage::GetEnvironmentInfo { .. }
+        | PerformMessage::GetGameStatistics { .. }
+        | PerformMessage::GetAvailableBuffs { .. } => true,
+        PerformMessage::PerformSelect { .. } => *stage == Stage::Rest,
+        PerformMessage::PerformMove { .. }
+        | PerformMessage::PerformTurn { .. }
+        | PerformMessage::PerformAttack { .. }
+        | PerformMessage::PerformSkill { .. } => *stage == Stage::Battle,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(IllegalAction {
+            message_type: message.message_type(),
+            stage: *stage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::BuffKind;
+
+    #[test]
+    fn select_only_during_rest() {
+        let select = PerformMessage::PerformSelect {
+            token: "1919810".into(),
+            buff_name: BuffKind::Reflect,
+        };
+        let query = PerformMessage::GetGameStatistics {
+            token: "1919810".into(),
+        };
+
+        assert_eq!(check(Some(&Stage::Rest), &select), Ok(()));
+        assert_eq!(
+            check(Some(&Stage::Battle), &select),
+            Err(IllegalAction {
+                message_type: "PERFORM_SELECT",
+                stage: Stage::Battle
+            })
+        );
+        assert!(check(Some(&Stage::End), &select).is_err());
+        assert_eq!(check(Some(&Stage::End), &query), Ok(()));
+        assert_eq!(check(None, &select), Ok(()));
+    }
+}
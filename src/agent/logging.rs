@@ -0,0 +1,112 @@
+//! Helpers that keep the debug log readable during long matches.
+
+use super::diff::StateDiff;
+use super::model::EnvironmentInfo;
+use super::model::pretty::PrettyPrint;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// Default interval between two lines with the same key in [`throttled`].
+pub const DEFAULT_THROTTLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rate limiter for repetitive log lines.
+///
+/// Each key is allowed once per interval; lines in between are counted and
+/// reported with the next allowed one.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use thuai_8_agent_rust::agent::logging::LogThrottle;
+///
+/// let mut throttle = LogThrottle::new(Duration::from_secs(60));
+///
+/// assert_eq!(throttle.allow("queue full"), Some(0));
+/// assert_eq!(throttle.allow("queue full"), None);
+/// assert_eq!(throttle.allow("other"), Some(0));
+/// ```
+#[derive(Debug)]
+pub struct LogThrottle {
+    interval: Duration,
+    seen: HashMap<String, (Instant, u64)>,
+}
+
+impl LogThrottle {
+    /// Constructs a [`LogThrottle`] letting each key through once per `interval`.
+    pub fn new(interval: Duration) -> LogThrottle {
+        LogThrottle {
+            interval,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Returns `Some(suppressed)` if a line with `key` may be logged now,
+    /// `suppressed` being how many were held back since the last one.
+    pub fn allow(&mut self, key: &str) -> Option<u64> {
+        let now = Instant::now();
+        match self.seen.get_mut(key) {
+            Some((last, suppressed)) if now.duration_since(*last) < self.interval => {
+                *suppressed += 1;
+                None
+            }
+            Some((last, suppressed)) => {
+                *last = now;
+                Some(std::mem::take(suppressed))
+            }
+            None => {
+                self.seen.insert(key.to_string(), (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+/// Run `log` at most once per [`DEFAULT_THROTTLE_INTERVAL`] for `key`,
+/// passing it the number of suppressed lines. The throttle is shared by the
+/// whole process.
+pub fn throttled(key: &str, log: impl FnOnce(u64)) {
+    static THROTTLE: OnceLock<Mutex<LogThrottle>> = OnceLock::new();
+    let allowed = THROTTLE
+        .get_or_init(|| Mutex::new(LogThrottle::new(DEFAULT_THROTTLE_INTERVAL)))
+        .lock()
+        .unwrap()
+        .allow(key);
+    if let Some(suppressed) = allowed {
+        log(suppressed);
+    }
+}
+
+/// Logs [`EnvironmentInfo`] compactly: the full snapshot once, then only
+/// what changed since the last logged one.
+#[derive(Debug, Default)]
+pub struct EnvironmentLogger {
+    last: Option<EnvironmentInfo>,
+}
+
+impl EnvironmentLogger {
+    /// Constructs an [`EnvironmentLogger`] that has not logged anything yet.
+    pub fn new() -> EnvironmentLogger {
+        EnvironmentLogger::default()
+    }
+
+    /// Log `info` at DEBUG level, skipping it when nothing changed.
+    pub fn log(&mut self, tick: Option<u64>, info: &EnvironmentInfo) {
+        let tick = tick.map_or_else(|| "?".to_string(), |tick| tick.to_string());
+        match &self.last {
+            None => debug!("Tick {tick}: {}", info.pretty()),
+            Some(last) => {
+                let diff = StateDiff::default().with_environment(last, info);
+                if diff.is_empty() {
+                    return;
+                }
+                debug!("Tick {tick}: {}", diff);
+            }
+        }
+        self.last = Some(info.clone());
+    }
+}
@@ -0,0 +1,705 @@
+//! The battlefield as a grid of unit cells.
+//!
+//! Cell `(x, y)` is the square from `(x, y)` to `(x + 1, y + 1)`, for `x` and
+//! `y` in `0..map_size`. Walls and fences lie on the grid lines between
+//! cells, see [`symmetry`](super::symmetry) for their layout; a fence with no
+//! health left no longer blocks anything.
+
+use super::model::{
+    Bullet, BulletId, CELL_SIZE, EnvironmentInfo, Fence, Player, PlayerToken, Position, Wall,
+};
+use super::symmetry::is_vertical;
+use getset::Getters;
+use std::collections::HashMap;
+
+use crate::math::geometry::{self, Segment};
+
+/// A side of a cell, and the direction to the neighbour across it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// Towards `x - 1`.
+    Left,
+    /// Towards `x + 1`.
+    Right,
+    /// Towards `y - 1`.
+    Down,
+    /// Towards `y + 1`.
+    Up,
+}
+
+impl Side {
+    /// Every [`Side`].
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Down, Side::Up];
+
+    fn step(self) -> (i32, i32) {
+        match self {
+            Side::Left => (-1, 0),
+            Side::Right => (1, 0),
+            Side::Down => (0, -1),
+            Side::Up => (0, 1),
+        }
+    }
+}
+
+/// What blocks a cell edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Barrier {
+    Wall,
+    /// A fence, with its health left.
+    Fence(u32),
+}
+
+/// What is in a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Occupancy {
+    /// The cell is off the map.
+    OutOfBounds,
+    Empty,
+    Player(PlayerToken),
+    Bullet(BulletId),
+}
+
+/// Where a line meets a barrier, see [`GameMap::first_hit`].
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct BarrierHit {
+    barrier: Barrier,
+    point: Position<f64>,
+    /// Side of the cell the line was in.
+    side: Side,
+}
+
+/// Grid edge key: start point and whether it is vertical.
+type Edge = (i32, i32, bool);
+
+/// The grid of a map, with the barriers between cells and what is in them.
+///
+/// Cells are [`Position<i32>`]s, whose angle is ignored.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::map::{Barrier, GameMap, Side};
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Position, Wall};
+///
+/// let environment = EnvironmentInfo::new(
+///     3,
+///     vec![Wall::new(1, 0, Angle::from_degrees(90.0))],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+/// let corner = Position::new(0, 0, 0.0);
+///
+/// assert_eq!(map.barrier(&corner, Side::Right), Some(Barrier::Wall));
+/// assert_eq!(map.neighbors(&corner), vec![Position::new(0, 1, 0.0)]);
+/// assert!(!map.can_move_straight(&corner, &Position::new(2, 0, 0.0)));
+/// assert!(map.can_move_straight(&corner, &Position::new(0, 2, 0.0)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct GameMap {
+    size: i32,
+    barriers: HashMap<Edge, Barrier>,
+    occupants: HashMap<Position<i32>, Occupancy>,
+}
+
+impl GameMap {
+    /// Build the grid of `environment`, with its bullets as occupants.
+    pub fn new(environment: &EnvironmentInfo) -> GameMap {
+        let mut map = GameMap {
+            size: 0,
+            barriers: HashMap::new(),
+            occupants: HashMap::new(),
+        };
+        map.update(environment);
+        map
+    }
+
+    /// Rebuild the barriers and bullets from a newer `environment`. Players
+    /// stay where they were.
+    pub fn update(&mut self, environment: &EnvironmentInfo) {
+        self.size = *environment.map_size() as i32;
+        self.barriers.clear();
+        for wall in environment.walls() {
+            let segment = (*wall.x(), *wall.y(), is_vertical(*wall.angle()));
+            self.barriers.insert(segment, Barrier::Wall);
+        }
+        for fence in environment.fences() {
+            let position = fence.position();
+            let segment = (*position.x(), *position.y(), is_vertical(*position.angle()));
+            if *fence.health() > 0 {
+                self.barriers
+                    .entry(segment)
+                    .or_insert(Barrier::Fence(*fence.health()));
+            }
+        }
+        self.occupants
+            .retain(|_, occupant| matches!(occupant, Occupancy::Player(_)));
+        for bullet in environment.bullets() {
+            let cell = GameMap::cell_of(bullet.position());
+            self.occupants
+                .entry(cell)
+                .or_insert(Occupancy::Bullet(*bullet.id()));
+        }
+    }
+
+    /// Add `players` as occupants; they take precedence over bullets.
+    pub fn with_players(mut self, players: &[Player]) -> Self {
+        for player in players {
+            let cell = GameMap::cell_of(player.position());
+            self.occupants
+                .insert(cell, Occupancy::Player(player.token().clone()));
+        }
+        self
+    }
+
+    /// The cell `position` is in.
+    pub fn cell_of(position: &Position<f64>) -> Position<i32> {
+        position.to_cell()
+    }
+
+    /// Side length of the map, in cells.
+    pub fn size(&self) -> u32 {
+        self.size as u32
+    }
+
+    /// Whether `cell` is on the map.
+    pub fn contains(&self, cell: &Position<i32>) -> bool {
+        (0..self.size).contains(cell.x()) && (0..self.size).contains(cell.y())
+    }
+
+    /// What is in `cell`.
+    pub fn occupancy(&self, cell: &Position<i32>) -> Occupancy {
+        if !self.contains(cell) {
+            return Occupancy::OutOfBounds;
+        }
+        self.occupants
+            .get(cell)
+            .cloned()
+            .unwrap_or(Occupancy::Empty)
+    }
+
+    /// The wall or fence on `side` of `cell`, if any.
+    pub fn barrier(&self, cell: &Position<i32>, side: Side) -> Option<Barrier> {
+        let (x, y) = (*cell.x(), *cell.y());
+        let segment = match side {
+            Side::Left => (x, y, true),
+            Side::Right => (x + 1, y, true),
+            Side::Down => (x, y, false),
+            Side::Up => (x, y + 1, false),
+        };
+        self.barriers.get(&segment).copied()
+    }
+
+    /// Whether there is a wall on the segment from `(x, y)` going up if
+    /// `vertical`, right otherwise.
+    pub fn wall_at(&self, x: i32, y: i32, vertical: bool) -> bool {
+        self.barriers.get(&(x, y, vertical)) == Some(&Barrier::Wall)
+    }
+
+    /// Health of the fence on the segment from `(x, y)` going up if
+    /// `vertical`, right otherwise. Fences with no health left are gone.
+    pub fn fence_at(&self, x: i32, y: i32, vertical: bool) -> Option<u32> {
+        match self.barriers.get(&(x, y, vertical)) {
+            Some(Barrier::Fence(health)) => Some(*health),
+            _ => None,
+        }
+    }
+
+    /// Remove the wall or fence on the segment from `(x, y)`, e.g. a fence
+    /// about to be shot down, returning what was there.
+    pub fn remove_barrier(&mut self, x: i32, y: i32, vertical: bool) -> Option<Barrier> {
+        self.barriers.remove(&(x, y, vertical))
+    }
+
+    /// Draw the map as text, top row first, for debug logs.
+    ///
+    /// Every cell is one character between its edges: `|` and `-` for
+    /// walls and the map edge, `:` and `~` for fences, `+` at corners.
+    /// Players are `A`, `B`, ... in the order given, bullets are `*`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::map::GameMap;
+    /// use thuai_8_agent_rust::agent::model::{
+    ///     Angle, Bullet, EnvironmentInfo, Fence, Position, Wall,
+    /// };
+    ///
+    /// let environment = EnvironmentInfo::new(
+    ///     2,
+    ///     vec![Wall::new(1, 0, Angle::from_degrees(90.0))],
+    ///     vec![Fence::new(Position::new(0, 1, 0.0), 2)],
+    ///     Vec::new(),
+    /// );
+    /// let bullet = Bullet::new(1, false, false, Position::new(1.5, 1.5, 0.0), 1.0, 1.0, 0.0);
+    ///
+    /// let drawing = GameMap::new(&environment).render_ascii(&[], &[bullet]);
+    ///
+    /// assert_eq!(drawing, "+-+-+\n|  *|\n+~+ +\n| | |\n+-+-+");
+    /// ```
+    pub fn render_ascii(&self, players: &[Player], bullets: &[Bullet]) -> String {
+        let size = self.size.max(0) as usize;
+        let width = 2 * size + 1;
+        let mut grid = vec![vec![' '; width]; width];
+        for (row, line) in grid.iter_mut().enumerate() {
+            for (column, char) in line.iter_mut().enumerate() {
+                let (x, y) = ((column / 2) as i32, (row / 2) as i32);
+                let on_edge = |at: usize| at == 0 || at == width - 1;
+                *char = match (column % 2, row % 2) {
+                    (0, 0) => '+',
+                    (0, _) if on_edge(column) => '|',
+                    (_, 0) if on_edge(row) => '-',
+                    (0, _) => match self.barriers.get(&(x, y, true)) {
+                        Some(Barrier::Wall) => '|',
+                        Some(Barrier::Fence(_)) => ':',
+                        None => ' ',
+                    },
+                    (_, 0) => match self.barriers.get(&(x, y, false)) {
+                        Some(Barrier::Wall) => '-',
+                        Some(Barrier::Fence(_)) => '~',
+                        None => ' ',
+                    },
+                    _ => ' ',
+                };
+            }
+        }
+        let mut mark = |position: &Position<f64>, mark: char| {
+            let cell = GameMap::cell_of(position);
+            if self.contains(&cell) {
+                grid[2 * *cell.y() as usize + 1][2 * *cell.x() as usize + 1] = mark;
+            }
+        };
+        for bullet in bullets {
+            mark(bullet.position(), '*');
+        }
+        for (player, letter) in players.iter().zip('A'..='Z') {
+            mark(player.position(), letter);
+        }
+        grid.iter()
+            .rev()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Every wall and fence, as its segment.
+    pub fn segments(&self) -> impl Iterator<Item = (Segment, Barrier)> + '_ {
+        self.barriers
+            .iter()
+            .map(|(&(x, y, vertical), barrier)| (edge_segment(x, y, vertical), *barrier))
+    }
+
+    /// Whether `side` of `cell` cannot be crossed, because of a barrier or
+    /// the edge of the map.
+    pub fn is_blocked(&self, cell: &Position<i32>, side: Side) -> bool {
+        self.barrier(cell, side).is_some() || !self.contains(&neighbor(cell, side))
+    }
+
+    /// The cells next to `cell` that can be reached in one step.
+    pub fn neighbors(&self, cell: &Position<i32>) -> Vec<Position<i32>> {
+        Side::ALL
+            .into_iter()
+            .filter(|side| !self.is_blocked(cell, *side))
+            .map(|side| neighbor(cell, side))
+            .collect()
+    }
+
+    /// Whether a straight move from the centre of `from` to the centre of
+    /// `to` crosses no barrier and stays on the map.
+    ///
+    /// A line passing exactly through a grid corner counts as blocked if
+    /// either way around the corner is.
+    pub fn can_move_straight(&self, from: &Position<i32>, to: &Position<i32>) -> bool {
+        if !self.contains(from) || !self.contains(to) {
+            return false;
+        }
+        let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+        let x_side = if dx > 0 { Side::Right } else { Side::Left };
+        let y_side = if dy > 0 { Side::Up } else { Side::Down };
+        let (nx, ny) = (dx.abs(), dy.abs());
+        let (mut ix, mut iy) = (0, 0);
+        let mut cell = from.clone();
+        // Walk the cells the line crosses; the next grid line in x is hit at
+        // parameter (2 * ix + 1) / (2 * nx), compared without division.
+        while ix < nx || iy < ny {
+            let order = ((2 * ix + 1) * ny).cmp(&((2 * iy + 1) * nx));
+            if iy == ny || (ix < nx && order.is_lt()) {
+                if self.is_blocked(&cell, x_side) {
+                    return false;
+                }
+                cell = neighbor(&cell, x_side);
+                ix += 1;
+            } else if ix == nx || order.is_gt() {
+                if self.is_blocked(&cell, y_side) {
+                    return false;
+                }
+                cell = neighbor(&cell, y_side);
+                iy += 1;
+            } else {
+                let via_x = neighbor(&cell, x_side);
+                let via_y = neighbor(&cell, y_side);
+                if self.is_blocked(&cell, x_side)
+                    || self.is_blocked(&via_x, y_side)
+                    || self.is_blocked(&cell, y_side)
+                    || self.is_blocked(&via_y, x_side)
+                {
+                    return false;
+                }
+                cell = neighbor(&via_x, y_side);
+                ix += 1;
+                iy += 1;
+            }
+        }
+        true
+    }
+
+    /// Whether nothing blocks the straight line from `from` to `to`, e.g.
+    /// whether a shot would reach its target.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::map::GameMap;
+    /// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Position, Wall};
+    ///
+    /// let environment = EnvironmentInfo::new(
+    ///     3,
+    ///     vec![Wall::new(1, 0, Angle::from_degrees(90.0))],
+    ///     Vec::new(),
+    ///     Vec::new(),
+    /// );
+    /// let map = GameMap::new(&environment);
+    /// let shooter = Position::new(0.5, 0.5, 0.0);
+    ///
+    /// assert!(!map.line_of_sight(&shooter, &Position::new(2.5, 0.5, 0.0)));
+    /// assert!(map.line_of_sight(&shooter, &Position::new(1.5, 2.5, 0.0)));
+    /// ```
+    pub fn line_of_sight(&self, from: &Position<f64>, to: &Position<f64>) -> bool {
+        self.first_barrier(from, to).is_none()
+    }
+
+    /// The first wall or fence on the straight line from `from` to `to`,
+    /// with the point where the line meets it. A line through a grid corner
+    /// is stopped by a barrier on either side of the corner.
+    pub fn first_barrier(
+        &self,
+        from: &Position<f64>,
+        to: &Position<f64>,
+    ) -> Option<(Barrier, Position<f64>)> {
+        self.first_hit(from, to).map(|hit| (hit.barrier, hit.point))
+    }
+
+    /// Like [`GameMap::first_barrier`], also telling which side of the cell
+    /// the line was in the barrier is on. A start on a grid line does not
+    /// hit a barrier on that line.
+    pub fn first_hit(&self, from: &Position<f64>, to: &Position<f64>) -> Option<BarrierHit> {
+        let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+        let x_side = if dx > 0.0 { Side::Right } else { Side::Left };
+        let y_side = if dy > 0.0 { Side::Up } else { Side::Down };
+        let line = Segment::new(from.clone(), to.clone());
+        self.barriers
+            .iter()
+            .filter_map(|(&(x, y, vertical), barrier)| {
+                let point = geometry::segment_intersection(&line, &edge_segment(x, y, vertical))?;
+                let distance = from.distance_to(&point);
+                // A start on the barrier's grid line moves off it.
+                (distance > EPSILON).then_some((distance, vertical, *barrier, point))
+            })
+            // Through a corner, the barrier across x first.
+            .min_by(|(a, a_vertical, ..), (b, b_vertical, ..)| {
+                if (a - b).abs() < EPSILON {
+                    b_vertical.cmp(a_vertical)
+                } else {
+                    a.total_cmp(b)
+                }
+            })
+            .map(|(_, vertical, barrier, point)| BarrierHit {
+                barrier,
+                point: Position::new(*point.x(), *point.y(), *from.angle()),
+                side: if vertical { x_side } else { y_side },
+            })
+    }
+}
+
+/// Tolerance when comparing where a line crosses grid lines.
+const EPSILON: f64 = 1e-9;
+
+fn neighbor(cell: &Position<i32>, side: Side) -> Position<i32> {
+    let (dx, dy) = side.step();
+    Position::new(cell.x() + dx, cell.y() + dy, *cell.angle())
+}
+
+/// The segment of the wall or fence on the grid edge from `(x, y)`, going
+/// up if `vertical` and right otherwise.
+pub fn edge_segment(x: i32, y: i32, vertical: bool) -> Segment {
+    let start = Position::new(x as f64 * CELL_SIZE, y as f64 * CELL_SIZE, 0.0);
+    let end = if vertical {
+        start.offset(0.0, CELL_SIZE)
+    } else {
+        start.offset(CELL_SIZE, 0.0)
+    };
+    Segment::new(start, end)
+}
+
+/// Walls passing within `radius` of `position`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::map;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Fence, Position, Wall};
+///
+/// let vertical = Angle::from_degrees(90.0);
+/// let environment = EnvironmentInfo::new(
+///     10,
+///     vec![Wall::new(2, 2, vertical), Wall::new(6, 2, Angle::ZERO)],
+///     vec![
+///         Fence::new(Position::new(3, 4, Angle::ZERO), 2),
+///         // Broken, so left out.
+///         Fence::new(Position::new(3, 3, Angle::ZERO), 0),
+///     ],
+///     Vec::new(),
+/// );
+/// let me = Position::new(3.0, 2.5, 0.0);
+///
+/// // The vertical wall from (2, 2) to (2, 3) is 1 away.
+/// assert_eq!(map::walls_within(&environment, &me, 1.0).len(), 1);
+/// assert_eq!(map::walls_within(&environment, &me, 3.5).len(), 2);
+/// assert!(map::fences_within(&environment, &me, 1.0).is_empty());
+/// assert_eq!(map::fences_within(&environment, &me, 1.5)[0].health(), &2);
+/// assert_eq!(map::fences_within(&environment, &me, 1.5).len(), 1);
+/// ```
+pub fn walls_within<'a>(
+    environment: &'a EnvironmentInfo,
+    position: &Position<f64>,
+    radius: f64,
+) -> Vec<&'a Wall> {
+    environment
+        .walls()
+        .iter()
+        .filter(|wall| {
+            let segment = edge_segment(*wall.x(), *wall.y(), is_vertical(*wall.angle()));
+            geometry::point_segment_distance(position, &segment) <= radius
+        })
+        .collect()
+}
+
+/// Fences still standing within `radius` of `position`.
+pub fn fences_within<'a>(
+    environment: &'a EnvironmentInfo,
+    position: &Position<f64>,
+    radius: f64,
+) -> Vec<&'a Fence> {
+    environment
+        .fences()
+        .iter()
+        .filter(|fence| *fence.health() > 0)
+        .filter(|fence| {
+            let at = fence.position();
+            let segment = edge_segment(*at.x(), *at.y(), is_vertical(*at.angle()));
+            geometry::point_segment_distance(position, &segment) <= radius
+        })
+        .collect()
+}
+
+/// Bullets ordered by how close they come to `position` flying straight
+/// on, and then by how soon. Bullets flying away count from where they
+/// are now.
+///
+/// Bounces are ignored; see [`ballistics::time_to_reach`](super::ballistics::time_to_reach)
+/// for those.
+///
+/// # Example
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use thuai_8_agent_rust::agent::map;
+/// use thuai_8_agent_rust::agent::model::{Bullet, EnvironmentInfo, Position};
+///
+/// let bullet = |id, x, y, angle| Bullet::new(id, false, false, Position::new(x, y, angle), 1.0, 1.0, 0.0);
+/// let environment = EnvironmentInfo::new(
+///     10,
+///     Vec::new(),
+///     Vec::new(),
+///     vec![
+///         // Flying away, 2 behind.
+///         bullet(1, 3.0, 5.0, PI),
+///         // Passing 1 away.
+///         bullet(2, 2.0, 6.0, 0.0),
+///         // Straight at me, from far and from close.
+///         bullet(3, 9.0, 5.0, PI),
+///         bullet(4, 7.0, 5.0, PI),
+///     ],
+/// );
+///
+/// let order: Vec<u32> = map::bullets_sorted_by_threat(&environment, &Position::new(5.0, 5.0, 0.0))
+///     .iter()
+///     .map(|bullet| bullet.id().get())
+///     .collect();
+///
+/// assert_eq!(order, [4, 3, 2, 1]);
+/// ```
+pub fn bullets_sorted_by_threat<'a>(
+    environment: &'a EnvironmentInfo,
+    position: &Position<f64>,
+) -> Vec<&'a Bullet> {
+    let mut bullets: Vec<_> = environment
+        .bullets()
+        .iter()
+        .map(|bullet| (closest_approach(bullet, position), bullet))
+        .collect();
+    bullets.sort_by(|((a_miss, a_time), _), ((b_miss, b_time), _)| {
+        a_miss.total_cmp(b_miss).then(a_time.total_cmp(b_time))
+    });
+    bullets.into_iter().map(|(_, bullet)| bullet).collect()
+}
+
+/// How far from `position` `bullet` passes flying straight on, and the time
+/// until it is closest.
+fn closest_approach(bullet: &Bullet, position: &Position<f64>) -> (f64, f64) {
+    let (sin, cos) = bullet.position().angle().sin_cos();
+    let (dx, dy) = (
+        position.x() - bullet.position().x(),
+        position.y() - bullet.position().y(),
+    );
+    let along = (dx * cos + dy * sin).max(0.0);
+    let miss = (dx - along * cos).hypot(dy - along * sin);
+    let time = if along == 0.0 {
+        0.0
+    } else if *bullet.speed() > 0.0 {
+        along / bullet.speed()
+    } else {
+        f64::INFINITY
+    };
+    (miss, time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{Angle, Bullet, Fence, Wall};
+    use crate::testing::fixtures;
+
+    fn cell(x: i32, y: i32) -> Position<i32> {
+        Position::new(x, y, 0.0)
+    }
+
+    #[test]
+    fn blocks_moves_across_barriers() {
+        let environment = EnvironmentInfo::new(
+            4,
+            vec![Wall::new(2, 0, Angle::from_degrees(90.0))],
+            vec![
+                Fence::new(Position::new(0, 2, 0.0), 3),
+                Fence::new(Position::new(1, 2, 0.0), 0),
+            ],
+            Vec::new(),
+        );
+        let map = GameMap::new(&environment);
+
+        assert_eq!(map.barrier(&cell(0, 1), Side::Up), Some(Barrier::Fence(3)));
+        assert_eq!(map.barrier(&cell(1, 1), Side::Up), None);
+        assert!(map.is_blocked(&cell(0, 0), Side::Left));
+        assert_eq!(map.neighbors(&cell(1, 0)), vec![cell(0, 0), cell(1, 1)]);
+
+        assert!(!map.can_move_straight(&cell(0, 0), &cell(3, 0)));
+        assert!(map.can_move_straight(&cell(0, 1), &cell(3, 1)));
+        assert!(!map.can_move_straight(&cell(0, 0), &cell(0, 3)));
+        assert!(map.can_move_straight(&cell(1, 0), &cell(1, 3)));
+        // Through the corner at (2, 1), next to the wall.
+        assert!(!map.can_move_straight(&cell(1, 0), &cell(2, 1)));
+        assert!(map.can_move_straight(&cell(1, 1), &cell(2, 2)));
+        assert!(!map.can_move_straight(&cell(0, 0), &cell(4, 0)));
+    }
+
+    #[test]
+    fn finds_first_barrier() {
+        let environment = EnvironmentInfo::new(
+            4,
+            vec![
+                Wall::new(2, 0, Angle::from_degrees(90.0)),
+                Wall::new(1, 3, Angle::from_degrees(0.0)),
+            ],
+            vec![Fence::new(
+                Position::new(3, 1, Angle::from_degrees(90.0)),
+                2,
+            )],
+            Vec::new(),
+        );
+        let map = GameMap::new(&environment);
+        let at = |x, y| Position::new(x, y, 0.0);
+
+        let (barrier, point) = map.first_barrier(&at(0.5, 1.5), &at(3.5, 1.5)).unwrap();
+        assert_eq!(barrier, Barrier::Fence(2));
+        assert_eq!(point, at(3.0, 1.5));
+        let (barrier, point) = map.first_barrier(&at(3.5, 0.5), &at(0.5, 0.5)).unwrap();
+        assert_eq!(barrier, Barrier::Wall);
+        assert_eq!(point, at(2.0, 0.5));
+        assert_eq!(
+            map.first_barrier(&at(1.5, 3.5), &at(1.5, 0.5)),
+            Some((Barrier::Wall, at(1.5, 3.0)))
+        );
+        // Through the corner at (2, 1), at the top of the wall.
+        assert!(!map.line_of_sight(&at(1.5, 0.5), &at(2.5, 1.5)));
+        assert!(map.line_of_sight(&at(0.5, 1.5), &at(2.5, 2.5)));
+        assert!(map.line_of_sight(&at(3.5, 2.5), &at(3.5, 2.5)));
+        // Leaving the wall at x = 2 either way.
+        assert!(map.line_of_sight(&at(2.0, 0.5), &at(0.5, 0.5)));
+        assert!(map.line_of_sight(&at(2.0, 0.5), &at(3.5, 0.5)));
+        let hit = map.first_hit(&at(0.5, 0.5), &at(3.5, 0.5)).unwrap();
+        assert_eq!(hit.side(), &Side::Right);
+    }
+
+    #[test]
+    fn tracks_occupants() {
+        let bullet = Bullet::new(7, false, false, Position::new(3.2, 5.9, 0.0), 1.0, 1.0, 0.0);
+        let environment = EnvironmentInfo::new(10, Vec::new(), Vec::new(), vec![bullet]);
+        let players = fixtures::open_field().players_info.unwrap();
+        let map = GameMap::new(&environment).with_players(&players);
+
+        assert_eq!(
+            map.occupancy(&cell(6, 5)),
+            Occupancy::Player(fixtures::OPPONENT_TOKEN.into())
+        );
+        // The agent stands on the bullet.
+        assert_eq!(
+            map.occupancy(&cell(3, 5)),
+            Occupancy::Player(fixtures::SELF_TOKEN.into())
+        );
+        assert_eq!(map.occupancy(&cell(0, 0)), Occupancy::Empty);
+        assert_eq!(map.occupancy(&cell(10, 0)), Occupancy::OutOfBounds);
+    }
+
+    #[test]
+    fn indexes_and_updates_barriers() {
+        let mut map = GameMap::new(&EnvironmentInfo::new(
+            4,
+            vec![Wall::new(1, 2, Angle::from_degrees(90.0))],
+            vec![Fence::new(Position::new(2, 1, 0.0), 3)],
+            Vec::new(),
+        ));
+        assert!(map.wall_at(1, 2, true));
+        assert!(!map.wall_at(1, 2, false));
+        assert_eq!(map.fence_at(2, 1, false), Some(3));
+        assert_eq!(map.fence_at(1, 2, true), None);
+
+        let players = fixtures::open_field().players_info.unwrap();
+        map = map.with_players(&players);
+        map.update(&EnvironmentInfo::new(
+            10,
+            vec![Wall::new(1, 2, Angle::from_degrees(90.0))],
+            vec![Fence::new(Position::new(2, 1, 0.0), 0)],
+            Vec::new(),
+        ));
+        assert_eq!(map.size(), 10);
+        assert!(map.wall_at(1, 2, true));
+        assert_eq!(map.fence_at(2, 1, false), None);
+        assert_eq!(
+            map.occupancy(&cell(6, 5)),
+            Occupancy::Player(fixtures::OPPONENT_TOKEN.into())
+        );
+    }
+}
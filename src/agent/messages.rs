@@ -0,0 +1,174 @@
+/*!
+Messages the server sends, typed.
+
+Mirrors [`PerformMessage`](super::connection::PerformMessage) on the receive
+side: a frame taken with [`AgentClient::recv`](super::connection::AgentClient::recv)
+parses into a [`ServerMessage`] by its `messageType`.
+
+The server has no message of its own for stage changes; the stage arrives
+in every `GAME_STATISTICS`, see [`ServerMessage::stage`].
+*/
+use std::fmt::Display;
+
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+
+use super::model::{AvailableBuffs, EnvironmentInfo, GameStatistics, Players, Stage};
+
+/// Body of a `PLAYERS_INFO` message.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct PlayersInfo {
+    #[serde(rename = "players")]
+    players: Players,
+}
+
+impl PlayersInfo {
+    pub fn new(players: Players) -> PlayersInfo {
+        PlayersInfo { players }
+    }
+}
+
+/// Body of an `AVAILABLE_BUFFS` message.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct AvailableBuffsInfo {
+    #[serde(rename = "buffs")]
+    buffs: AvailableBuffs,
+}
+
+impl AvailableBuffsInfo {
+    pub fn new(buffs: AvailableBuffs) -> AvailableBuffsInfo {
+        AvailableBuffsInfo { buffs }
+    }
+}
+
+/// Body of an `ERROR` message, sent in reply to a request the server
+/// rejected.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct ServerError {
+    #[serde(rename = "errorCode")]
+    error_code: i32,
+    #[serde(rename = "message")]
+    message: String,
+}
+
+impl ServerError {
+    pub fn new(error_code: i32, message: impl Into<String>) -> ServerError {
+        ServerError {
+            error_code,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server error {}: {}", self.error_code, self.message)
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+/// Any message the server sends, tagged by `messageType`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::messages::ServerMessage;
+///
+/// let frame = r#"{"messageType":"ERROR","errorCode":3,"message":"Invalid token"}"#;
+///
+/// let ServerMessage::Error(error) = serde_json::from_str(frame).unwrap() else {
+///     panic!("Not an error");
+/// };
+///
+/// assert_eq!(error.error_code(), &3);
+/// assert_eq!(error.message(), "Invalid token");
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "messageType")]
+pub enum ServerMessage {
+    /// Older servers call it `PLAYER_INFO`.
+    #[serde(rename = "PLAYERS_INFO", alias = "PLAYER_INFO")]
+    PlayersInfo(PlayersInfo),
+    #[serde(rename = "ENVIRONMENT_INFO")]
+    EnvironmentInfo(EnvironmentInfo),
+    #[serde(rename = "GAME_STATISTICS")]
+    GameStatistics(GameStatistics),
+    #[serde(rename = "AVAILABLE_BUFFS")]
+    AvailableBuffs(AvailableBuffsInfo),
+    #[serde(rename = "ERROR")]
+    Error(ServerError),
+}
+
+impl ServerMessage {
+    /// The `messageType` the message is serialized with.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            ServerMessage::PlayersInfo(_) => "PLAYERS_INFO",
+            ServerMessage::EnvironmentInfo(_) => "ENVIRONMENT_INFO",
+            ServerMessage::GameStatistics(_) => "GAME_STATISTICS",
+            ServerMessage::AvailableBuffs(_) => "AVAILABLE_BUFFS",
+            ServerMessage::Error(_) => "ERROR",
+        }
+    }
+
+    /// The stage the game is in, if the message tells it.
+    pub fn stage(&self) -> Option<Stage> {
+        match self {
+            ServerMessage::GameStatistics(statistics) => Some(*statistics.current_stage()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::BuffKind;
+
+    #[test]
+    fn parses_every_message_type() {
+        let frames = [
+            r#"{"messageType":"PLAYER_INFO","players":[]}"#,
+            r#"{"messageType":"ENVIRONMENT_INFO","mapSize":10,"walls":[],"fences":[],"bullets":[]}"#,
+            r#"{"messageType":"GAME_STATISTICS","currentStage":"END","countDown":0,"ticks":9,"scores":[]}"#,
+            r#"{"messageType":"AVAILABLE_BUFFS","buffs":["KNIFE","DODGE"]}"#,
+            r#"{"messageType":"ERROR","errorCode":1,"message":"Not your turn"}"#,
+        ];
+
+        let messages: Vec<ServerMessage> = frames
+            .iter()
+            .map(|frame| serde_json::from_str(frame).unwrap())
+            .collect();
+
+        let types: Vec<&str> = messages.iter().map(ServerMessage::message_type).collect();
+        assert_eq!(
+            types,
+            [
+                "PLAYERS_INFO",
+                "ENVIRONMENT_INFO",
+                "GAME_STATISTICS",
+                "AVAILABLE_BUFFS",
+                "ERROR"
+            ]
+        );
+        assert_eq!(messages[2].stage(), Some(Stage::End));
+        let ServerMessage::AvailableBuffs(buffs) = &messages[3] else {
+            panic!("Not available buffs");
+        };
+        assert_eq!(buffs.buffs(), &[BuffKind::Knife, BuffKind::Dodge]);
+        assert_eq!(
+            serde_json::to_string(&messages[0]).unwrap(),
+            r#"{"messageType":"PLAYERS_INFO","players":[]}"#
+        );
+    }
+}
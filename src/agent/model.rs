@@ -1,8 +1,19 @@
+pub mod angle;
+pub mod buff_table;
+pub mod pretty;
+pub mod strict;
+
+pub use angle::Angle;
+
+use std::cmp::Ordering;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
 
 use getset::Getters;
 
 use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 
 // Position Things
 const EPSILON: f64 = 1e-6;
@@ -13,6 +24,9 @@ const EPSILON: f64 = 1e-6;
 /// You **Shouldn't** use [`Position<T>`] where `T` is not [`i32`] or [`f64`].
 /// [`PartialEq`] doesn't compare angles.
 ///
+/// [`Position<i32>`] is also [`Eq`], [`Hash`] and [`Ord`], ignoring the angle
+/// as well, so grid cells can go into sets and maps. They are ordered by `y`,
+/// then `x`, i.e. row by row.
 ///
 /// [`Display`] is implemented for `T` implements [`Serialize`], thus [`Position<i32>`]
 /// and [`Position<f64>`] both can be formatted printed.
@@ -21,7 +35,8 @@ const EPSILON: f64 = 1e-6;
 ///
 /// Fields should be get through getter method `field()`.
 ///
-/// `angle` is represented in `rad`, but not limited in $[0,2\pi)$ or $[-\pi,\pi)$.
+/// `angle` is an [`Angle`], sent in `rad`, but not limited in $[0,2\pi)$ or
+/// $[-\pi,\pi)$.
 ///
 /// # Example
 ///
@@ -64,7 +79,7 @@ const EPSILON: f64 = 1e-6;
 pub struct Position<T> {
     x: T,
     y: T,
-    angle: f64,
+    angle: Angle,
 }
 
 impl PartialEq for Position<i32> {
@@ -73,6 +88,40 @@ impl PartialEq for Position<i32> {
     }
 }
 
+impl Eq for Position<i32> {}
+
+impl Hash for Position<i32> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.x, self.y).hash(state);
+    }
+}
+
+/// Row by row: by `y`, then `x`.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::{BTreeSet, HashSet};
+/// use thuai_8_agent_rust::agent::model::Position;
+///
+/// let visited: HashSet<_> = [Position::new(1, 2, 0.0), Position::new(1, 2, 3.0)].into();
+/// assert_eq!(visited.len(), 1);
+///
+/// let cells: BTreeSet<_> = [Position::new(0, 1, 0.0), Position::new(1, 0, 0.0)].into();
+/// assert_eq!(cells.first(), Some(&Position::new(1, 0, 0.0)));
+/// ```
+impl Ord for Position<i32> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.y, self.x).cmp(&(other.y, other.x))
+    }
+}
+
+impl PartialOrd for Position<i32> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl PartialEq for Position<f64> {
     fn eq(&self, other: &Self) -> bool {
         (self.x - other.x).abs() < EPSILON && (self.y - other.y).abs() < EPSILON
@@ -93,9 +142,311 @@ where
 }
 
 impl<T> Position<T> {
-    /// Constructs a new [`Position<T>`] with `{ x, y, angle }`
-    pub fn new(x: T, y: T, angle: f64) -> Position<T> {
-        Position { x, y, angle }
+    /// Constructs a new [`Position<T>`] with `{ x, y, angle }`, `angle` in
+    /// `rad` if given as a number.
+    pub fn new(x: T, y: T, angle: impl Into<Angle>) -> Position<T> {
+        Position {
+            x,
+            y,
+            angle: angle.into(),
+        }
+    }
+}
+
+/// Side length of a grid cell, in the unit of positions. Walls and fences
+/// are one cell long.
+pub const CELL_SIZE: f64 = 1.0;
+
+/// Conversion between grid cells and continuous positions. Cell `(x, y)`
+/// covers `x * CELL_SIZE` up to `(x + 1) * CELL_SIZE` along x, and likewise
+/// along y. Angles are kept.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::Position;
+///
+/// let cell = Position::new(2.7, 0.2, 1.0).to_cell();
+///
+/// assert_eq!(cell, Position::new(2, 0, 1.0));
+/// assert_eq!(cell.center(), Position::new(2.5, 0.5, 1.0));
+/// assert_eq!(Position::new(-0.5, 1.0, 0.0).to_cell(), Position::new(-1, 1, 0.0));
+/// ```
+impl Position<i32> {
+    /// The centre of the cell.
+    pub fn center(&self) -> Position<f64> {
+        Position::new(
+            (self.x as f64 + 0.5) * CELL_SIZE,
+            (self.y as f64 + 0.5) * CELL_SIZE,
+            self.angle,
+        )
+    }
+}
+
+/// Vector math, treating positions as points or as vectors from the origin.
+/// Angles are in `rad`, counterclockwise from the x axis.
+///
+/// # Example
+///
+/// ```
+/// use std::f64::consts::FRAC_PI_2;
+/// use thuai_8_agent_rust::agent::model::Position;
+///
+/// let origin = Position::new(0.0, 0.0, 0.0);
+/// let target = Position::new(3.0, 4.0, 0.0);
+///
+/// assert_eq!(origin.distance_to(&target), 5.0);
+/// assert_eq!(origin.offset(3.0, 4.0), target);
+/// assert_eq!(target.dot(&target), 25.0);
+/// assert_eq!(
+///     Position::new(1.0, 0.0, 0.0).rotate_around(&origin, FRAC_PI_2),
+///     Position::new(0.0, 1.0, 0.0)
+/// );
+/// assert!(origin.angle_to(&Position::new(0.0, 2.0, 0.0)).approx_eq(FRAC_PI_2.into(), 1e-9));
+/// ```
+impl Position<f64> {
+    /// The grid cell the position is in.
+    pub fn to_cell(&self) -> Position<i32> {
+        Position::new(
+            (self.x / CELL_SIZE).floor() as i32,
+            (self.y / CELL_SIZE).floor() as i32,
+            self.angle,
+        )
+    }
+
+    /// Euclidean distance to `other`.
+    pub fn distance_to(&self, other: &Position<f64>) -> f64 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
+
+    /// Direction from `self` towards `other`, in $(-\pi,\pi]$.
+    pub fn angle_to(&self, other: &Position<f64>) -> Angle {
+        Angle::from_radians((other.y - self.y).atan2(other.x - self.x))
+    }
+
+    /// The position moved by `(dx, dy)`, facing the same way.
+    pub fn offset(&self, dx: f64, dy: f64) -> Position<f64> {
+        Position::new(self.x + dx, self.y + dy, self.angle)
+    }
+
+    /// The position rotated counterclockwise by `angle` around `center`; its
+    /// facing turns along.
+    pub fn rotate_around(&self, center: &Position<f64>, angle: impl Into<Angle>) -> Position<f64> {
+        let angle = angle.into();
+        let (sin, cos) = angle.sin_cos();
+        let (dx, dy) = (self.x - center.x, self.y - center.y);
+        Position::new(
+            center.x + dx * cos - dy * sin,
+            center.y + dx * sin + dy * cos,
+            self.angle + angle,
+        )
+    }
+
+    /// Dot product of both positions as vectors.
+    pub fn dot(&self, other: &Position<f64>) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Z component of the cross product of both positions as vectors;
+    /// positive when `other` is counterclockwise of `self`.
+    pub fn cross(&self, other: &Position<f64>) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    /// The position a fraction `t` of the way to `other`, e.g. between two
+    /// info updates. The facing turns the short way round; `t` outside 0 to 1
+    /// extrapolates along the line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::f64::consts::PI;
+    /// use thuai_8_agent_rust::agent::model::{Angle, Position};
+    ///
+    /// let from = Position::new(0.0, 0.0, 0.1);
+    /// let to = Position::new(2.0, 4.0, 2.0 * PI - 0.1);
+    ///
+    /// let halfway = from.lerp(&to, 0.5);
+    ///
+    /// assert_eq!(halfway, Position::new(1.0, 2.0, 0.0));
+    /// assert!(halfway.angle().approx_eq(Angle::ZERO, 1e-9));
+    /// ```
+    pub fn lerp(&self, other: &Position<f64>, t: f64) -> Position<f64> {
+        let (direction, turn) = self.angle.turn_to(other.angle);
+        let turn = match direction {
+            TurnDirection::Clockwise => -turn,
+            TurnDirection::CounterClockwise => turn,
+        };
+        Position::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+            self.angle + turn * t,
+        )
+    }
+
+    /// Where the position will be after moving at `speed` along `heading`
+    /// (in `rad`) for `dt`, in the same time unit as `speed`. The facing is
+    /// kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::f64::consts::FRAC_PI_2;
+    /// use thuai_8_agent_rust::agent::model::Position;
+    ///
+    /// let bullet = Position::new(1.0, 1.0, FRAC_PI_2);
+    ///
+    /// assert_eq!(
+    ///     bullet.extrapolate(2.0, *bullet.angle(), 1.5),
+    ///     Position::new(1.0, 4.0, FRAC_PI_2)
+    /// );
+    /// ```
+    pub fn extrapolate(&self, speed: f64, heading: impl Into<Angle>, dt: f64) -> Position<f64> {
+        let (sin, cos) = heading.into().sin_cos();
+        let distance = speed * dt;
+        self.offset(distance * cos, distance * sin)
+    }
+}
+
+// Identifier Things...
+
+/// Characters of a [`PlayerToken`] shown by its [`Debug`] output.
+const TOKEN_DEBUG_PREFIX: usize = 2;
+
+/// Token identifying a player, as used by the server.
+///
+/// [`Display`] prints the whole token, as sent to the server, while
+/// [`Debug`] only prints its start, so tokens do not leak into debug logs
+/// and panic messages.
+///
+/// Can be compared with [`str`] and converted from [`String`] and `&str`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::PlayerToken;
+///
+/// let token = PlayerToken::from("1919810");
+///
+/// assert_eq!(token, "1919810");
+/// assert_eq!(token.to_string(), "1919810");
+/// assert_eq!(format!("{:?}", token), r#"PlayerToken("19…")"#);
+/// assert_eq!(serde_json::to_string(&token).unwrap(), r#""1919810""#);
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PlayerToken(String);
+
+impl PlayerToken {
+    /// Constructs a new [`PlayerToken`].
+    pub fn new(token: impl Into<String>) -> PlayerToken {
+        PlayerToken(token.into())
+    }
+
+    /// The whole token.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the token is empty, which the server never accepts.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl std::fmt::Debug for PlayerToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let shown: String = self.0.chars().take(TOKEN_DEBUG_PREFIX).collect();
+        let ellipsis = if shown.len() < self.0.len() {
+            "…"
+        } else {
+            ""
+        };
+        write!(f, "PlayerToken(\"{shown}{ellipsis}\")")
+    }
+}
+
+impl Display for PlayerToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for PlayerToken {
+    fn from(token: String) -> Self {
+        PlayerToken(token)
+    }
+}
+
+impl From<&str> for PlayerToken {
+    fn from(token: &str) -> Self {
+        PlayerToken(token.to_string())
+    }
+}
+
+impl AsRef<str> for PlayerToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::borrow::Borrow<str> for PlayerToken {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for PlayerToken {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for PlayerToken {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+/// Identifier of a [`Bullet`], what the server calls its `no`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::BulletId;
+///
+/// let id: BulletId = serde_json::from_str("7").unwrap();
+///
+/// assert_eq!(id, BulletId::new(7));
+/// assert_eq!(id.get(), 7);
+/// ```
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct BulletId(u32);
+
+impl BulletId {
+    /// Constructs a new [`BulletId`].
+    pub fn new(id: u32) -> BulletId {
+        BulletId(id)
+    }
+
+    /// The raw id.
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl Display for BulletId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u32> for BulletId {
+    fn from(id: u32) -> Self {
+        BulletId(id)
     }
 }
 
@@ -123,7 +474,7 @@ impl<T> Position<T> {
 ///
 /// assert_eq!(stage, Stage::Battle);
 /// ```
-#[derive(Debug, EnumString, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, EnumString, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum Stage {
     #[serde(rename = "REST")]
     Rest,
@@ -138,10 +489,23 @@ pub enum Stage {
 /// Should be created with [`TokenScore::new`].
 ///
 /// Fields should be get through getter method `field()`.
-#[derive(Debug, PartialEq, Getters)]
+///
+/// # Example
+///
+/// Serialize
+/// ```
+/// use thuai_8_agent_rust::agent::model::TokenScore;
+///
+/// let score = TokenScore::new("1919810".to_string(), 3);
+///
+/// let data = serde_json::to_string(&score).unwrap();
+///
+/// assert_eq!(data, r#"{"token":"1919810","score":3}"#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
 #[getset(get = "pub")]
 pub struct TokenScore {
-    token: String,
+    token: PlayerToken,
     score: u32,
 }
 
@@ -150,7 +514,10 @@ pub struct TokenScore {
 /// Should be created with [`ScoreBoard::new`].
 ///
 /// Fields should be get through getter method `field()`.
-#[derive(Debug, Getters)]
+///
+/// (De)serialized as a plain list of [`TokenScore`]s.
+#[derive(Debug, Clone, Default, Getters, Serialize, Deserialize)]
+#[serde(transparent)]
 #[getset(get = "pub")]
 pub struct ScoreBoard {
     scores: Vec<TokenScore>,
@@ -165,18 +532,46 @@ pub struct ScoreBoard {
 /// Should be created with [`GameStatistics::new`].
 ///
 /// Fields should be get through getter method `field()`.
-#[derive(Debug, Getters)]
+///
+/// # Example
+///
+/// Deserialize
+/// ```
+/// use thuai_8_agent_rust::agent::model::{GameStatistics, Stage};
+///
+/// let data = r#"{
+///     "messageType": "GAME_STATISTICS",
+///     "currentStage": "BATTLE",
+///     "countDown": 30,
+///     "ticks": 200,
+///     "scores": [{"token": "1919810", "score": 3}]
+/// }"#;
+///
+/// let statistics: GameStatistics = serde_json::from_str(data).unwrap();
+///
+/// assert_eq!(statistics.current_stage(), &Stage::Battle);
+/// assert_eq!(statistics.ticks(), &200);
+/// assert_eq!(statistics.scores().scores()[0].score(), &3);
+/// ```
+#[derive(Debug, Clone, Getters, Serialize, Deserialize)]
 #[getset(get = "pub")]
 pub struct GameStatistics {
+    #[serde(rename = "currentStage")]
     current_stage: Stage,
+    #[serde(rename = "countDown", default)]
     count_down: u32,
+    #[serde(rename = "ticks", default)]
     ticks: u32,
+    #[serde(rename = "scores", default)]
     scores: ScoreBoard,
 }
 
 impl TokenScore {
-    pub fn new(token: String, score: u32) -> TokenScore {
-        TokenScore { token, score }
+    pub fn new(token: impl Into<PlayerToken>, score: u32) -> TokenScore {
+        TokenScore {
+            token: token.into(),
+            score,
+        }
     }
 }
 
@@ -184,6 +579,61 @@ impl ScoreBoard {
     pub fn new(scores: Vec<TokenScore>) -> ScoreBoard {
         ScoreBoard { scores }
     }
+
+    /// Score of the player with `token`, if listed.
+    pub fn score_of(&self, token: &PlayerToken) -> Option<u32> {
+        self.scores
+            .iter()
+            .find(|entry| &entry.token == token)
+            .map(|entry| entry.score)
+    }
+
+    /// Rank of the player with `token`, starting from 1. Players with the
+    /// same score share a rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::{ScoreBoard, TokenScore};
+    ///
+    /// let board = ScoreBoard::new(vec![
+    ///     TokenScore::new("a", 2),
+    ///     TokenScore::new("b", 5),
+    ///     TokenScore::new("c", 5),
+    /// ]);
+    ///
+    /// assert_eq!(board.score_of(&"a".into()), Some(2));
+    /// assert_eq!(board.rank_of(&"a".into()), Some(3));
+    /// assert_eq!(board.rank_of(&"c".into()), Some(1));
+    /// assert_eq!(board.leader().unwrap().token(), "b");
+    /// assert!(board.is_tied());
+    /// ```
+    pub fn rank_of(&self, token: &PlayerToken) -> Option<usize> {
+        let score = self.score_of(token)?;
+        Some(
+            1 + self
+                .scores
+                .iter()
+                .filter(|entry| entry.score > score)
+                .count(),
+        )
+    }
+
+    /// The entry with the highest score, the first listed one on a tie.
+    pub fn leader(&self) -> Option<&TokenScore> {
+        self.scores.iter().rev().max_by_key(|entry| entry.score)
+    }
+
+    /// Whether more than one player has the highest score.
+    pub fn is_tied(&self) -> bool {
+        self.leader().is_some_and(|leader| {
+            self.scores
+                .iter()
+                .filter(|entry| entry.score == leader.score)
+                .count()
+                > 1
+        })
+    }
 }
 
 impl GameStatistics {
@@ -200,11 +650,43 @@ impl GameStatistics {
             scores,
         }
     }
+
+    /// Whether the players are fighting.
+    pub fn is_battle(&self) -> bool {
+        self.current_stage == Stage::Battle
+    }
+
+    /// Whether the players are choosing buffs between battles.
+    pub fn is_rest(&self) -> bool {
+        self.current_stage == Stage::Rest
+    }
+
+    /// Time left in the current stage, taking `count_down` as ticks left
+    /// and the server running `ticks_per_second` ticks per second.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use thuai_8_agent_rust::agent::model::{GameStatistics, ScoreBoard, Stage};
+    ///
+    /// let statistics = GameStatistics::new(Stage::Battle, 100, 20, ScoreBoard::new(Vec::new()));
+    ///
+    /// assert!(statistics.is_battle() && !statistics.is_rest());
+    /// assert_eq!(statistics.estimated_remaining(20.0), Duration::from_secs(5));
+    /// ```
+    pub fn estimated_remaining(&self, ticks_per_second: f64) -> Duration {
+        if self.count_down == 0 {
+            return Duration::ZERO;
+        }
+        Duration::try_from_secs_f64(self.count_down as f64 / ticks_per_second)
+            .unwrap_or(Duration::MAX)
+    }
 }
 
 impl Display for Stage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Stage: {}", self.to_string())
+        write!(f, "Stage: {:?}", self)
     }
 }
 
@@ -237,32 +719,47 @@ impl Display for GameStatistics {
 // Environment Info things...
 /// Represent a unbreakable wall in the map.
 ///
-/// Note that walls have directions, and it is recorded in `position.angle`,
-/// though the angle of a wall will only be 0 (parallel to x axis) or 90 (
-/// parallel to y axis).
+/// Note that walls have directions, and it is recorded in `angle`, though
+/// the angle of a wall will only be 0 (parallel to x axis) or 90 degrees
+/// (parallel to y axis). It is sent in degrees.
 ///
 /// Fields should be get through getter method `field()`.
-#[derive(Debug, Getters, Serialize, Deserialize)]
+///
+/// # Example
+///
+/// ```
+/// use std::f64::consts::FRAC_PI_2;
+/// use thuai_8_agent_rust::agent::model::{Angle, Wall};
+///
+/// let wall: Wall = serde_json::from_str(r#"{"x": 1, "y": 2, "angle": 90.0}"#).unwrap();
+///
+/// assert!(wall.angle().approx_eq(Angle::from(FRAC_PI_2), 1e-9));
+/// assert_eq!(serde_json::to_string(&wall).unwrap(), r#"{"x":1,"y":2,"angle":90.0}"#);
+/// ```
+#[derive(Debug, Clone, Getters, Serialize, Deserialize)]
 #[getset(get = "pub")]
 pub struct Wall {
     x: i32,
     y: i32,
-    angle: f64,
+    #[serde(with = "angle::degrees")]
+    angle: Angle,
 }
 
 /// Represent a breakable wall (aka fence in thuai-8) in the map.
 ///
 /// Note that fences have directions, and it is recorded in `position.angle`,
 /// though the angle of a fence will only be 0 (parallel to x axis) or 90
-/// (parallel to y axis).
+/// degrees (parallel to y axis). It is sent in degrees.
 ///
 /// When health goes to 0, the fence will be broken and will disappear.
 ///
 /// Fields should be get through getter method `field()`.
-#[derive(Debug, Getters, Serialize, Deserialize)]
+#[derive(Debug, Clone, Getters, Serialize, Deserialize)]
 #[getset(get = "pub")]
 pub struct Fence {
+    #[serde(with = "fence_position")]
     position: Position<i32>,
+    #[serde(default)]
     health: u32,
 }
 
@@ -279,22 +776,22 @@ pub struct Fence {
 /// anti-armor.
 ///
 /// Fields should be get through getter method `field()`.
-#[derive(Debug, Getters, Serialize, Deserialize)]
+#[derive(Debug, Clone, Getters, Serialize, Deserialize)]
 #[getset(get = "pub")]
 pub struct Bullet {
     #[serde(rename = "no")]
-    id: u32,
-    #[serde(rename = "isMissile")]
+    id: BulletId,
+    #[serde(rename = "isMissile", default)]
     is_missile: bool,
-    #[serde(rename = "isAntiArmor")]
+    #[serde(rename = "isAntiArmor", default)]
     is_anti_armor: bool,
     #[serde(rename = "position")]
     position: Position<f64>,
-    #[serde(rename = "speed")]
+    #[serde(rename = "speed", default)]
     speed: f64,
-    #[serde(rename = "damage")]
+    #[serde(rename = "damage", default)]
     damage: f64,
-    #[serde(rename = "traveledDistance")]
+    #[serde(rename = "traveledDistance", default)]
     traveled_distance: f64,
 }
 
@@ -306,21 +803,146 @@ pub struct Bullet {
 /// - List of [`Bullet`]s
 ///
 /// Fields should be get through getter method `field()`.
-#[derive(Debug, Getters)]
+///
+/// # Example
+///
+/// Deserialize
+/// ```
+/// use thuai_8_agent_rust::agent::model::EnvironmentInfo;
+///
+/// let data = r#"{
+///     "messageType": "ENVIRONMENT_INFO",
+///     "mapSize": 10,
+///     "walls": [{"x": 1, "y": 2, "angle": 90.0}],
+///     "fences": [{"position": {"x": 3, "y": 4, "angle": 0.0}, "health": 2}],
+///     "bullets": []
+/// }"#;
+///
+/// let environment: EnvironmentInfo = serde_json::from_str(data).unwrap();
+///
+/// assert_eq!(environment.map_size(), &10);
+/// assert_eq!(environment.walls()[0].y(), &2);
+/// assert_eq!(environment.fences()[0].health(), &2);
+/// ```
+///
+/// Serialize
+/// ```
+/// use thuai_8_agent_rust::agent::model::EnvironmentInfo;
+///
+/// let environment = EnvironmentInfo::new(10, Vec::new(), Vec::new(), Vec::new());
+///
+/// let data = serde_json::to_string(&environment).unwrap();
+///
+/// assert_eq!(data, r#"{"mapSize":10,"walls":[],"fences":[],"bullets":[]}"#);
+/// ```
+#[derive(Debug, Clone, Getters, Serialize, Deserialize)]
 #[getset(get = "pub")]
 pub struct EnvironmentInfo {
+    #[serde(rename = "mapSize")]
     map_size: u32,
+    #[serde(rename = "walls", default)]
     walls: Vec<Wall>,
+    #[serde(rename = "fences", default)]
     fences: Vec<Fence>,
+    #[serde(rename = "bullets", default)]
     bullets: Vec<Bullet>,
 }
 
+/// (De)serialize the position of a fence, whose angle is sent in degrees.
+mod fence_position {
+    use super::{Angle, Position, angle};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Sent {
+        x: i32,
+        y: i32,
+        #[serde(with = "angle::degrees")]
+        angle: Angle,
+    }
+
+    pub fn serialize<S: Serializer>(
+        position: &Position<i32>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        Sent {
+            x: position.x,
+            y: position.y,
+            angle: position.angle,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Position<i32>, D::Error> {
+        let sent = Sent::deserialize(deserializer)?;
+        Ok(Position::new(sent.x, sent.y, sent.angle))
+    }
+}
+
+impl Wall {
+    /// Constructs a new [`Wall`] at `(x, y)` along `angle`.
+    pub fn new(x: i32, y: i32, angle: Angle) -> Wall {
+        Wall { x, y, angle }
+    }
+}
+
+impl Fence {
+    /// Constructs a new [`Fence`].
+    pub fn new(position: Position<i32>, health: u32) -> Fence {
+        Fence { position, health }
+    }
+}
+
+impl Bullet {
+    /// Constructs a new [`Bullet`].
+    pub fn new(
+        id: impl Into<BulletId>,
+        is_missile: bool,
+        is_anti_armor: bool,
+        position: Position<f64>,
+        speed: f64,
+        damage: f64,
+        traveled_distance: f64,
+    ) -> Bullet {
+        Bullet {
+            id: id.into(),
+            is_missile,
+            is_anti_armor,
+            position,
+            speed,
+            damage,
+            traveled_distance,
+        }
+    }
+}
+
+impl EnvironmentInfo {
+    /// Constructs a new [`EnvironmentInfo`].
+    pub fn new(
+        map_size: u32,
+        walls: Vec<Wall>,
+        fences: Vec<Fence>,
+        bullets: Vec<Bullet>,
+    ) -> EnvironmentInfo {
+        EnvironmentInfo {
+            map_size,
+            walls,
+            fences,
+            bullets,
+        }
+    }
+}
+
 impl Display for Wall {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "Wall: {{ position: {{ x: {}, y: {}, angle: {}}} }}",
-            self.x, self.y, self.angle
+            self.x,
+            self.y,
+            self.angle.degrees()
         )
     }
 }
@@ -329,8 +951,11 @@ impl Display for Fence {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Fence: {{ position: {}, health: {} }}",
-            self.position, self.health
+            "Fence: {{ position: {{x: {}, y: {}, angle: {}}}, health: {} }}",
+            self.position.x,
+            self.position.y,
+            self.position.angle.degrees(),
+            self.health
         )
     }
 }
@@ -392,10 +1017,21 @@ impl Display for EnvironmentInfo {
 /// compare a [`BuffKind`] with a [`SkillKind`]. Note that the inverted compare
 /// is wrong!
 ///
-/// Can be converted from String.
+/// Can be converted from String, by variant or protocol name. Formatted, and
+/// [`AsRef<str>`], as the protocol name.
 ///
 /// # Examples
 ///
+/// Format as the protocol name.
+/// ```
+/// use thuai_8_agent_rust::agent::model::BuffKind;
+/// use std::str::FromStr;
+///
+/// assert_eq!(BuffKind::SpeedUp.to_string(), "SPEED_UP");
+/// assert_eq!(BuffKind::AntiArmor.as_ref(), "ANTI_ARMOR");
+/// assert_eq!(BuffKind::from_str("SPEED_UP").unwrap(), BuffKind::SpeedUp);
+/// ```
+///
 /// Compare between [`BuffKind`] and [`SkillKind`]
 /// ```
 /// use thuai_8_agent_rust::agent::model::{BuffKind, SkillKind};
@@ -426,60 +1062,167 @@ impl Display for EnvironmentInfo {
 ///
 /// assert_eq!(buff, buff_from_string);
 /// ```
-#[derive(Debug, EnumString, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[derive(
+    Debug,
+    EnumString,
+    EnumIter,
+    strum::Display,
+    AsRefStr,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+)]
 pub enum BuffKind {
     #[serde(rename = "BLACK_OUT")]
+    #[strum(to_string = "BLACK_OUT", serialize = "BlackOut")]
     BlackOut,
     #[serde(rename = "SPEED_UP")]
+    #[strum(to_string = "SPEED_UP", serialize = "SpeedUp")]
     SpeedUp,
     #[serde(rename = "FLASH")]
+    #[strum(to_string = "FLASH", serialize = "Flash")]
     Flash,
     #[serde(rename = "DESTROY")]
+    #[strum(to_string = "DESTROY", serialize = "Destroy")]
     Destroy,
     #[serde(rename = "CONSTRUCT")]
+    #[strum(to_string = "CONSTRUCT", serialize = "Construct")]
     Construct,
     #[serde(rename = "TRAP")]
+    #[strum(to_string = "TRAP", serialize = "Trap")]
     Trap,
     #[serde(rename = "MISSILE")]
+    #[strum(to_string = "MISSILE", serialize = "Missile")]
     Missile,
     #[serde(rename = "KAMUI")]
+    #[strum(to_string = "KAMUI", serialize = "Kamui")]
     Kamui,
     #[serde(rename = "BULLET_COUNT")]
+    #[strum(to_string = "BULLET_COUNT", serialize = "BulletCount")]
     BulletCount,
     #[serde(rename = "BULLET_SPEED")]
+    #[strum(to_string = "BULLET_SPEED", serialize = "BulletSpeed")]
     BulletSpeed,
     #[serde(rename = "ATTACK_SPEED")]
+    #[strum(to_string = "ATTACK_SPEED", serialize = "AttackSpeed")]
     AttackSpeed,
     #[serde(rename = "LASER")]
+    #[strum(to_string = "LASER", serialize = "Laser")]
     Laser,
     #[serde(rename = "DAMAGE")]
+    #[strum(to_string = "DAMAGE", serialize = "Damage")]
     Damage,
     #[serde(rename = "ANTI_ARMOR")]
+    #[strum(to_string = "ANTI_ARMOR", serialize = "AntiArmor")]
     AntiArmor,
     #[serde(rename = "ARMOR")]
+    #[strum(to_string = "ARMOR", serialize = "Armor")]
     Armor,
     #[serde(rename = "REFLECT")]
+    #[strum(to_string = "REFLECT", serialize = "Reflect")]
     Reflect,
     #[serde(rename = "DODGE")]
+    #[strum(to_string = "DODGE", serialize = "Dodge")]
     Dodge,
     #[serde(rename = "KNIFE")]
+    #[strum(to_string = "KNIFE", serialize = "Knife")]
     Knife,
     #[serde(rename = "GRAVITY")]
+    #[strum(to_string = "GRAVITY", serialize = "Gravity")]
     Gravity,
 }
 
-impl Display for BuffKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
-    }
-}
-
 /// Type alias for AvailableBuffs, which is a [`Vec<T>`] where `T` is [`BuffKind`].
 pub type AvailableBuffs = Vec<BuffKind>;
 
 impl PartialEq<SkillKind> for BuffKind {
     fn eq(&self, other: &SkillKind) -> bool {
-        other.clone() as u8 == self.clone() as u8
+        *other as u8 == *self as u8
+    }
+}
+
+/// Coarse grouping of [`BuffKind`]s by what they help with.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum BuffCategory {
+    /// Dealing damage.
+    Offense,
+    /// Avoiding or absorbing damage.
+    Defense,
+    /// Getting around the map.
+    Mobility,
+}
+
+impl BuffKind {
+    /// Every [`BuffKind`], in declaration order.
+    pub fn all() -> impl Iterator<Item = BuffKind> {
+        BuffKind::iter()
+    }
+
+    /// The skill the buff grants, if it grants an active skill rather than a
+    /// passive stat.
+    pub fn skill(self) -> Option<SkillKind> {
+        match self {
+            BuffKind::BlackOut => Some(SkillKind::BlackOut),
+            BuffKind::SpeedUp => Some(SkillKind::SpeedUp),
+            BuffKind::Flash => Some(SkillKind::Flash),
+            BuffKind::Destroy => Some(SkillKind::Destroy),
+            BuffKind::Construct => Some(SkillKind::Construct),
+            BuffKind::Trap => Some(SkillKind::Trap),
+            BuffKind::Missile => Some(SkillKind::Missile),
+            BuffKind::Kamui => Some(SkillKind::Kamui),
+            _ => None,
+        }
+    }
+
+    /// Whether the buff grants an active skill, used with
+    /// [`PerformSkill`](crate::agent::connection::PerformMessage::PerformSkill).
+    pub fn is_skill(self) -> bool {
+        self.skill().is_some()
+    }
+
+    /// Whether the buff improves a stat of the weapon or armor.
+    pub fn is_passive(self) -> bool {
+        !self.is_skill()
+    }
+
+    /// What the buff helps with.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::{BuffCategory, BuffKind};
+    ///
+    /// let offense: Vec<BuffKind> = BuffKind::all()
+    ///     .filter(|buff| buff.is_skill() && buff.category() == BuffCategory::Offense)
+    ///     .collect();
+    ///
+    /// assert_eq!(offense, [BuffKind::Trap, BuffKind::Missile]);
+    /// assert!(BuffKind::Dodge.is_passive());
+    /// ```
+    pub fn category(self) -> BuffCategory {
+        match self {
+            BuffKind::Trap
+            | BuffKind::Missile
+            | BuffKind::BulletCount
+            | BuffKind::BulletSpeed
+            | BuffKind::AttackSpeed
+            | BuffKind::Laser
+            | BuffKind::Damage
+            | BuffKind::AntiArmor => BuffCategory::Offense,
+            BuffKind::BlackOut
+            | BuffKind::Construct
+            | BuffKind::Kamui
+            | BuffKind::Armor
+            | BuffKind::Reflect
+            | BuffKind::Dodge
+            | BuffKind::Knife
+            | BuffKind::Gravity => BuffCategory::Defense,
+            BuffKind::SpeedUp | BuffKind::Flash | BuffKind::Destroy => BuffCategory::Mobility,
+        }
     }
 }
 
@@ -490,7 +1233,8 @@ impl PartialEq<SkillKind> for BuffKind {
 ///
 /// [`PartialEq<Self>`] is implemented, so you can compare between [`ArmorKnifeState`]s.
 ///
-/// Can be converted from String.
+/// Can be converted from String, by variant or protocol name. Formatted, and
+/// [`AsRef<str>`], as the protocol name.
 ///
 /// # Examples
 ///
@@ -504,24 +1248,25 @@ impl PartialEq<SkillKind> for BuffKind {
 ///
 /// assert_eq!(state, state_from_string);
 /// ```
-#[derive(Debug, PartialEq, EnumString, Clone, Serialize, Deserialize)]
+#[derive(
+    Debug, Default, PartialEq, EnumString, strum::Display, AsRefStr, Clone, Serialize, Deserialize,
+)]
 pub enum ArmorKnifeState {
+    #[default]
     #[serde(rename = "NOT_OWNED")]
+    #[strum(to_string = "NOT_OWNED", serialize = "NotOwned")]
     NotOwned,
     #[serde(rename = "AVAILABLE")]
+    #[strum(to_string = "AVAILABLE", serialize = "Available")]
     Available,
     #[serde(rename = "ACTIVE")]
+    #[strum(to_string = "ACTIVE", serialize = "Active")]
     Active,
     #[serde(rename = "BROKEN")]
+    #[strum(to_string = "BROKEN", serialize = "Broken")]
     Broken,
 }
 
-impl Display for ArmorKnifeState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
-    }
-}
-
 /// Enum class to represent all kinds of skills. Skills are provided by the buff
 /// with the same name and can be actively activated.
 ///
@@ -531,7 +1276,8 @@ impl Display for ArmorKnifeState {
 /// meaning that you can compare between [`BuffKind`] and [`SkillKind`]. Note that
 /// inverted compare is not implemented.
 ///
-/// Can be converted from String.
+/// Can be converted from String, by variant or protocol name. Formatted, and
+/// [`AsRef<str>`], as the protocol name.
 ///
 /// # Examples
 ///
@@ -565,29 +1311,59 @@ impl Display for ArmorKnifeState {
 ///
 /// assert_eq!(buff, buff_from_string);
 /// ```
-#[derive(Debug, PartialEq, EnumString, Clone, Copy, Serialize, Deserialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    EnumString,
+    EnumIter,
+    strum::Display,
+    AsRefStr,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+)]
 pub enum SkillKind {
     #[serde(rename = "BLACK_OUT")]
+    #[strum(to_string = "BLACK_OUT", serialize = "BlackOut")]
     BlackOut,
     #[serde(rename = "SPEED_UP")]
+    #[strum(to_string = "SPEED_UP", serialize = "SpeedUp")]
     SpeedUp,
     #[serde(rename = "FLASH")]
+    #[strum(to_string = "FLASH", serialize = "Flash")]
     Flash,
     #[serde(rename = "DESTROY")]
+    #[strum(to_string = "DESTROY", serialize = "Destroy")]
     Destroy,
     #[serde(rename = "CONSTRUCT")]
+    #[strum(to_string = "CONSTRUCT", serialize = "Construct")]
     Construct,
     #[serde(rename = "TRAP")]
+    #[strum(to_string = "TRAP", serialize = "Trap")]
     Trap,
     #[serde(rename = "MISSILE")]
+    #[strum(to_string = "MISSILE", serialize = "Missile")]
     Missile,
     #[serde(rename = "KAMUI")]
+    #[strum(to_string = "KAMUI", serialize = "Kamui")]
     Kamui,
 }
 
-impl Display for SkillKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
+impl SkillKind {
+    /// Every [`SkillKind`], in declaration order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::{BuffKind, SkillKind};
+    ///
+    /// let granted: Vec<SkillKind> = BuffKind::all().filter_map(BuffKind::skill).collect();
+    ///
+    /// assert_eq!(granted, SkillKind::all().collect::<Vec<_>>());
+    /// ```
+    pub fn all() -> impl Iterator<Item = SkillKind> {
+        SkillKind::iter()
     }
 }
 
@@ -603,8 +1379,9 @@ impl Display for SkillKind {
 ///
 /// assert_eq!(weapon.damage(), &20);
 /// ```
-#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Getters, Serialize, Deserialize)]
 #[getset(get = "pub")]
+#[serde(default)]
 pub struct Weapon {
     #[serde(rename = "attackSpeed")]
     attack_speed: f64,
@@ -622,6 +1399,15 @@ pub struct Weapon {
     current_bullets: u32,
 }
 
+/// Highest dodge rate [`Armor::effective_durability`] counts with.
+pub const MAX_DODGE_RATE: f64 = 0.9;
+
+/// How much a reflecting armor adds to [`Armor::effective_durability`].
+pub const REFLECT_DURABILITY_FACTOR: f64 = 1.25;
+
+/// How much a knife not used up yet adds to [`Armor::effective_durability`].
+pub const KNIFE_DURABILITY_FACTOR: f64 = 1.5;
+
 /// Represent the armor info of a player.
 ///
 /// Fields should be get through getter method `field()`.
@@ -636,8 +1422,9 @@ pub struct Weapon {
 /// assert_eq!(armor.health(), &0);
 ///
 /// ```
-#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Getters, Serialize, Deserialize)]
 #[getset(get = "pub")]
+#[serde(default)]
 pub struct Armor {
     #[serde(rename = "canReflect")]
     can_reflect: bool,
@@ -671,11 +1458,11 @@ pub struct Armor {
 pub struct Skill {
     #[serde(rename = "name")]
     name: SkillKind,
-    #[serde(rename = "maxCooldown")]
+    #[serde(rename = "maxCooldown", default)]
     max_cool_down: u32,
-    #[serde(rename = "currentCooldown")]
+    #[serde(rename = "currentCooldown", default)]
     current_cool_down: u32,
-    #[serde(rename = "isActive")]
+    #[serde(rename = "isActive", default)]
     is_active: bool,
 }
 
@@ -705,19 +1492,69 @@ pub struct Skill {
 #[getset(get = "pub")]
 pub struct Player {
     #[serde(rename = "token")]
-    token: String,
+    token: PlayerToken,
     #[serde(rename = "position")]
     position: Position<f64>,
-    #[serde(rename = "weapon")]
+    #[serde(rename = "weapon", default)]
     weapon: Weapon,
-    #[serde(rename = "armor")]
+    #[serde(rename = "armor", default)]
     armor: Armor,
-    #[serde(rename = "skills")]
+    #[serde(rename = "skills", default)]
     skills: Vec<Skill>,
 }
 
 pub type Players = Vec<Player>;
 
+/// Merge `update` into `players` by token: players in both are replaced in
+/// place and new ones are appended. Of the others, those the reply
+/// `covers` left the game and are removed, the rest are kept, e.g. the
+/// opponent when a reply only carries the player itself.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::{self, Armor, ArmorKnifeState, Player, Position, Weapon};
+///
+/// let player = |token: &str, health: i32| {
+///     Player::new(
+///         token,
+///         Position::new(1.0, 1.0, 0.0),
+///         Weapon::new(1.0, 1.0, false, false, 10, 10, 10),
+///         Armor::new(false, false, 0, health, 0.0, ArmorKnifeState::NotOwned),
+///         Vec::new(),
+///     )
+/// };
+/// let mut players = vec![player("self", 20), player("opponent", 20), player("gone", 20)];
+///
+/// // A reply to the player itself.
+/// model::merge_players(&mut players, vec![player("self", 15)], |token| token == "self");
+/// assert_eq!(players.len(), 3);
+/// assert_eq!(players[0].armor().health(), &15);
+///
+/// // A reply to the opponents, without the one that left.
+/// model::merge_players(&mut players, vec![player("opponent", 18)], |token| token != "self");
+/// assert_eq!(players.len(), 2);
+/// assert_eq!(players[1].token(), &"opponent");
+/// ```
+pub fn merge_players(
+    players: &mut Players,
+    update: Players,
+    covers: impl Fn(&PlayerToken) -> bool,
+) {
+    players.retain(|cached| {
+        !covers(&cached.token) || update.iter().any(|player| player.token == cached.token)
+    });
+    for player in update {
+        match players
+            .iter_mut()
+            .find(|cached| cached.token == player.token)
+        {
+            Some(cached) => *cached = player,
+            None => players.push(player),
+        }
+    }
+}
+
 impl Weapon {
     pub fn new(
         attack_speed: f64,
@@ -738,6 +1575,110 @@ impl Weapon {
             current_bullets,
         }
     }
+
+    /// Ticks between two shots, taking `attack_speed` as shots per tick.
+    /// Infinite if the weapon cannot fire.
+    pub fn ticks_per_shot(&self) -> f64 {
+        if self.attack_speed > 0.0 {
+            1.0 / self.attack_speed
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Whether the weapon can fire `ticks_since_last_shot` ticks after its
+    /// last shot, [`None`] if it has not fired yet: a bullet must be left
+    /// and [`Weapon::ticks_per_shot`] passed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::Weapon;
+    ///
+    /// let weapon = Weapon::new(0.25, 3.0, false, false, 10, 10, 1);
+    ///
+    /// assert!(weapon.can_fire_after(None));
+    /// assert!(!weapon.can_fire_after(Some(3)));
+    /// assert!(weapon.can_fire_after(Some(4)));
+    /// assert!(!Weapon::new(0.25, 3.0, false, false, 10, 10, 0).can_fire_after(None));
+    /// ```
+    pub fn can_fire_after(&self, ticks_since_last_shot: Option<u64>) -> bool {
+        self.current_bullets > 0
+            && ticks_since_last_shot.is_none_or(|ticks| ticks as f64 >= self.ticks_per_shot())
+    }
+
+    /// Damage dealt per tick when firing continuously.
+    pub fn dps(&self) -> f64 {
+        self.damage as f64 * self.attack_speed.max(0.0)
+    }
+
+    /// Hits needed to bring `target` to no health. Armor value absorbs
+    /// damage first, unless the weapon is anti-armor. [`None`] if the weapon
+    /// does no damage.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::{Armor, ArmorKnifeState, Weapon};
+    ///
+    /// let weapon = Weapon::new(0.25, 3.0, false, false, 10, 10, 10);
+    /// let tank = Armor::new(false, false, u32::MAX, 20, 0.0, ArmorKnifeState::NotOwned);
+    ///
+    /// // Health and armor add up beyond `u32::MAX` without overflowing.
+    /// assert_eq!(weapon.shots_to_kill(&tank), Some(u32::MAX.div_ceil(10)));
+    /// ```
+    pub fn shots_to_kill(&self, target: &Armor) -> Option<u32> {
+        if target.health <= 0 {
+            return Some(0);
+        }
+        if self.damage == 0 {
+            return None;
+        }
+        let armor = if self.anti_armor {
+            0
+        } else {
+            target.armor_value
+        };
+        Some(
+            (target.health as u32)
+                .saturating_add(armor)
+                .div_ceil(self.damage),
+        )
+    }
+
+    /// Hits needed to destroy `fence`, taking its health as damage it
+    /// can take. [`None`] if the weapon does no damage.
+    pub fn shots_to_break(&self, fence: &Fence) -> Option<u32> {
+        match self.damage {
+            0 if fence.health > 0 => None,
+            0 => Some(0),
+            damage => Some(fence.health.div_ceil(damage)),
+        }
+    }
+
+    /// Ticks from the first hit until `target` has no health, if every shot
+    /// hits. Infinite if the weapon cannot kill it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::{Armor, ArmorKnifeState, Weapon};
+    ///
+    /// let weapon = Weapon::new(0.25, 3.0, false, false, 10, 10, 10);
+    /// let target = Armor::new(false, false, 5, 20, 0.0, ArmorKnifeState::NotOwned);
+    ///
+    /// assert_eq!(weapon.ticks_per_shot(), 4.0);
+    /// assert_eq!(weapon.dps(), 2.5);
+    /// assert_eq!(weapon.shots_to_kill(&target), Some(3));
+    /// assert_eq!(weapon.time_to_kill(&target), 8.0);
+    /// ```
+    pub fn time_to_kill(&self, target: &Armor) -> f64 {
+        match self.shots_to_kill(target) {
+            Some(0) => 0.0,
+            Some(shots) if self.attack_speed > 0.0 => (shots - 1) as f64 * self.ticks_per_shot(),
+            _ => f64::INFINITY,
+        }
+    }
 }
 
 impl Armor {
@@ -758,6 +1699,45 @@ impl Armor {
             knife,
         }
     }
+
+    /// A single number to compare how hard players are to kill: health and
+    /// armor value, scaled up by the expected share of dodged hits, a
+    /// reflecting armor and a knife that is not used up yet. Zero when
+    /// there is no health left.
+    ///
+    /// `dodge_rate` is taken as a probability, capped at
+    /// [`MAX_DODGE_RATE`] so a high rate does not make a player unkillable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::{Armor, ArmorKnifeState};
+    ///
+    /// let plain = Armor::new(false, false, 5, 15, 0.0, ArmorKnifeState::NotOwned);
+    /// let dodging = Armor::new(false, false, 5, 15, 0.5, ArmorKnifeState::Broken);
+    /// let knife = Armor::new(false, false, 5, 15, 0.0, ArmorKnifeState::Available);
+    ///
+    /// assert_eq!(plain.effective_durability(), 20.0);
+    /// assert_eq!(dodging.effective_durability(), 40.0);
+    /// assert!(knife.effective_durability() > plain.effective_durability());
+    /// ```
+    pub fn effective_durability(&self) -> f64 {
+        if self.health <= 0 {
+            return 0.0;
+        }
+        let mut durability = (self.health as f64 + self.armor_value as f64)
+            / (1.0 - self.dodge_rate.clamp(0.0, MAX_DODGE_RATE));
+        if self.can_reflect {
+            durability *= REFLECT_DURABILITY_FACTOR;
+        }
+        if matches!(
+            self.knife,
+            ArmorKnifeState::Available | ArmorKnifeState::Active
+        ) {
+            durability *= KNIFE_DURABILITY_FACTOR;
+        }
+        durability
+    }
 }
 
 impl Skill {
@@ -778,23 +1758,83 @@ impl Skill {
 
 impl Player {
     pub fn new(
-        token: String,
+        token: impl Into<PlayerToken>,
         position: Position<f64>,
         weapon: Weapon,
         armor: Armor,
         skills: Vec<Skill>,
     ) -> Player {
         Player {
-            token,
+            token: token.into(),
             position,
             weapon,
             armor,
             skills,
         }
     }
+
+    /// Whether the player has health left.
+    pub fn is_alive(&self) -> bool {
+        self.armor.health > 0
+    }
+
+    /// The player's skill of kind `kind`, if it has one.
+    pub fn skill(&self, kind: SkillKind) -> Option<&Skill> {
+        self.skills.iter().find(|skill| skill.name == kind)
+    }
+
+    /// Whether the player has a skill of kind `kind` that is off cool down.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::{
+    ///     Armor, ArmorKnifeState, Player, Position, Skill, SkillKind, Weapon,
+    /// };
+    ///
+    /// let player = Player::new(
+    ///     "1919810",
+    ///     Position::new(2.0, 3.0, 0.0),
+    ///     Weapon::new(1.0, 1.0, false, false, 10, 10, 0),
+    ///     Armor::new(false, false, 5, 20, 1.0, ArmorKnifeState::NotOwned),
+    ///     vec![Skill::new(SkillKind::Flash, 20, 0, false)],
+    /// );
+    ///
+    /// assert!(player.is_alive());
+    /// assert!(player.skill_ready(SkillKind::Flash));
+    /// assert!(!player.skill_ready(SkillKind::Kamui));
+    /// assert_eq!(player.effective_health(), 25);
+    /// ```
+    pub fn skill_ready(&self, kind: SkillKind) -> bool {
+        self.skill(kind)
+            .is_some_and(|skill| skill.current_cool_down == 0)
+    }
+
+    /// Health plus armor value: the damage the player can take. Saturates
+    /// at [`i32::MAX`] for values out of range.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::model::{Armor, ArmorKnifeState, Player, Position, Weapon};
+    ///
+    /// let armored = Player::new(
+    ///     "1919810",
+    ///     Position::new(2.0, 3.0, 0.0),
+    ///     Weapon::default(),
+    ///     Armor::new(false, false, u32::MAX, 20, 0.0, ArmorKnifeState::NotOwned),
+    ///     Vec::new(),
+    /// );
+    ///
+    /// assert_eq!(armored.effective_health(), i32::MAX);
+    /// ```
+    pub fn effective_health(&self) -> i32 {
+        let armor_value = i32::try_from(self.armor.armor_value).unwrap_or(i32::MAX);
+        self.armor.health.saturating_add(armor_value)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum MoveDirection {
     #[serde(rename = "BACK")]
     Back,
@@ -802,7 +1842,7 @@ pub enum MoveDirection {
     Forth,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum TurnDirection {
     #[serde(rename = "CLOCKWISE")]
     Clockwise,
@@ -810,7 +1850,7 @@ pub enum TurnDirection {
     CounterClockwise,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum RequestType {
     #[serde(rename = "SELF")]
     TheSelf,
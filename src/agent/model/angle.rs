@@ -0,0 +1,260 @@
+/*!
+Angle helpers.
+
+Angles in the model are [`Angle`]s, in `rad` counterclockwise from the x
+axis and not limited to any range; walls and fences are sent in degrees,
+and [`PerformTurn`](crate::agent::connection::PerformMessage::PerformTurn)
+takes whole degrees.
+*/
+use std::f64::consts::{PI, TAU};
+use std::fmt::Display;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+use super::TurnDirection;
+
+/// An angle, stored in `rad`.
+///
+/// Arithmetic does not wrap, like on plain numbers; [`Angle::difference`]
+/// and [`Angle::approx_eq`] take wraparound into account. Serialized as the
+/// bare number of `rad`.
+///
+/// # Example
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use thuai_8_agent_rust::agent::model::angle::Angle;
+///
+/// let heading = Angle::from_degrees(350.0);
+/// let target = Angle::from_degrees(10.0);
+///
+/// assert!((target.difference(heading).degrees() - 20.0).abs() < 1e-9);
+/// assert!((heading + Angle::from_degrees(20.0)).approx_eq(target, 1e-9));
+/// assert_eq!(Angle::from(-PI / 2.0).normalized(), Angle::from(1.5 * PI));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Angle(f64);
+
+impl Angle {
+    pub const ZERO: Angle = Angle(0.0);
+    pub const QUARTER_TURN: Angle = Angle(PI / 2.0);
+    pub const HALF_TURN: Angle = Angle(PI);
+
+    pub fn from_radians(radians: f64) -> Angle {
+        Angle(radians)
+    }
+
+    pub fn from_degrees(degrees: f64) -> Angle {
+        Angle(degrees.to_radians())
+    }
+
+    pub fn radians(self) -> f64 {
+        self.0
+    }
+
+    pub fn degrees(self) -> f64 {
+        self.0.to_degrees()
+    }
+
+    /// The same direction, within 0 to 2π.
+    pub fn normalized(self) -> Angle {
+        Angle(normalize(self.0))
+    }
+
+    /// `self - other` the short way round, counterclockwise positive, within
+    /// ±π.
+    pub fn difference(self, other: Angle) -> Angle {
+        let turn = normalize(self.0 - other.0);
+        Angle(if turn > PI { turn - TAU } else { turn })
+    }
+
+    /// Whether both point the same way, up to `epsilon` `rad`.
+    pub fn approx_eq(self, other: Angle, epsilon: f64) -> bool {
+        self.difference(other).0.abs() <= epsilon
+    }
+
+    /// The direction and size of the smallest turn to face `target`, as
+    /// [`shortest_turn`].
+    pub fn turn_to(self, target: Angle) -> (TurnDirection, Angle) {
+        let (direction, turn) = shortest_turn(self.0, target.0);
+        (direction, Angle(turn))
+    }
+
+    /// Sine and cosine, in that order.
+    pub fn sin_cos(self) -> (f64, f64) {
+        self.0.sin_cos()
+    }
+}
+
+impl From<f64> for Angle {
+    /// From `rad`.
+    fn from(radians: f64) -> Self {
+        Angle(radians)
+    }
+}
+
+impl From<Angle> for f64 {
+    fn from(angle: Angle) -> Self {
+        angle.0
+    }
+}
+
+impl Display for Angle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Add for Angle {
+    type Output = Angle;
+
+    fn add(self, rhs: Angle) -> Angle {
+        Angle(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Angle {
+    type Output = Angle;
+
+    fn sub(self, rhs: Angle) -> Angle {
+        Angle(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Angle {
+    type Output = Angle;
+
+    fn neg(self) -> Angle {
+        Angle(-self.0)
+    }
+}
+
+impl Mul<f64> for Angle {
+    type Output = Angle;
+
+    fn mul(self, rhs: f64) -> Angle {
+        Angle(self.0 * rhs)
+    }
+}
+
+impl AddAssign for Angle {
+    fn add_assign(&mut self, rhs: Angle) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Angle {
+    fn sub_assign(&mut self, rhs: Angle) {
+        self.0 -= rhs.0;
+    }
+}
+
+/// (De)serialize an [`Angle`] as a number of degrees, as walls and fences
+/// are sent.
+pub(crate) mod degrees {
+    use super::Angle;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(angle: &Angle, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(angle.degrees())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Angle, D::Error> {
+        f64::deserialize(deserializer).map(Angle::from_degrees)
+    }
+}
+
+/// `angle` wrapped into $[0,2\pi)$.
+///
+/// # Example
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use thuai_8_agent_rust::agent::model::angle;
+///
+/// assert_eq!(angle::normalize(-PI / 2.0), 1.5 * PI);
+/// assert_eq!(angle::normalize(2.0 * PI), 0.0);
+/// ```
+pub fn normalize(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(TAU);
+    // rem_euclid may round up to TAU itself for tiny negative angles.
+    if angle >= TAU { 0.0 } else { angle }
+}
+
+/// Convert `degrees` to `rad`.
+pub fn to_radians(degrees: f64) -> f64 {
+    degrees.to_radians()
+}
+
+/// Convert `radians` to degrees.
+pub fn to_degrees(radians: f64) -> f64 {
+    radians.to_degrees()
+}
+
+/// The direction and size, in $[0,\pi]$ `rad`, of the smallest turn from
+/// facing `from` to facing `to`. A half turn goes counterclockwise.
+///
+/// # Example
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use thuai_8_agent_rust::agent::model::{TurnDirection, angle};
+///
+/// let (direction, turn) = angle::shortest_turn(0.1, 2.0 * PI - 0.1);
+///
+/// assert_eq!(direction, TurnDirection::Clockwise);
+/// assert_eq!(angle::to_degrees(turn).round() as u32, 11);
+/// ```
+pub fn shortest_turn(from: f64, to: f64) -> (TurnDirection, f64) {
+    let counterclockwise = normalize(to - from);
+    if counterclockwise > PI {
+        (TurnDirection::Clockwise, TAU - counterclockwise)
+    } else {
+        (TurnDirection::CounterClockwise, counterclockwise)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_the_short_way() {
+        assert_eq!(normalize(-1e-18), 0.0);
+        assert!((normalize(7.0 * PI) - PI).abs() < 1e-9);
+
+        let (direction, turn) = shortest_turn(PI / 4.0, -PI / 4.0);
+        assert_eq!(direction, TurnDirection::Clockwise);
+        assert!((turn - PI / 2.0).abs() < 1e-9);
+
+        let (direction, turn) = shortest_turn(-4.0 * PI, PI / 2.0);
+        assert_eq!(direction, TurnDirection::CounterClockwise);
+        assert!((turn - PI / 2.0).abs() < 1e-9);
+
+        assert_eq!(
+            shortest_turn(1.0, 1.0),
+            (TurnDirection::CounterClockwise, 0.0)
+        );
+    }
+
+    #[test]
+    fn angle_wraps_only_where_asked() {
+        let full = Angle::from_degrees(360.0);
+        assert!((full + full).radians() > TAU);
+        assert!((full + full).approx_eq(Angle::ZERO, 1e-9));
+        assert!(!Angle::from(0.1).approx_eq(Angle::from(-0.1), 0.1));
+
+        assert_eq!(Angle::from(PI).difference(Angle::ZERO), Angle::from(PI));
+        assert_eq!(Angle::ZERO.difference(Angle::from(PI)), Angle::from(PI));
+        assert!(
+            (Angle::from(-3.0).difference(Angle::from(3.0)).radians() - (TAU - 6.0)).abs() < 1e-9
+        );
+
+        let (direction, turn) = Angle::from(PI / 4.0).turn_to(-Angle::from(PI / 4.0));
+        assert_eq!(direction, TurnDirection::Clockwise);
+        assert!(turn.approx_eq(Angle::from(PI / 2.0), 1e-9));
+        assert_eq!(serde_json::to_string(&Angle::from(1.5)).unwrap(), "1.5");
+    }
+}
@@ -0,0 +1,198 @@
+/*!
+Numeric effects of buffs.
+
+The server does not send how much a buff changes, so a [`BuffTable`] is
+either loaded from JSON, e.g. with the numbers of the current rules, or
+learned during a match by comparing the player before and after a buff was
+selected, see [`BuffTable::learn`].
+*/
+use std::collections::HashMap;
+
+use getset::CopyGetters;
+use serde::{Deserialize, Serialize};
+
+use super::{BuffKind, Player};
+
+/// What one buff changes. A field is [`None`] when the buff leaves that stat
+/// alone, or when it is not known.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, CopyGetters, Serialize, Deserialize)]
+#[getset(get_copy = "pub")]
+#[serde(default)]
+pub struct BuffEffect {
+    /// Added to the weapon damage.
+    #[serde(rename = "damageBonus")]
+    damage_bonus: Option<f64>,
+    /// Factor on the bullet speed.
+    #[serde(rename = "bulletSpeedMultiplier")]
+    bullet_speed_multiplier: Option<f64>,
+    /// Factor on the attack speed.
+    #[serde(rename = "attackSpeedMultiplier")]
+    attack_speed_multiplier: Option<f64>,
+    /// Added to the bullets the weapon holds.
+    #[serde(rename = "maxBulletsBonus")]
+    max_bullets_bonus: Option<f64>,
+    /// Added to the armor value.
+    #[serde(rename = "armorValue")]
+    armor_value: Option<f64>,
+    /// Added to the dodge rate.
+    #[serde(rename = "dodgeRate")]
+    dodge_rate: Option<f64>,
+    /// Cool down of the granted skill, in ticks.
+    #[serde(rename = "coolDown")]
+    cool_down: Option<u32>,
+}
+
+impl BuffEffect {
+    /// Constructs a [`BuffEffect`] changing nothing.
+    pub fn new() -> BuffEffect {
+        BuffEffect::default()
+    }
+
+    pub fn with_damage_bonus(mut self, bonus: f64) -> Self {
+        self.damage_bonus = Some(bonus);
+        self
+    }
+
+    pub fn with_bullet_speed_multiplier(mut self, multiplier: f64) -> Self {
+        self.bullet_speed_multiplier = Some(multiplier);
+        self
+    }
+
+    pub fn with_attack_speed_multiplier(mut self, multiplier: f64) -> Self {
+        self.attack_speed_multiplier = Some(multiplier);
+        self
+    }
+
+    pub fn with_max_bullets_bonus(mut self, bonus: f64) -> Self {
+        self.max_bullets_bonus = Some(bonus);
+        self
+    }
+
+    pub fn with_armor_value(mut self, armor_value: f64) -> Self {
+        self.armor_value = Some(armor_value);
+        self
+    }
+
+    pub fn with_dodge_rate(mut self, dodge_rate: f64) -> Self {
+        self.dodge_rate = Some(dodge_rate);
+        self
+    }
+
+    pub fn with_cool_down(mut self, cool_down: u32) -> Self {
+        self.cool_down = Some(cool_down);
+        self
+    }
+
+    /// The effect seen from `before` to `after` the buff was applied.
+    fn between(kind: BuffKind, before: &Player, after: &Player) -> BuffEffect {
+        let bonus = |old: f64, new: f64| (new != old).then_some(new - old);
+        let factor = |old: f64, new: f64| (new != old && old != 0.0).then_some(new / old);
+        let (old, new) = (before.weapon(), after.weapon());
+        BuffEffect {
+            damage_bonus: bonus(*old.damage() as f64, *new.damage() as f64),
+            bullet_speed_multiplier: factor(*old.bullet_speed(), *new.bullet_speed()),
+            attack_speed_multiplier: factor(*old.attack_speed(), *new.attack_speed()),
+            max_bullets_bonus: bonus(*old.max_bullets() as f64, *new.max_bullets() as f64),
+            armor_value: bonus(
+                *before.armor().armor_value() as f64,
+                *after.armor().armor_value() as f64,
+            ),
+            dodge_rate: bonus(*before.armor().dodge_rate(), *after.armor().dodge_rate()),
+            cool_down: kind
+                .skill()
+                .and_then(|skill| after.skill(skill))
+                .map(|skill| *skill.max_cool_down()),
+        }
+    }
+}
+
+/// The [`BuffEffect`] of each buff. (De)serialized as a map from buff name,
+/// e.g. `"DAMAGE"`, to its effect.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::BuffKind;
+/// use thuai_8_agent_rust::agent::model::buff_table::BuffTable;
+///
+/// let table: BuffTable = serde_json::from_str(
+///     r#"{"DAMAGE": {"damageBonus": 2.0}, "FLASH": {"coolDown": 100}}"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(table.effect(BuffKind::Damage).unwrap().damage_bonus(), Some(2.0));
+/// assert_eq!(table.effect(BuffKind::Flash).unwrap().cool_down(), Some(100));
+/// assert!(table.effect(BuffKind::Dodge).is_none());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BuffTable {
+    effects: HashMap<BuffKind, BuffEffect>,
+}
+
+impl BuffTable {
+    /// Constructs an empty [`BuffTable`].
+    pub fn new() -> BuffTable {
+        BuffTable::default()
+    }
+
+    /// The effect of `kind`, if known.
+    pub fn effect(&self, kind: BuffKind) -> Option<&BuffEffect> {
+        self.effects.get(&kind)
+    }
+
+    /// Set the effect of `kind`.
+    pub fn insert(&mut self, kind: BuffKind, effect: BuffEffect) {
+        self.effects.insert(kind, effect);
+    }
+
+    /// Learn the effect of `kind` from the same player `before` and `after`
+    /// selecting it, unless the effect is already known.
+    pub fn learn(&mut self, kind: BuffKind, before: &Player, after: &Player) -> &BuffEffect {
+        self.effects
+            .entry(kind)
+            .or_insert_with(|| BuffEffect::between(kind, before, after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{Skill, SkillKind, Weapon};
+    use crate::testing::fixtures::PlayerBuilder;
+
+    #[test]
+    fn learns_effects_from_players() {
+        let mut table = BuffTable::new();
+        let player = |damage, bullet_speed, skills| {
+            PlayerBuilder::new("1")
+                .with_weapon(Weapon::new(1.0, bullet_speed, false, false, damage, 10, 10))
+                .with_skills(skills)
+                .build()
+        };
+
+        let effect = *table.learn(
+            BuffKind::Damage,
+            &player(10, 2.0, Vec::new()),
+            &player(13, 2.0, Vec::new()),
+        );
+        assert_eq!(effect, BuffEffect::new().with_damage_bonus(3.0));
+
+        let before = player(10, 2.0, Vec::new());
+        let after = player(10, 3.0, vec![Skill::new(SkillKind::Missile, 80, 0, false)]);
+        table.learn(BuffKind::Missile, &before, &after);
+        let missile = table.effect(BuffKind::Missile).unwrap();
+        assert_eq!(missile.cool_down(), Some(80));
+        assert_eq!(missile.bullet_speed_multiplier(), Some(1.5));
+
+        // Known effects are kept.
+        table.learn(BuffKind::Damage, &before, &after);
+        assert_eq!(table.effect(BuffKind::Damage), Some(&effect));
+        assert_eq!(
+            serde_json::to_value(&table).unwrap()["DAMAGE"]["damageBonus"],
+            3.0
+        );
+    }
+}
@@ -0,0 +1,294 @@
+/*!
+Readable output of game state for logs.
+
+The [`Display`] impls of the model put everything on one line, which gets
+unreadable for a whole [`EnvironmentInfo`]. [`PrettyPrint`] writes it over
+several indented lines instead, or as a short summary. Both are reached
+through [`PrettyPrint::pretty`]: `{}` prints the indented form, `{:#}` the
+summary.
+*/
+use std::fmt::{Display, Formatter, Result};
+
+use super::{EnvironmentInfo, GameStatistics, Player};
+
+/// Spaces per indent level.
+const INDENT: usize = 2;
+
+/// Multi-line and summary output.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::pretty::PrettyPrint;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Fence, Position, Wall};
+///
+/// let environment = EnvironmentInfo::new(
+///     10,
+///     vec![Wall::new(1, 2, Angle::from_degrees(90.0))],
+///     vec![Fence::new(Position::new(3, 4, 0.0), 2)],
+///     Vec::new(),
+/// );
+///
+/// assert_eq!(
+///     environment.pretty().to_string(),
+///     "EnvironmentInfo\n\
+///     \x20 MapSize: 10\n\
+///     \x20 Walls:\n\
+///     \x20   Wall: { position: { x: 1, y: 2, angle: 90} }\n\
+///     \x20 Fences:\n\
+///     \x20   Fence: { position: {x: 3, y: 4, angle: 0}, health: 2 }\n\
+///     \x20 Bullets: []"
+/// );
+/// assert_eq!(
+///     format!("{:#}", environment.pretty()),
+///     "EnvironmentInfo: size 10, 1 walls, 1 fences, 0 bullets"
+/// );
+/// ```
+pub trait PrettyPrint {
+    /// Write `self` over one or more lines, each indented by `indent`
+    /// levels, without a trailing newline.
+    fn fmt_indent(&self, f: &mut Formatter<'_>, indent: usize) -> Result;
+
+    /// Write a one line summary of `self`.
+    fn fmt_summary(&self, f: &mut Formatter<'_>) -> Result;
+
+    /// Wrap `self` to [`Display`] it through this trait.
+    fn pretty(&self) -> Pretty<'_, Self> {
+        Pretty(self)
+    }
+}
+
+/// [`Display`]s the wrapped value with [`PrettyPrint::fmt_indent`], or with
+/// [`PrettyPrint::fmt_summary`] when formatted with `{:#}`.
+#[derive(Debug, Clone, Copy)]
+pub struct Pretty<'a, T: ?Sized>(&'a T);
+
+impl<T: PrettyPrint + ?Sized> Display for Pretty<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if f.alternate() {
+            self.0.fmt_summary(f)
+        } else {
+            self.0.fmt_indent(f, 0)
+        }
+    }
+}
+
+/// Start a new line at `indent` levels.
+fn line(f: &mut Formatter<'_>, indent: usize) -> Result {
+    write!(f, "\n{:width$}", "", width = indent * INDENT)
+}
+
+/// Write `name:` and then each item on its own line, one level deeper.
+fn list<T: Display>(f: &mut Formatter<'_>, indent: usize, name: &str, items: &[T]) -> Result {
+    line(f, indent)?;
+    if items.is_empty() {
+        return write!(f, "{}: []", name);
+    }
+    write!(f, "{}:", name)?;
+    for item in items {
+        line(f, indent + 1)?;
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl PrettyPrint for EnvironmentInfo {
+    fn fmt_indent(&self, f: &mut Formatter<'_>, indent: usize) -> Result {
+        write!(f, "{:width$}EnvironmentInfo", "", width = indent * INDENT)?;
+        line(f, indent + 1)?;
+        write!(f, "MapSize: {}", self.map_size())?;
+        list(f, indent + 1, "Walls", self.walls())?;
+        list(f, indent + 1, "Fences", self.fences())?;
+        list(f, indent + 1, "Bullets", self.bullets())
+    }
+
+    fn fmt_summary(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "EnvironmentInfo: size {}, {} walls, {} fences, {} bullets",
+            self.map_size(),
+            self.walls().len(),
+            self.fences().len(),
+            self.bullets().len()
+        )
+    }
+}
+
+impl PrettyPrint for GameStatistics {
+    fn fmt_indent(&self, f: &mut Formatter<'_>, indent: usize) -> Result {
+        write!(f, "{:width$}GameStatistics", "", width = indent * INDENT)?;
+        line(f, indent + 1)?;
+        write!(f, "Stage: {:?}", self.current_stage())?;
+        line(f, indent + 1)?;
+        write!(f, "CountDown: {}", self.count_down())?;
+        line(f, indent + 1)?;
+        write!(f, "Ticks: {}", self.ticks())?;
+        let scores: Vec<_> = self
+            .scores()
+            .scores()
+            .iter()
+            .map(|score| format!("{}: {}", score.token(), score.score()))
+            .collect();
+        list(f, indent + 1, "Scores", &scores)
+    }
+
+    fn fmt_summary(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "GameStatistics: {:?}, tick {}, count down {}",
+            self.current_stage(),
+            self.ticks(),
+            self.count_down()
+        )?;
+        for score in self.scores().scores() {
+            write!(f, ", {} scored {}", score.token(), score.score())?;
+        }
+        Ok(())
+    }
+}
+
+impl PrettyPrint for Player {
+    fn fmt_indent(&self, f: &mut Formatter<'_>, indent: usize) -> Result {
+        let (weapon, armor) = (self.weapon(), self.armor());
+        write!(
+            f,
+            "{:width$}Player {}",
+            "",
+            self.token(),
+            width = indent * INDENT
+        )?;
+        line(f, indent + 1)?;
+        write!(f, "{}", self.position())?;
+        line(f, indent + 1)?;
+        write!(
+            f,
+            "Weapon: damage {}, bullets {}/{}, attack speed {}, bullet speed {}",
+            weapon.damage(),
+            weapon.current_bullets(),
+            weapon.max_bullets(),
+            weapon.attack_speed(),
+            weapon.bullet_speed()
+        )?;
+        if *weapon.is_laser() {
+            write!(f, ", laser")?;
+        }
+        if *weapon.anti_armor() {
+            write!(f, ", anti-armor")?;
+        }
+        line(f, indent + 1)?;
+        write!(
+            f,
+            "Armor: health {}, armor {}, dodge rate {}, knife {}",
+            armor.health(),
+            armor.armor_value(),
+            armor.dodge_rate(),
+            armor.knife()
+        )?;
+        if *armor.can_reflect() {
+            write!(f, ", reflect")?;
+        }
+        if *armor.gravity_field() {
+            write!(f, ", gravity field")?;
+        }
+        let skills: Vec<_> = self
+            .skills()
+            .iter()
+            .map(|skill| {
+                format!(
+                    "{}: cool down {}/{}{}",
+                    skill.name(),
+                    skill.current_cool_down(),
+                    skill.max_cool_down(),
+                    if *skill.is_active() { ", active" } else { "" }
+                )
+            })
+            .collect();
+        list(f, indent + 1, "Skills", &skills)
+    }
+
+    fn fmt_summary(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "Player {} at ({:.2}, {:.2}), health {}, armor {}",
+            self.token(),
+            self.position().x(),
+            self.position().y(),
+            self.armor().health(),
+            self.armor().armor_value()
+        )
+    }
+}
+
+impl PrettyPrint for [Player] {
+    fn fmt_indent(&self, f: &mut Formatter<'_>, indent: usize) -> Result {
+        write!(f, "{:width$}Players", "", width = indent * INDENT)?;
+        if self.is_empty() {
+            return write!(f, ": []");
+        }
+        for player in self {
+            writeln!(f)?;
+            player.fmt_indent(f, indent + 1)?;
+        }
+        Ok(())
+    }
+
+    fn fmt_summary(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{} players", self.len())?;
+        for (index, player) in self.iter().enumerate() {
+            write!(f, "{}", if index == 0 { ": " } else { "; " })?;
+            player.fmt_summary(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{ScoreBoard, Skill, SkillKind, Stage, TokenScore, Weapon};
+    use crate::testing::fixtures::PlayerBuilder;
+
+    #[test]
+    fn nests_players_and_summarizes_them() {
+        let player = |token| {
+            PlayerBuilder::new(token)
+                .at(1.0, 2.5, 0.0)
+                .with_weapon(Weapon::new(1.0, 2.0, true, false, 10, 10, 3))
+                .with_health(20)
+                .with_armor_value(5)
+                .with_skills(vec![Skill::new(SkillKind::BlackOut, 10, 4, true)])
+                .build()
+        };
+        let players = [player("a"), player("b")];
+        let pretty = players.pretty().to_string();
+        let lines: Vec<_> = pretty.lines().collect();
+        assert_eq!(lines.len(), 1 + 2 * 6);
+        assert_eq!(lines[1], "  Player a");
+        assert_eq!(
+            lines[3],
+            "    Weapon: damage 10, bullets 3/10, attack speed 1, bullet speed 2, laser"
+        );
+        assert_eq!(lines[6], "      BLACK_OUT: cool down 4/10, active");
+        assert_eq!(
+            format!("{:#}", players.pretty()),
+            "2 players: Player a at (1.00, 2.50), health 20, armor 5; \
+            Player b at (1.00, 2.50), health 20, armor 5"
+        );
+        assert_eq!(Vec::<Player>::new().pretty().to_string(), "Players: []");
+
+        let statistics = GameStatistics::new(
+            Stage::Battle,
+            30,
+            200,
+            ScoreBoard::new(vec![TokenScore::new("a", 3)]),
+        );
+        assert_eq!(
+            statistics.pretty().to_string(),
+            "GameStatistics\n  Stage: Battle\n  CountDown: 30\n  Ticks: 200\n  Scores:\n    a: 3"
+        );
+        assert_eq!(
+            format!("{:#}", statistics.pretty()),
+            "GameStatistics: Battle, tick 200, count down 30, a scored 3"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+/*!
+Strict parsing, for protocol testing.
+
+Model types are parsed leniently: unknown fields are ignored and most
+missing ones take their default, so a server update adding or dropping a
+field mid-competition does not break the agent. [`from_str_strict`]
+parses the same types but reports every field the model does not know,
+to notice such updates in tests.
+*/
+use std::{error::Error, fmt::Display};
+
+use serde::de::DeserializeOwned;
+
+/// Why [`from_str_strict`] failed.
+#[derive(Debug)]
+pub enum StrictError {
+    /// Not valid JSON, or a required field is missing or has a wrong type.
+    Parse(serde_json::Error),
+    /// Parsed, but these fields are not part of the model, by path, e.g.
+    /// `weapon.range`.
+    UnknownFields(Vec<String>),
+}
+
+impl Display for StrictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StrictError::Parse(error) => write!(f, "{}", error),
+            StrictError::UnknownFields(paths) => {
+                write!(f, "unknown fields: {}", paths.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for StrictError {}
+
+/// Parse `json` as `T`, failing on fields `T` does not know. The top level
+/// `messageType` is allowed, as every server message carries it.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::Weapon;
+/// use thuai_8_agent_rust::agent::model::strict::{self, StrictError};
+///
+/// let data = r#"{"attackSpeed": 1.0, "damage": 10, "range": 5.0}"#;
+///
+/// // Lenient parsing ignores the new field and defaults the missing ones.
+/// let weapon: Weapon = serde_json::from_str(data).unwrap();
+/// assert_eq!(weapon.damage(), &10);
+/// assert_eq!(weapon.max_bullets(), &0);
+///
+/// match strict::from_str_strict::<Weapon>(data) {
+///     Err(StrictError::UnknownFields(paths)) => assert_eq!(paths, ["range"]),
+///     other => panic!("unexpected {:?}", other),
+/// }
+/// ```
+pub fn from_str_strict<T: DeserializeOwned>(json: &str) -> Result<T, StrictError> {
+    let mut unknown = Vec::new();
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let value = serde_ignored::deserialize(&mut deserializer, |path| {
+        let path = path.to_string();
+        if path != "messageType" {
+            unknown.push(path);
+        }
+    })
+    .and_then(|value| deserializer.end().map(|_| value))
+    .map_err(StrictError::Parse)?;
+    if unknown.is_empty() {
+        Ok(value)
+    } else {
+        Err(StrictError::UnknownFields(unknown))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{EnvironmentInfo, Player};
+
+    #[test]
+    fn reports_nested_unknown_fields() {
+        let player = r#"{
+            "token": "1919810",
+            "position": {"x": 1.0, "y": 2.0, "angle": 0.0},
+            "weapon": {"damage": 3, "range": 5.0},
+            "armor": {"health": 10, "shield": true},
+            "skills": []
+        }"#;
+        let lenient: Player = serde_json::from_str(player).unwrap();
+        assert_eq!(lenient.armor().health(), &10);
+        match from_str_strict::<Player>(player) {
+            Err(StrictError::UnknownFields(paths)) => {
+                assert_eq!(paths, ["weapon.range", "armor.shield"])
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let environment = r#"{"messageType": "ENVIRONMENT_INFO", "mapSize": 10}"#;
+        let environment: EnvironmentInfo = from_str_strict(environment).unwrap();
+        assert!(environment.walls().is_empty());
+
+        assert!(matches!(
+            from_str_strict::<Player>(r#"{"position": {"x": 1.0, "y": 2.0, "angle": 0.0}}"#),
+            Err(StrictError::Parse(_))
+        ));
+    }
+}
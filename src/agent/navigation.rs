@@ -0,0 +1,192 @@
+//! Walking to a point, one perform at a time.
+//!
+//! A [`Navigator`] plans a path to its goal with [`pathfinding::find_path`]
+//! and turns it into turns and moves, one per call of
+//! [`Navigator::next_step`], so it can be driven once per tick. It plans
+//! again whenever the environment it planned on was updated.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use super::map::GameMap;
+use super::model::{Position, TurnDirection};
+use super::pathfinding::{self, PathOptions};
+
+/// Default distance to a waypoint within which it counts as reached.
+pub const DEFAULT_TOLERANCE: f64 = 0.1;
+
+/// What to do next to reach the goal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NavigationStep {
+    /// Turn by whole degrees to face the next waypoint.
+    Turn(TurnDirection, u32),
+    /// Move forward by the distance to the next waypoint.
+    Forward(f64),
+    /// The goal is reached.
+    Arrived,
+    /// No path leads to the goal.
+    Unreachable,
+    /// The own position or the environment is not known yet.
+    Waiting,
+}
+
+/// Leads to a goal over a [`GameMap`].
+///
+/// Should be created with [`Navigator::new`] and then extended with the
+/// `with_*` methods.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Position, TurnDirection, Wall};
+/// use thuai_8_agent_rust::agent::navigation::{NavigationStep, Navigator};
+///
+/// let environment = EnvironmentInfo::new(
+///     3,
+///     vec![Wall::new(1, 0, Angle::from_degrees(90.0))],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+/// let mut navigator = Navigator::new(Position::new(1.5, 0.5, 0.0));
+///
+/// // Around the wall: up first.
+/// let me = Position::new(0.5, 0.5, 0.0);
+/// let step = navigator.next_step(&map, &me, None);
+/// assert_eq!(step, NavigationStep::Turn(TurnDirection::CounterClockwise, 90));
+///
+/// let me = Position::new(0.5, 0.5, Angle::QUARTER_TURN);
+/// assert_eq!(navigator.next_step(&map, &me, None), NavigationStep::Forward(1.0));
+/// assert_eq!(
+///     navigator.next_step(&map, &Position::new(1.5, 0.5, 0.0), None),
+///     NavigationStep::Arrived
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Navigator {
+    goal: Position<f64>,
+    options: PathOptions,
+    tolerance: f64,
+    path: Option<VecDeque<Position<f64>>>,
+    planned_for: Option<Instant>,
+}
+
+impl Navigator {
+    /// Constructs a [`Navigator`] to `goal`.
+    pub fn new(goal: Position<f64>) -> Navigator {
+        Navigator {
+            goal,
+            options: PathOptions::default(),
+            tolerance: DEFAULT_TOLERANCE,
+            path: None,
+            planned_for: None,
+        }
+    }
+
+    /// Plan paths with `options`.
+    pub fn with_options(mut self, options: PathOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Count waypoints within `tolerance` as reached.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Where the navigator leads to.
+    pub fn goal(&self) -> &Position<f64> {
+        &self.goal
+    }
+
+    /// The next step from `position` towards the goal.
+    ///
+    /// `environment` tells when the environment `map` was built from was
+    /// updated; the path is planned again when it changes.
+    pub fn next_step(
+        &mut self,
+        map: &GameMap,
+        position: &Position<f64>,
+        environment: Option<Instant>,
+    ) -> NavigationStep {
+        if position.distance_to(&self.goal) <= self.tolerance {
+            return NavigationStep::Arrived;
+        }
+        if self.path.is_none() || self.planned_for != environment {
+            self.plan(map, position);
+            self.planned_for = environment;
+        }
+        let Some(path) = &mut self.path else {
+            return NavigationStep::Unreachable;
+        };
+        while path.len() > 1
+            && path
+                .front()
+                .is_some_and(|waypoint| position.distance_to(waypoint) <= self.tolerance)
+        {
+            path.pop_front();
+        }
+        let Some(waypoint) = path.front() else {
+            return NavigationStep::Arrived;
+        };
+        let (direction, turn) = position.angle().turn_to(position.angle_to(waypoint));
+        let degrees = turn.degrees().round() as u32;
+        if degrees > 0 {
+            NavigationStep::Turn(direction, degrees)
+        } else {
+            NavigationStep::Forward(position.distance_to(waypoint))
+        }
+    }
+
+    /// Plan the path from `position`, ending exactly at the goal.
+    fn plan(&mut self, map: &GameMap, position: &Position<f64>) {
+        let goal = GameMap::cell_of(&self.goal);
+        self.path = pathfinding::find_path(map, position, &goal, &self.options).map(|path| {
+            let mut path: VecDeque<_> = path.into();
+            path.pop_back();
+            path.push_back(self.goal.clone());
+            path
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{Angle, EnvironmentInfo, Wall};
+
+    #[test]
+    fn replans_when_the_environment_changes() {
+        let open = GameMap::new(&EnvironmentInfo::new(3, Vec::new(), Vec::new(), Vec::new()));
+        let walled = GameMap::new(&EnvironmentInfo::new(
+            3,
+            vec![
+                Wall::new(0, 1, Angle::ZERO),
+                Wall::new(1, 1, Angle::ZERO),
+                Wall::new(2, 1, Angle::ZERO),
+            ],
+            Vec::new(),
+            Vec::new(),
+        ));
+        let mut navigator = Navigator::new(Position::new(0.5, 2.5, 0.0));
+        let me = Position::new(0.5, 0.5, Angle::QUARTER_TURN);
+        let first = Instant::now();
+
+        assert_eq!(
+            navigator.next_step(&open, &me, Some(first)),
+            NavigationStep::Forward(1.0)
+        );
+        // Planned on the same environment, the wall is not known.
+        assert_eq!(
+            navigator.next_step(&walled, &me, Some(first)),
+            NavigationStep::Forward(1.0)
+        );
+        let second = first + std::time::Duration::from_millis(1);
+        assert_eq!(
+            navigator.next_step(&walled, &me, Some(second)),
+            NavigationStep::Unreachable
+        );
+    }
+}
@@ -0,0 +1,458 @@
+//! Shortest paths over a [`GameMap`].
+//!
+//! [`find_path`] runs A* from the cell a player stands in to a goal cell and
+//! returns the centres of the cells to pass, e.g. to feed to successive
+//! turns and moves. [`analyze_fence`] tells whether shooting a fence down
+//! is worth it.
+
+use super::map::{Barrier, GameMap, Side};
+use super::model::{Fence, Position, Weapon};
+use super::symmetry::is_vertical;
+use getset::CopyGetters;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::f64::consts::SQRT_2;
+
+/// How [`find_path`] may move.
+///
+/// Should be created with [`PathOptions::new`] (or [`Default`]) and then
+/// extended with the `with_*` methods.
+///
+/// By default a path only moves between cells sharing a side and never
+/// crosses a fence.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PathOptions {
+    diagonal: bool,
+    fence_health: Option<u32>,
+}
+
+impl PathOptions {
+    /// Constructs [`PathOptions`] with the defaults.
+    pub fn new() -> PathOptions {
+        PathOptions::default()
+    }
+
+    /// Also move diagonally, when both cells around the corner can be
+    /// passed, so corners are never cut.
+    pub fn with_diagonal(mut self, diagonal: bool) -> Self {
+        self.diagonal = diagonal;
+        self
+    }
+
+    /// Cross fences with at most `health` left, as they can be shot down on
+    /// the way. Each costs its health on top of the step, so open ways are
+    /// preferred.
+    pub fn with_passable_fences(mut self, health: u32) -> Self {
+        self.fence_health = Some(health);
+        self
+    }
+}
+
+/// Cost of crossing `side` of `cell`, or [`None`] if it cannot be crossed.
+fn crossing_cost(
+    map: &GameMap,
+    options: &PathOptions,
+    cell: &Position<i32>,
+    side: Side,
+) -> Option<f64> {
+    if !map.contains(&step(cell, &[side])) {
+        return None;
+    }
+    match map.barrier(cell, side) {
+        None => Some(0.0),
+        Some(Barrier::Wall) => None,
+        Some(Barrier::Fence(health)) => options
+            .fence_health
+            .filter(|max| health <= *max)
+            .map(|_| health as f64),
+    }
+}
+
+fn step(cell: &Position<i32>, sides: &[Side]) -> Position<i32> {
+    let (mut x, mut y) = (*cell.x(), *cell.y());
+    for side in sides {
+        match side {
+            Side::Left => x -= 1,
+            Side::Right => x += 1,
+            Side::Down => y -= 1,
+            Side::Up => y += 1,
+        }
+    }
+    Position::new(x, y, 0.0)
+}
+
+/// Reachable cells next to `cell`, with the cost to get there.
+fn successors(
+    map: &GameMap,
+    options: &PathOptions,
+    cell: &Position<i32>,
+) -> Vec<(Position<i32>, f64)> {
+    let mut successors: Vec<_> = Side::ALL
+        .into_iter()
+        .filter_map(|side| {
+            let cost = crossing_cost(map, options, cell, side)?;
+            Some((step(cell, &[side]), 1.0 + cost))
+        })
+        .collect();
+    if options.diagonal {
+        for (x_side, y_side) in [
+            (Side::Left, Side::Down),
+            (Side::Left, Side::Up),
+            (Side::Right, Side::Down),
+            (Side::Right, Side::Up),
+        ] {
+            let route = |first: Side, second: Side| {
+                let first_cost = crossing_cost(map, options, cell, first)?;
+                let second_cost = crossing_cost(map, options, &step(cell, &[first]), second)?;
+                Some(first_cost + second_cost)
+            };
+            if let (Some(via_x), Some(via_y)) = (route(x_side, y_side), route(y_side, x_side)) {
+                successors.push((step(cell, &[x_side, y_side]), SQRT_2 + via_x.max(via_y)));
+            }
+        }
+    }
+    successors
+}
+
+/// A lower bound of the cost from `cell` to `goal`.
+fn heuristic(options: &PathOptions, cell: &Position<i32>, goal: &Position<i32>) -> f64 {
+    let dx = (goal.x() - cell.x()).abs() as f64;
+    let dy = (goal.y() - cell.y()).abs() as f64;
+    if options.diagonal {
+        dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy)
+    } else {
+        dx + dy
+    }
+}
+
+/// A cell in the open set, ordered by lowest estimated total cost first.
+struct Open {
+    estimate: f64,
+    cell: Position<i32>,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The cheapest path from the cell `from` is in to `goal`, as the centres
+/// of the cells to pass, ending with the centre of `goal`. Empty if `from`
+/// already is in `goal`; [`None`] if `goal` cannot be reached.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Position, Wall};
+/// use thuai_8_agent_rust::agent::pathfinding::{self, PathOptions};
+///
+/// let environment = EnvironmentInfo::new(
+///     3,
+///     vec![Wall::new(1, 0, Angle::from_degrees(90.0))],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+///
+/// let path = pathfinding::find_path(
+///     &map,
+///     &Position::new(0.3, 0.6, 0.0),
+///     &Position::new(1, 0, 0.0),
+///     &PathOptions::new(),
+/// );
+///
+/// assert_eq!(
+///     path,
+///     Some(vec![
+///         Position::new(0.5, 1.5, 0.0),
+///         Position::new(1.5, 1.5, 0.0),
+///         Position::new(1.5, 0.5, 0.0),
+///     ])
+/// );
+/// ```
+pub fn find_path(
+    map: &GameMap,
+    from: &Position<f64>,
+    goal: &Position<i32>,
+    options: &PathOptions,
+) -> Option<Vec<Position<f64>>> {
+    let start = GameMap::cell_of(from);
+    let start = Position::new(*start.x(), *start.y(), 0.0);
+    if !map.contains(&start) || !map.contains(goal) {
+        return None;
+    }
+    let mut costs = HashMap::from([(start.clone(), 0.0)]);
+    let mut came_from: HashMap<Position<i32>, Position<i32>> = HashMap::new();
+    let mut open = BinaryHeap::from([Open {
+        estimate: heuristic(options, &start, goal),
+        cell: start.clone(),
+    }]);
+    while let Some(Open { estimate, cell }) = open.pop() {
+        let cost = costs[&cell];
+        if cell == *goal {
+            let mut path = vec![cell];
+            while let Some(previous) = came_from.get(path.last()?) {
+                path.push(previous.clone());
+            }
+            path.pop();
+            return Some(path.iter().rev().map(Position::center).collect());
+        }
+        if estimate > cost + heuristic(options, &cell, goal) {
+            // Already reached more cheaply.
+            continue;
+        }
+        for (next, step_cost) in successors(map, options, &cell) {
+            let next_cost = cost + step_cost;
+            if costs.get(&next).is_none_or(|known| next_cost < *known) {
+                costs.insert(next.clone(), next_cost);
+                came_from.insert(next.clone(), cell.clone());
+                open.push(Open {
+                    estimate: next_cost + heuristic(options, &next, goal),
+                    cell: next,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// The cost of the cheapest path from the cell `from` is in to every cell
+/// it can reach, itself included at 0.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Position, Wall};
+/// use thuai_8_agent_rust::agent::pathfinding::{self, PathOptions};
+///
+/// // Cell (0, 0) is walled off on its right and top.
+/// let environment = EnvironmentInfo::new(
+///     2,
+///     vec![Wall::new(1, 0, Angle::from_degrees(90.0)), Wall::new(0, 1, Angle::ZERO)],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+///
+/// let open = pathfinding::reachable(&map, &Position::new(1.5, 1.5, 0.0), &PathOptions::new());
+/// assert_eq!(open.len(), 3);
+/// assert_eq!(open[&Position::new(1, 0, 0.0)], 1.0);
+///
+/// let closed = pathfinding::reachable(&map, &Position::new(0.5, 0.5, 0.0), &PathOptions::new());
+/// assert_eq!(closed.len(), 1);
+/// ```
+pub fn reachable(
+    map: &GameMap,
+    from: &Position<f64>,
+    options: &PathOptions,
+) -> HashMap<Position<i32>, f64> {
+    let start = GameMap::cell_of(from);
+    let start = Position::new(*start.x(), *start.y(), 0.0);
+    if !map.contains(&start) {
+        return HashMap::new();
+    }
+    let mut costs = HashMap::from([(start.clone(), 0.0)]);
+    let mut open = BinaryHeap::from([Open {
+        estimate: 0.0,
+        cell: start,
+    }]);
+    while let Some(Open { estimate, cell }) = open.pop() {
+        if estimate > costs[&cell] {
+            // Already reached more cheaply.
+            continue;
+        }
+        for (next, step_cost) in successors(map, options, &cell) {
+            let next_cost = estimate + step_cost;
+            if costs.get(&next).is_none_or(|known| next_cost < *known) {
+                costs.insert(next.clone(), next_cost);
+                open.push(Open {
+                    estimate: next_cost,
+                    cell: next,
+                });
+            }
+        }
+    }
+    costs
+}
+
+/// Length of `path`, walked from the centre of the cell `from` is in.
+fn path_length(from: &Position<f64>, path: &[Position<f64>]) -> f64 {
+    let mut previous = from.to_cell().center();
+    let mut length = 0.0;
+    for point in path {
+        length += previous.distance_to(point);
+        previous = point.clone();
+    }
+    length
+}
+
+/// What shooting down one fence is worth, see [`analyze_fence`].
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct FenceAnalysis {
+    /// Hits needed to destroy the fence, [`None`] if the weapon cannot.
+    shots: Option<u32>,
+    /// Length of the path to the goal with the fence standing.
+    current: Option<f64>,
+    /// Length of the path to the goal once the fence is gone.
+    opened: Option<f64>,
+}
+
+impl FenceAnalysis {
+    /// How much shorter the path gets by breaking the fence; infinite if
+    /// it opens the only way. [`None`] if it does not help or cannot be
+    /// broken.
+    pub fn saves(&self) -> Option<f64> {
+        self.shots?;
+        match (self.current, self.opened) {
+            (Some(current), Some(opened)) if opened < current => Some(current - opened),
+            (None, Some(_)) => Some(f64::INFINITY),
+            _ => None,
+        }
+    }
+
+    /// Whether breaking the fence opens a shorter path.
+    pub fn opens_shorter_path(&self) -> bool {
+        self.saves().is_some()
+    }
+}
+
+/// How many shots of `weapon` break `fence`, and how the path from `from`
+/// to `goal` changes once it is gone.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Fence, Position, Wall, Weapon};
+/// use thuai_8_agent_rust::agent::pathfinding::{self, PathOptions};
+///
+/// // A wall along x = 1 with a fence at its bottom.
+/// let fence = Fence::new(Position::new(1, 0, Angle::from_degrees(90.0)), 3);
+/// let environment = EnvironmentInfo::new(
+///     3,
+///     vec![Wall::new(1, 1, Angle::from_degrees(90.0))],
+///     vec![fence.clone()],
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+/// let weapon = Weapon::new(1.0, 1.0, false, false, 2, 10, 10);
+///
+/// let analysis = pathfinding::analyze_fence(
+///     &map,
+///     &weapon,
+///     &fence,
+///     &Position::new(0.5, 0.5, 0.0),
+///     &Position::new(2, 0, 0.0),
+///     &PathOptions::new(),
+/// );
+///
+/// assert_eq!(analysis.shots(), Some(2));
+/// assert_eq!(analysis.current(), Some(6.0));
+/// assert_eq!(analysis.saves(), Some(4.0));
+/// ```
+pub fn analyze_fence(
+    map: &GameMap,
+    weapon: &Weapon,
+    fence: &Fence,
+    from: &Position<f64>,
+    goal: &Position<i32>,
+    options: &PathOptions,
+) -> FenceAnalysis {
+    let length =
+        |map: &GameMap| find_path(map, from, goal, options).map(|path| path_length(from, &path));
+    let position = fence.position();
+    let mut opened = map.clone();
+    let removed =
+        opened.remove_barrier(*position.x(), *position.y(), is_vertical(*position.angle()));
+    FenceAnalysis {
+        shots: weapon.shots_to_break(fence),
+        current: length(map),
+        opened: match removed {
+            Some(Barrier::Fence(_)) => length(&opened),
+            _ => length(map),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{Angle, EnvironmentInfo, Fence, Wall};
+
+    fn center(x: i32, y: i32) -> Position<f64> {
+        Position::new(x, y, 0.0).center()
+    }
+
+    #[test]
+    fn finds_paths_around_barriers() {
+        // A wall from (2, 0) to (2, 3), with a fence above it.
+        let environment = EnvironmentInfo::new(
+            4,
+            vec![
+                Wall::new(2, 0, Angle::from_degrees(90.0)),
+                Wall::new(2, 1, Angle::from_degrees(90.0)),
+                Wall::new(2, 2, Angle::from_degrees(90.0)),
+            ],
+            vec![Fence::new(
+                Position::new(2, 3, Angle::from_degrees(90.0)),
+                2,
+            )],
+            Vec::new(),
+        );
+        let map = GameMap::new(&environment);
+        let goal = Position::new(3, 0, 0.0);
+
+        let around = find_path(&map, &center(1, 0), &goal, &PathOptions::new());
+        assert_eq!(around, None);
+
+        let through = find_path(
+            &map,
+            &center(1, 0),
+            &goal,
+            &PathOptions::new().with_passable_fences(2),
+        )
+        .unwrap();
+        assert_eq!(through.len(), 8);
+        assert_eq!(through[2..4], [center(1, 3), center(2, 3)]);
+        assert_eq!(through.last(), Some(&center(3, 0)));
+
+        let diagonal = PathOptions::new().with_diagonal(true);
+        assert_eq!(
+            find_path(&map, &center(0, 0), &Position::new(1, 1, 0.0), &diagonal),
+            Some(vec![center(1, 1)])
+        );
+        // No cutting the corner at the end of the wall.
+        let corner = find_path(&map, &center(1, 2), &Position::new(2, 3, 0.0), &diagonal);
+        assert_eq!(corner, None);
+
+        assert_eq!(
+            find_path(
+                &map,
+                &center(1, 1),
+                &Position::new(1, 1, 0.0),
+                &PathOptions::new()
+            ),
+            Some(Vec::new())
+        );
+    }
+}
@@ -1,22 +1,65 @@
 use super::{
-    connection::ConnectionAPI,
-    model::{AvailableBuffs, BuffKind, EnvironmentInfo, GameStatistics, Players, SkillKind},
+    AgentError,
+    connection::{ConnectionAPI, ConnectionStats},
+    model::{
+        AvailableBuffs, BuffKind, EnvironmentInfo, GameStatistics, Player, PlayerToken, Players,
+        SkillKind,
+    },
 };
 
 pub trait PlayerOperate: ConnectionAPI {
-    fn token(&self) -> &str;
+    fn token(&self) -> &PlayerToken;
     fn players_info(&self) -> Option<&Players>;
     fn game_statistics(&self) -> Option<&GameStatistics>;
     fn environment_info(&self) -> Option<&EnvironmentInfo>;
     fn available_buffs(&self) -> Option<&AvailableBuffs>;
-    fn move_forward(&mut self, distance: f64) -> impl std::future::Future<Output = ()> + Send;
-    fn move_backward(&mut self, distance: f64) -> impl std::future::Future<Output = ()> + Send;
-    fn turn_clockwise(&mut self, angle: u32) -> impl std::future::Future<Output = ()> + Send;
+    fn connection_stats(&self) -> ConnectionStats;
+
+    /// The cached player with the own token.
+    fn self_player(&self) -> Option<&Player> {
+        self.players_info()?
+            .iter()
+            .find(|player| player.token() == self.token())
+    }
+
+    /// The first cached player with another token; the only one in a game
+    /// of two.
+    fn opponent(&self) -> Option<&Player> {
+        self.opponents().into_iter().next()
+    }
+
+    /// Every cached player with another token.
+    fn opponents(&self) -> Vec<&Player> {
+        self.players_info()
+            .into_iter()
+            .flatten()
+            .filter(|player| player.token() != self.token())
+            .collect()
+    }
+
+    fn move_forward(
+        &mut self,
+        distance: f64,
+    ) -> impl std::future::Future<Output = Result<(), AgentError>> + Send;
+    fn move_backward(
+        &mut self,
+        distance: f64,
+    ) -> impl std::future::Future<Output = Result<(), AgentError>> + Send;
+    fn turn_clockwise(
+        &mut self,
+        angle: u32,
+    ) -> impl std::future::Future<Output = Result<(), AgentError>> + Send;
     fn turn_counter_clockwise(
         &mut self,
         angle: u32,
-    ) -> impl std::future::Future<Output = ()> + Send;
-    fn attack(&mut self) -> impl std::future::Future<Output = ()> + Send;
-    fn use_skill(&mut self, skill: SkillKind) -> impl std::future::Future<Output = ()> + Send;
-    fn select_buff(&mut self, buff: BuffKind) -> impl std::future::Future<Output = ()> + Send;
+    ) -> impl std::future::Future<Output = Result<(), AgentError>> + Send;
+    fn attack(&mut self) -> impl std::future::Future<Output = Result<(), AgentError>> + Send;
+    fn use_skill(
+        &mut self,
+        skill: SkillKind,
+    ) -> impl std::future::Future<Output = Result<(), AgentError>> + Send;
+    fn select_buff(
+        &mut self,
+        buff: BuffKind,
+    ) -> impl std::future::Future<Output = Result<(), AgentError>> + Send;
 }
@@ -0,0 +1,300 @@
+//! Requesting each part of the game state at its own rate.
+//!
+//! Players change every tick, the environment less often and the game
+//! statistics rarely, so requesting everything on every poll mostly loads
+//! the server. A [`PollSchedule`] sets how many ticks apart each part is
+//! requested, and a [`Poller`] tells which parts are due, see
+//! [`Agent::poll`](super::Agent::poll).
+//!
+//! The server only tells the tick in the statistics the schedule itself
+//! requests, so between two of them the [`Poller`] estimates the tick from
+//! the rate the announced ticks went at.
+
+use getset::CopyGetters;
+
+use std::time::Instant;
+
+use super::freshness::CachedState;
+use super::model::Stage;
+
+/// Default ticks between two `GET_PLAYER_INFO` requests.
+pub const DEFAULT_PLAYERS_EVERY: u64 = 1;
+/// Default ticks between two `GET_ENVIRONMENT_INFO` requests.
+pub const DEFAULT_ENVIRONMENT_EVERY: u64 = 2;
+/// Default ticks between two `GET_GAME_STATISTICS` requests.
+pub const DEFAULT_STATISTICS_EVERY: u64 = 10;
+/// Default ticks between two `GET_AVAILABLE_BUFFS` requests.
+pub const DEFAULT_BUFFS_EVERY: u64 = 10;
+
+/// How many ticks apart each part of the state is requested; 0 never
+/// requests it.
+///
+/// Should be created with [`PollSchedule::new`] (or [`Default`]) and then
+/// extended with the `with_*` methods.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct PollSchedule {
+    players: u64,
+    environment: u64,
+    statistics: u64,
+    buffs: u64,
+}
+
+impl Default for PollSchedule {
+    fn default() -> Self {
+        PollSchedule {
+            players: DEFAULT_PLAYERS_EVERY,
+            environment: DEFAULT_ENVIRONMENT_EVERY,
+            statistics: DEFAULT_STATISTICS_EVERY,
+            buffs: DEFAULT_BUFFS_EVERY,
+        }
+    }
+}
+
+impl PollSchedule {
+    /// Constructs a [`PollSchedule`] with the defaults.
+    pub fn new() -> PollSchedule {
+        PollSchedule::default()
+    }
+
+    /// Request both players every `ticks` ticks.
+    pub fn with_players_every(mut self, ticks: u64) -> Self {
+        self.players = ticks;
+        self
+    }
+
+    /// Request the environment every `ticks` ticks.
+    pub fn with_environment_every(mut self, ticks: u64) -> Self {
+        self.environment = ticks;
+        self
+    }
+
+    /// Request the game statistics every `ticks` ticks.
+    pub fn with_statistics_every(mut self, ticks: u64) -> Self {
+        self.statistics = ticks;
+        self
+    }
+
+    /// Request the available buffs every `ticks` ticks of the `REST` stage.
+    pub fn with_buffs_every(mut self, ticks: u64) -> Self {
+        self.buffs = ticks;
+        self
+    }
+
+    /// Ticks between two requests of `state`.
+    pub fn every(&self, state: CachedState) -> u64 {
+        match state {
+            CachedState::Players => self.players,
+            CachedState::Environment => self.environment,
+            CachedState::Statistics => self.statistics,
+            CachedState::Buffs => self.buffs,
+        }
+    }
+}
+
+/// Estimates the server tick between the ticks announced.
+#[derive(Debug, Clone, Default)]
+struct TickClock {
+    /// The first tick seen since the ticks started, and when.
+    first: Option<(u64, Instant)>,
+    /// The latest tick seen, and when.
+    latest: Option<(u64, Instant)>,
+}
+
+impl TickClock {
+    /// Take note of `tick` announced at `now`, returning whether the ticks
+    /// started over.
+    fn observe(&mut self, tick: u64, now: Instant) -> bool {
+        match self.latest {
+            Some((latest, _)) if tick == latest => false,
+            Some((latest, _)) if tick > latest => {
+                self.latest = Some((tick, now));
+                false
+            }
+            latest => {
+                self.first = Some((tick, now));
+                self.latest = Some((tick, now));
+                latest.is_some()
+            }
+        }
+    }
+
+    /// Seconds per tick, once two different ticks were seen.
+    fn seconds_per_tick(&self) -> Option<f64> {
+        let (first, since) = self.first?;
+        let (latest, at) = self.latest?;
+        (latest > first).then(|| (at - since).as_secs_f64() / (latest - first) as f64)
+    }
+
+    /// The tick at `now`, counted on from the latest one seen.
+    fn estimate(&self, now: Instant) -> Option<u64> {
+        let (latest, at) = self.latest?;
+        let passed = match self.seconds_per_tick() {
+            Some(seconds) if seconds > 0.0 => (now - at).as_secs_f64() / seconds,
+            _ => 0.0,
+        };
+        Some(latest + passed as u64)
+    }
+}
+
+/// Tracks when each part of the state was last requested.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::freshness::CachedState;
+/// use thuai_8_agent_rust::agent::model::Stage;
+/// use thuai_8_agent_rust::agent::poller::{PollSchedule, Poller};
+///
+/// let mut poller = Poller::new(PollSchedule::new().with_environment_every(3));
+///
+/// assert_eq!(
+///     poller.due(Some(10), Some(Stage::Battle)),
+///     [
+///         CachedState::Players,
+///         CachedState::Environment,
+///         CachedState::Statistics
+///     ]
+/// );
+/// // Nothing twice in the same tick, but the statistics until the ticks
+/// // were seen moving.
+/// assert_eq!(
+///     poller.due(Some(10), Some(Stage::Battle)),
+///     [CachedState::Statistics]
+/// );
+/// // The buffs only while they can be selected.
+/// assert_eq!(
+///     poller.due(Some(11), Some(Stage::Rest)),
+///     [CachedState::Players, CachedState::Buffs]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Poller {
+    schedule: PollSchedule,
+    /// The tick each part was last requested at.
+    last: [Option<u64>; 4],
+    clock: TickClock,
+}
+
+impl Default for Poller {
+    fn default() -> Self {
+        Poller::new(PollSchedule::default())
+    }
+}
+
+impl Poller {
+    const STATES: [CachedState; 4] = [
+        CachedState::Players,
+        CachedState::Environment,
+        CachedState::Statistics,
+        CachedState::Buffs,
+    ];
+
+    /// Constructs a [`Poller`] following `schedule`, with everything due.
+    pub fn new(schedule: PollSchedule) -> Poller {
+        Poller {
+            schedule,
+            last: [None; 4],
+            clock: TickClock::default(),
+        }
+    }
+
+    /// The schedule the parts of the state are requested by.
+    pub fn schedule(&self) -> &PollSchedule {
+        &self.schedule
+    }
+
+    /// The parts of the state due now, with `tick` the latest tick the
+    /// server announced and `stage` the stage of the game. They are then
+    /// taken as requested.
+    ///
+    /// Between the announced ticks the tick is estimated, and the
+    /// statistics are due on every call until two different ticks were
+    /// announced. Without a tick, e.g. before the first statistics or after
+    /// the ticks started over, every part is due. The buffs are only due in
+    /// the `REST` stage, where they can be selected.
+    pub fn due(&mut self, tick: Option<u64>, stage: Option<Stage>) -> Vec<CachedState> {
+        self.due_at(tick, stage, Instant::now())
+    }
+
+    fn due_at(
+        &mut self,
+        tick: Option<u64>,
+        stage: Option<Stage>,
+        now: Instant,
+    ) -> Vec<CachedState> {
+        let restarted = match tick {
+            Some(tick) => self.clock.observe(tick, now),
+            None => {
+                self.clock = TickClock::default();
+                true
+            }
+        };
+        if restarted {
+            self.last = [None; 4];
+        }
+        let tick = self.clock.estimate(now);
+        let learning = self.clock.seconds_per_tick().is_none();
+        let mut due = Vec::new();
+        for (state, last) in Poller::STATES.into_iter().zip(&mut self.last) {
+            let every = self.schedule.every(state);
+            if every == 0 || (state == CachedState::Buffs && stage != Some(Stage::Rest)) {
+                continue;
+            }
+            let is_due = match (tick, *last) {
+                (Some(tick), Some(last)) => {
+                    tick.saturating_sub(last) >= every
+                        || (state == CachedState::Statistics && learning)
+                }
+                _ => true,
+            };
+            if is_due {
+                *last = tick;
+                due.push(state);
+            }
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn estimates_ticks_between_statistics() {
+        let schedule = PollSchedule::new().with_environment_every(3);
+        let mut poller = Poller::new(schedule);
+        let start = Instant::now();
+        let at = |millis: u64| start + Duration::from_millis(millis);
+        let battle = Some(Stage::Battle);
+
+        assert_eq!(poller.due_at(Some(10), battle, at(0)).len(), 3);
+        assert_eq!(
+            poller.due_at(Some(10), battle, at(50)),
+            [CachedState::Statistics]
+        );
+        // 50ms per tick from now on.
+        assert_eq!(
+            poller.due_at(Some(12), battle, at(100)),
+            [CachedState::Players]
+        );
+        assert!(poller.due_at(Some(12), battle, at(120)).is_empty());
+        assert_eq!(
+            poller.due_at(Some(12), battle, at(150)),
+            [CachedState::Players, CachedState::Environment]
+        );
+        assert!(
+            poller
+                .due_at(Some(12), battle, at(600))
+                .contains(&CachedState::Statistics)
+        );
+
+        // A new game makes everything due again.
+        assert_eq!(poller.due_at(Some(1), Some(Stage::Rest), at(650)).len(), 4);
+        assert_eq!(poller.due_at(None, battle, at(700)).len(), 3);
+    }
+}
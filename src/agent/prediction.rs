@@ -0,0 +1,218 @@
+//! Where an opponent will be in a few ticks.
+//!
+//! An [`OpponentPredictor`] estimates a player's velocity from the last
+//! snapshots of a [`StateHistory`] and extrapolates it, with a confidence
+//! that decays with every tick predicted, for leading shots and
+//! interception.
+
+use super::history::StateHistory;
+use super::model::{Angle, PlayerToken, Position};
+use getset::{CopyGetters, Getters};
+
+/// Default number of recent snapshots the velocity is estimated from.
+pub const DEFAULT_WINDOW: usize = 4;
+
+/// Default factor the confidence is multiplied by per tick predicted.
+pub const DEFAULT_CONFIDENCE_DECAY: f64 = 0.9;
+
+/// Times the flight time of a leading shot is refined.
+const LEAD_ITERATIONS: usize = 4;
+
+/// Confidence of a prediction from a single snapshot, which assumes the
+/// player stands still.
+const STANDING_CONFIDENCE: f64 = 0.5;
+
+/// A predicted position.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters, CopyGetters)]
+pub struct Prediction {
+    #[getset(get = "pub")]
+    position: Position<f64>,
+    /// From 0 to 1.
+    #[getset(get_copy = "pub")]
+    confidence: f64,
+}
+
+/// Constant velocity model of one player.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::history::{Snapshot, StateHistory};
+/// use thuai_8_agent_rust::agent::model::{
+///     Angle, Armor, ArmorKnifeState, Player, Position, Weapon,
+/// };
+/// use thuai_8_agent_rust::agent::prediction::OpponentPredictor;
+///
+/// let at = |x: f64| {
+///     Player::new(
+///         "enemy",
+///         Position::new(x, 2.0, 0.0),
+///         Weapon::new(1.0, 1.0, false, false, 10, 10, 10),
+///         Armor::new(false, false, 0, 20, 0.0, ArmorKnifeState::NotOwned),
+///         Vec::new(),
+///     )
+/// };
+/// let mut history = StateHistory::new(8);
+/// for tick in 0..3 {
+///     history.push(Snapshot::new(tick, vec![at(1.0 + 0.5 * tick as f64)]));
+/// }
+///
+/// let predictor = OpponentPredictor::new(&history, &"enemy".into()).unwrap();
+///
+/// assert_eq!(predictor.speed(), 0.5);
+/// assert_eq!(predictor.heading(), Angle::ZERO);
+/// let prediction = predictor.predict(4);
+/// assert_eq!(prediction.position(), &Position::new(4.0, 2.0, 0.0));
+/// assert!((prediction.confidence() - 0.9f64.powi(4)).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpponentPredictor {
+    last: Position<f64>,
+    velocity: Option<(f64, f64)>,
+    decay: f64,
+}
+
+impl OpponentPredictor {
+    /// Estimate the motion of the player with `token` from the last
+    /// [`DEFAULT_WINDOW`] snapshots it is in. [`None`] if it is in none.
+    pub fn new(history: &StateHistory, token: &PlayerToken) -> Option<OpponentPredictor> {
+        OpponentPredictor::with_window(history, token, DEFAULT_WINDOW)
+    }
+
+    /// Like [`OpponentPredictor::new`], over the last `window` snapshots the
+    /// player is in. A longer window smooths out jitter but reacts later to
+    /// turns.
+    pub fn with_window(
+        history: &StateHistory,
+        token: &PlayerToken,
+        window: usize,
+    ) -> Option<OpponentPredictor> {
+        let seen: Vec<_> = history
+            .iter()
+            .rev()
+            .filter_map(|snapshot| {
+                let player = snapshot
+                    .players()
+                    .iter()
+                    .find(|player| player.token() == token)?;
+                Some((*snapshot.tick(), player.position()))
+            })
+            .take(window.max(2))
+            .collect();
+        let (tick, last) = seen.first()?;
+        let velocity =
+            seen.last()
+                .filter(|(first_tick, _)| first_tick < tick)
+                .map(|(first_tick, first)| {
+                    let ticks = (tick - first_tick) as f64;
+                    (
+                        (last.x() - first.x()) / ticks,
+                        (last.y() - first.y()) / ticks,
+                    )
+                });
+        Some(OpponentPredictor {
+            last: (*last).clone(),
+            velocity,
+            decay: DEFAULT_CONFIDENCE_DECAY,
+        })
+    }
+
+    /// Multiply the confidence by `decay` for every tick predicted.
+    pub fn with_decay(mut self, decay: f64) -> Self {
+        self.decay = decay.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Estimated velocity per tick, zero if only seen once.
+    pub fn velocity(&self) -> (f64, f64) {
+        self.velocity.unwrap_or((0.0, 0.0))
+    }
+
+    /// Distance covered per tick.
+    pub fn speed(&self) -> f64 {
+        let (dx, dy) = self.velocity();
+        dx.hypot(dy)
+    }
+
+    /// Direction of motion; where the player faces when it stands still.
+    pub fn heading(&self) -> Angle {
+        match self.velocity {
+            Some((dx, dy)) if dx != 0.0 || dy != 0.0 => Angle::from_radians(dy.atan2(dx)),
+            _ => *self.last.angle(),
+        }
+    }
+
+    /// Where the player will be `ticks` ticks after the last snapshot.
+    pub fn predict(&self, ticks: u32) -> Prediction {
+        let (dx, dy) = self.velocity();
+        let base = if self.velocity.is_some() {
+            1.0
+        } else {
+            STANDING_CONFIDENCE
+        };
+        let position = self.last.offset(dx * ticks as f64, dy * ticks as f64);
+        Prediction {
+            position: Position::new(*position.x(), *position.y(), self.heading()),
+            confidence: base * self.decay.powi(ticks as i32),
+        }
+    }
+
+    /// Where to aim from `shooter` for a bullet flying `bullet_speed` per
+    /// tick to meet the player, ignoring bounces. Where the player is now
+    /// if the bullet does not move.
+    pub fn lead(&self, shooter: &Position<f64>, bullet_speed: f64) -> Prediction {
+        if bullet_speed <= 0.0 {
+            return self.predict(0);
+        }
+        let mut prediction = self.predict(0);
+        for _ in 0..LEAD_ITERATIONS {
+            let ticks = (shooter.distance_to(prediction.position()) / bullet_speed).ceil();
+            prediction = self.predict(ticks as u32);
+        }
+        prediction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::history::Snapshot;
+    use crate::testing::fixtures::PlayerBuilder;
+
+    #[test]
+    fn smooths_over_the_window() {
+        let mut history = StateHistory::new(8);
+        let player = |x, y| PlayerBuilder::new("enemy").at(x, y, 1.0).build();
+        assert!(OpponentPredictor::new(&history, &"enemy".into()).is_none());
+
+        history.push(Snapshot::new(0, vec![player(0.0, 0.0)]));
+        let standing = OpponentPredictor::new(&history, &"enemy".into()).unwrap();
+        assert_eq!(standing.heading(), Angle::from(1.0));
+        assert_eq!(standing.predict(0).confidence(), STANDING_CONFIDENCE);
+
+        // A jitter at tick 2 is averaged out over the window.
+        for (tick, x) in [(1, 1.0), (2, 2.5), (3, 3.0)] {
+            history.push(Snapshot::new(tick, vec![player(x, 0.0)]));
+        }
+        history.push(Snapshot::new(5, Vec::new()));
+        let moving = OpponentPredictor::new(&history, &"enemy".into())
+            .unwrap()
+            .with_decay(0.5);
+        assert_eq!(moving.velocity(), (1.0, 0.0));
+        assert_eq!(moving.predict(2).position(), &Position::new(5.0, 0.0, 0.0));
+        assert_eq!(moving.predict(2).confidence(), 0.25);
+
+        let short = OpponentPredictor::with_window(&history, &"enemy".into(), 2).unwrap();
+        assert_eq!(short.velocity(), (0.5, 0.0));
+
+        // A bullet of speed 2 meets it after 2 ticks.
+        let shooter = Position::new(3.0, 2.0, 0.0);
+        assert_eq!(
+            moving.lead(&shooter, 2.0).position(),
+            &Position::new(5.0, 0.0, 0.0)
+        );
+        assert_eq!(moving.lead(&shooter, 0.0), moving.predict(0));
+    }
+}
@@ -0,0 +1,230 @@
+//! Queries over recorded snapshots, for analysing matches.
+//!
+//! A [`Query`] selects snapshots of a [`StateHistory`] with filters, maps
+//! them to values, or exports them as CSV, so a question about a match
+//! takes a few lines instead of a loop over the history. A recorded match
+//! becomes a history with [`StateHistory::replay`].
+//!
+//! # Example
+//!
+//! ```
+//! use thuai_8_agent_rust::agent::history::{Snapshot, StateHistory};
+//! use thuai_8_agent_rust::agent::model::{
+//!     Armor, ArmorKnifeState, GameStatistics, Player, Position, ScoreBoard, Stage, Weapon,
+//! };
+//!
+//! let snapshot = |tick: u32, stage: Stage, health: i32| {
+//!     let player = Player::new(
+//!         "1919810",
+//!         Position::new(0.0, 0.0, 0.0),
+//!         Weapon::new(1.0, 1.0, false, false, 10, 10, 10),
+//!         Armor::new(false, false, 0, health, 0.0, ArmorKnifeState::NotOwned),
+//!         Vec::new(),
+//!     );
+//!     Snapshot::new(tick, vec![player])
+//!         .with_statistics(GameStatistics::new(stage, 0, tick, ScoreBoard::default()))
+//! };
+//! let mut history = StateHistory::new(8);
+//! history.push(snapshot(1, Stage::Rest, 20));
+//! history.push(snapshot(2, Stage::Battle, 20));
+//! history.push(snapshot(3, Stage::Battle, 14));
+//!
+//! let me = "1919810".into();
+//! let health = history
+//!     .query()
+//!     .in_stage(Stage::Battle)
+//!     .map(|snapshot| snapshot.player(&me).map(|player| *player.armor().health()));
+//! assert_eq!(health, [Some(20), Some(14)]);
+//!
+//! let csv = history
+//!     .query()
+//!     .csv()
+//!     .column("health", |snapshot| {
+//!         snapshot.player(&me).map(|player| *player.armor().health())
+//!     })
+//!     .to_string();
+//! assert_eq!(csv, "tick,health\n1,20\n2,20\n3,14\n");
+//! ```
+
+use std::fmt::Display;
+use std::io;
+
+use super::history::{Snapshot, StateHistory};
+use super::model::Stage;
+
+/// A selection of snapshots, oldest first.
+///
+/// Should be created with [`StateHistory::query`] and then narrowed with
+/// the filters.
+#[derive(Debug, Clone)]
+pub struct Query<'a> {
+    snapshots: Vec<&'a Snapshot>,
+}
+
+impl StateHistory {
+    /// Select every kept snapshot.
+    pub fn query(&self) -> Query<'_> {
+        Query {
+            snapshots: self.iter().collect(),
+        }
+    }
+}
+
+impl<'a> Query<'a> {
+    /// Keep the snapshots `predicate` holds for.
+    pub fn filter(mut self, predicate: impl Fn(&Snapshot) -> bool) -> Self {
+        self.snapshots.retain(|snapshot| predicate(snapshot));
+        self
+    }
+
+    /// Keep the snapshots taken in `stage`. Snapshots without statistics
+    /// have no known stage and are dropped.
+    pub fn in_stage(self, stage: Stage) -> Self {
+        self.filter(|snapshot| snapshot.stage() == Some(stage))
+    }
+
+    /// Keep the snapshots from tick `from` up to and including `to`.
+    pub fn between(self, from: u32, to: u32) -> Self {
+        self.filter(|snapshot| (from..=to).contains(snapshot.tick()))
+    }
+
+    /// The value of `f` for each snapshot.
+    pub fn map<T>(&self, f: impl Fn(&Snapshot) -> T) -> Vec<T> {
+        self.snapshots.iter().map(|snapshot| f(snapshot)).collect()
+    }
+
+    /// The value of `f` for each snapshot, paired with its tick.
+    pub fn series<T>(&self, f: impl Fn(&Snapshot) -> T) -> Vec<(u32, T)> {
+        self.snapshots
+            .iter()
+            .map(|snapshot| (*snapshot.tick(), f(snapshot)))
+            .collect()
+    }
+
+    /// The selected snapshots, oldest first.
+    pub fn snapshots(&self) -> &[&'a Snapshot] {
+        &self.snapshots
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Export the selected snapshots as CSV, one row each, starting with a
+    /// `tick` column.
+    pub fn csv(&self) -> CsvExport<'_, 'a> {
+        CsvExport {
+            query: self,
+            columns: Vec::new(),
+        }
+    }
+}
+
+type Column<'q> = Box<dyn Fn(&Snapshot) -> Option<String> + 'q>;
+
+/// The columns of a CSV export, see [`Query::csv`].
+pub struct CsvExport<'q, 'a> {
+    query: &'q Query<'a>,
+    columns: Vec<(String, Column<'q>)>,
+}
+
+impl<'q> CsvExport<'q, '_> {
+    /// Add a column `name` with the value of `f`; [`None`] leaves the cell
+    /// empty.
+    pub fn column<T: Display>(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&Snapshot) -> Option<T> + 'q,
+    ) -> Self {
+        let column = move |snapshot: &Snapshot| f(snapshot).map(|value| value.to_string());
+        self.columns.push((name.into(), Box::new(column)));
+        self
+    }
+
+    /// Write the header and the rows to `writer`.
+    pub fn write_to(&self, mut writer: impl io::Write) -> io::Result<()> {
+        write!(writer, "tick")?;
+        for (name, _) in &self.columns {
+            write!(writer, ",{}", escape(name))?;
+        }
+        writeln!(writer)?;
+        for snapshot in &self.query.snapshots {
+            write!(writer, "{}", snapshot.tick())?;
+            for (_, column) in &self.columns {
+                let cell = column(snapshot).unwrap_or_default();
+                write!(writer, ",{}", escape(&cell))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for CsvExport<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut buffer = Vec::new();
+        self.write_to(&mut buffer).map_err(|_| std::fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&buffer))
+    }
+}
+
+/// Quote `cell` if it holds a separator, a quote or a line break.
+fn escape(cell: &str) -> std::borrow::Cow<'_, str> {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\"")).into()
+    } else {
+        cell.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{GameStatistics, ScoreBoard};
+
+    fn snapshot(tick: u32, stage: Option<Stage>) -> Snapshot {
+        let snapshot = Snapshot::new(tick, Vec::new());
+        match stage {
+            Some(stage) => {
+                snapshot.with_statistics(GameStatistics::new(stage, 0, tick, ScoreBoard::default()))
+            }
+            None => snapshot,
+        }
+    }
+
+    #[test]
+    fn filters_and_quotes_cells() {
+        let mut history = StateHistory::new(8);
+        history.push(snapshot(1, None));
+        history.push(snapshot(2, Some(Stage::Rest)));
+        history.push(snapshot(3, Some(Stage::Battle)));
+        history.push(snapshot(4, Some(Stage::Battle)));
+
+        assert_eq!(history.query().in_stage(Stage::Battle).len(), 2);
+        assert_eq!(
+            history.query().between(2, 3).series(Snapshot::stage),
+            [(2, Some(Stage::Rest)), (3, Some(Stage::Battle))]
+        );
+        assert!(history.query().between(5, 9).is_empty());
+
+        let csv = history
+            .query()
+            .filter(|snapshot| *snapshot.tick() % 2 == 1)
+            .csv()
+            .column("stage, if known", |snapshot| {
+                snapshot.stage().map(|stage| format!("{stage:?}"))
+            })
+            .column("note", |_| Some("say \"hi\""))
+            .to_string();
+        assert_eq!(
+            csv,
+            "tick,\"stage, if known\",note\n\
+            1,,\"say \"\"hi\"\"\"\n\
+            3,Battle,\"say \"\"hi\"\"\"\n"
+        );
+    }
+}
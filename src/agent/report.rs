@@ -0,0 +1,155 @@
+/*! End-of-match summary, optionally posted to a webhook. */
+use std::fmt::Display;
+
+use getset::Getters;
+use serde::Serialize;
+
+use super::connection::LatencyReport;
+use super::model::PlayerToken;
+
+/// Outcome of a match for one agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MatchResult {
+    #[serde(rename = "WIN")]
+    Win,
+    #[serde(rename = "LOSS")]
+    Loss,
+    #[serde(rename = "DRAW")]
+    Draw,
+    /// The agent's token was not on the final scoreboard.
+    #[serde(rename = "UNKNOWN")]
+    Unknown,
+}
+
+/// Summary of a finished match, built from the final game statistics.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, PartialEq, Getters, Serialize)]
+#[getset(get = "pub")]
+pub struct MatchSummary {
+    token: PlayerToken,
+    result: MatchResult,
+    score: Option<u32>,
+    #[serde(rename = "opponentScore")]
+    opponent_score: Option<u32>,
+    ticks: Option<u64>,
+    /// Number of `ERROR` messages received from the server.
+    errors: u64,
+    /// Where the time of each tick went over the match.
+    latency: LatencyReport,
+}
+
+impl MatchSummary {
+    /// Build the summary for `token` from the raw final `GAME_STATISTICS`
+    /// message. The best score among the other players counts as the
+    /// opponent's score.
+    pub fn from_statistics(
+        token: &PlayerToken,
+        statistics: &serde_json::Value,
+        errors: u64,
+    ) -> Self {
+        let mut score = None;
+        let mut opponent_score: Option<u32> = None;
+        let entries = statistics
+            .get("scores")
+            .and_then(|scores| scores.as_array())
+            .into_iter()
+            .flatten();
+        for entry in entries {
+            let (Some(entry_token), Some(entry_score)) = (
+                entry.get("token").and_then(|token| token.as_str()),
+                entry.get("score").and_then(|score| score.as_u64()),
+            ) else {
+                continue;
+            };
+            let entry_score = entry_score as u32;
+            if token == entry_token {
+                score = Some(entry_score);
+            } else {
+                opponent_score =
+                    Some(opponent_score.map_or(entry_score, |best| best.max(entry_score)));
+            }
+        }
+        let result = match (score, opponent_score) {
+            (Some(mine), Some(theirs)) if mine > theirs => MatchResult::Win,
+            (Some(mine), Some(theirs)) if mine < theirs => MatchResult::Loss,
+            (Some(_), _) => MatchResult::Draw,
+            (None, _) => MatchResult::Unknown,
+        };
+        MatchSummary {
+            token: token.clone(),
+            result,
+            score,
+            opponent_score,
+            ticks: statistics.get("ticks").and_then(|ticks| ticks.as_u64()),
+            errors,
+            latency: LatencyReport::default(),
+        }
+    }
+
+    /// Attach the latency report of the match.
+    pub fn with_latency(mut self, latency: LatencyReport) -> Self {
+        self.latency = latency;
+        self
+    }
+}
+
+impl Display for MatchSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |value: Option<u32>| value.map_or("?".to_string(), |value| value.to_string());
+        write!(
+            f,
+            "Match finished for {}: {:?} ({} : {}), {} errors",
+            self.token,
+            self.result,
+            show(self.score),
+            show(self.opponent_score),
+            self.errors
+        )?;
+        if !self.latency.is_empty() {
+            write!(f, ", latency: {}", self.latency)?;
+        }
+        Ok(())
+    }
+}
+
+/// Post `summary` as JSON to `url`.
+///
+/// The body carries a `text` field with the one-line [`Display`] form, as
+/// expected by Slack-style incoming webhooks, next to the structured fields.
+#[cfg(feature = "webhook")]
+pub async fn post_summary(url: &str, summary: &MatchSummary) -> Result<(), reqwest::Error> {
+    let mut body = serde_json::to_value(summary).unwrap_or_default();
+    body["text"] = summary.to_string().into();
+    reqwest::Client::new()
+        .post(url)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_from_statistics() {
+        let statistics = serde_json::json!({
+            "messageType": "GAME_STATISTICS",
+            "currentStage": "END",
+            "ticks": 1200,
+            "scores": [{"token": "a", "score": 3}, {"token": "b", "score": 1}],
+        });
+
+        let summary = MatchSummary::from_statistics(&"a".into(), &statistics, 2);
+
+        assert_eq!(summary.result(), &MatchResult::Win);
+        assert_eq!(summary.opponent_score(), &Some(1));
+        assert_eq!(
+            summary.to_string(),
+            "Match finished for a: Win (3 : 1), 2 errors"
+        );
+    }
+}
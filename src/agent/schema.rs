@@ -0,0 +1,346 @@
+/*!
+Message schemas observed in recorded traffic, and the differences between
+two recordings.
+
+Feed the frames of a session to a [`Schema`] (or load a recording with
+[`Schema::from_recording`]), then [`diff`] the schemas seen against an old
+and a new server build to spot protocol changes, e.g. a renamed field or a
+new stage.
+
+See `examples/protocol_diff.rs` for a command line front end.
+*/
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::io::{self, BufRead};
+
+use serde_json::Value;
+
+use super::connection::{FrameDirection, RecordedFrame};
+
+/// Distinct string values kept per field; fields with more are not
+/// enum-like and their values are not compared.
+const MAX_STRING_VALUES: usize = 16;
+
+/// What was observed of one field of one kind of message.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct FieldSchema {
+    kinds: BTreeSet<&'static str>,
+    range: Option<(f64, f64)>,
+    /// [`None`] once more than [`MAX_STRING_VALUES`] were seen.
+    strings: Option<BTreeSet<String>>,
+}
+
+impl FieldSchema {
+    fn observe(&mut self, value: &Value) {
+        self.kinds.insert(kind_of(value));
+        match value {
+            Value::Number(number) => {
+                let number = number.as_f64().unwrap_or_default();
+                self.range = Some(match self.range {
+                    Some((min, max)) => (min.min(number), max.max(number)),
+                    None => (number, number),
+                });
+            }
+            Value::String(string) => {
+                if let Some(strings) = &mut self.strings {
+                    strings.insert(string.clone());
+                    if strings.len() > MAX_STRING_VALUES {
+                        self.strings = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn kind_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// The fields seen in the messages of each direction and `messageType`.
+///
+/// Nested fields are named by their path, e.g. `scores[].score`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::connection::FrameDirection;
+/// use thuai_8_agent_rust::agent::schema::{self, Schema};
+///
+/// let mut old = Schema::new();
+/// old.observe(FrameDirection::Received, r#"{"messageType":"GAME_STATISTICS","ticks":3}"#);
+/// let mut new = Schema::new();
+/// new.observe(FrameDirection::Received, r#"{"messageType":"GAME_STATISTICS","tick":3}"#);
+///
+/// assert_eq!(schema::diff(&old, &new).len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    messages: BTreeMap<String, BTreeMap<String, FieldSchema>>,
+}
+
+impl Schema {
+    /// Constructs a [`Schema`] with nothing observed yet.
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    /// Read a recording, one frame per line: either a [`RecordedFrame`] as
+    /// JSON, or a bare frame, which is taken as received. Blank lines and
+    /// frames that are not JSON objects are skipped.
+    pub fn from_recording(reader: impl BufRead) -> io::Result<Schema> {
+        let mut schema = Schema::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<RecordedFrame>(&line) {
+                Ok(recorded) => schema.observe(*recorded.direction(), recorded.frame()),
+                Err(_) => schema.observe(FrameDirection::Received, &line),
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Add one raw frame to the schema.
+    pub fn observe(&mut self, direction: FrameDirection, frame: &str) {
+        let Ok(Value::Object(object)) = serde_json::from_str::<Value>(frame) else {
+            return;
+        };
+        let message_type = object
+            .get("messageType")
+            .and_then(Value::as_str)
+            .unwrap_or("?");
+        let key = format!("{direction:?} {message_type}");
+        let fields = self.messages.entry(key).or_default();
+        for (name, value) in &object {
+            observe_field(fields, name.clone(), value);
+        }
+    }
+
+    /// Whether nothing was observed.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+fn observe_field(fields: &mut BTreeMap<String, FieldSchema>, path: String, value: &Value) {
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object {
+                observe_field(fields, format!("{path}.{name}"), value);
+            }
+        }
+        Value::Array(array) => {
+            for value in array {
+                observe_field(fields, format!("{path}[]"), value);
+            }
+        }
+        _ => {}
+    }
+    fields
+        .entry(path)
+        .or_insert_with(|| FieldSchema {
+            strings: Some(BTreeSet::new()),
+            ..FieldSchema::default()
+        })
+        .observe(value);
+}
+
+/// One difference between two [`Schema`]s. `message` is the direction and
+/// `messageType`, e.g. `Received GAME_STATISTICS`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolChange {
+    MessageAdded {
+        message: String,
+    },
+    MessageRemoved {
+        message: String,
+    },
+    FieldAdded {
+        message: String,
+        field: String,
+    },
+    FieldRemoved {
+        message: String,
+        field: String,
+    },
+    /// The JSON types a field takes changed.
+    KindChanged {
+        message: String,
+        field: String,
+        old: Vec<&'static str>,
+        new: Vec<&'static str>,
+    },
+    /// A number field took values outside its old range.
+    RangeWidened {
+        message: String,
+        field: String,
+        old: (f64, f64),
+        new: (f64, f64),
+    },
+    /// An enum-like string field took new values.
+    ValuesAdded {
+        message: String,
+        field: String,
+        values: Vec<String>,
+    },
+}
+
+impl Display for ProtocolChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolChange::MessageAdded { message } => write!(f, "+ {message}"),
+            ProtocolChange::MessageRemoved { message } => write!(f, "- {message}"),
+            ProtocolChange::FieldAdded { message, field } => write!(f, "+ {message}: {field}"),
+            ProtocolChange::FieldRemoved { message, field } => write!(f, "- {message}: {field}"),
+            ProtocolChange::KindChanged {
+                message,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ {message}: {field} was {}, now {}",
+                old.join("|"),
+                new.join("|")
+            ),
+            ProtocolChange::RangeWidened {
+                message,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ {message}: {field} ranged {}..={}, now {}..={}",
+                old.0, old.1, new.0, new.1
+            ),
+            ProtocolChange::ValuesAdded {
+                message,
+                field,
+                values,
+            } => write!(f, "~ {message}: {field} has new values {values:?}"),
+        }
+    }
+}
+
+/// Everything that changed from `old` to `new`, sorted by message and field.
+pub fn diff(old: &Schema, new: &Schema) -> Vec<ProtocolChange> {
+    let mut changes = Vec::new();
+    for message in old.messages.keys() {
+        if !new.messages.contains_key(message) {
+            changes.push(ProtocolChange::MessageRemoved {
+                message: message.clone(),
+            });
+        }
+    }
+    for (message, new_fields) in &new.messages {
+        let Some(old_fields) = old.messages.get(message) else {
+            changes.push(ProtocolChange::MessageAdded {
+                message: message.clone(),
+            });
+            continue;
+        };
+        for field in old_fields.keys() {
+            if !new_fields.contains_key(field) {
+                changes.push(ProtocolChange::FieldRemoved {
+                    message: message.clone(),
+                    field: field.clone(),
+                });
+            }
+        }
+        for (field, new_field) in new_fields {
+            let Some(old_field) = old_fields.get(field) else {
+                changes.push(ProtocolChange::FieldAdded {
+                    message: message.clone(),
+                    field: field.clone(),
+                });
+                continue;
+            };
+            diff_field(message, field, old_field, new_field, &mut changes);
+        }
+    }
+    changes
+}
+
+fn diff_field(
+    message: &str,
+    field: &str,
+    old: &FieldSchema,
+    new: &FieldSchema,
+    changes: &mut Vec<ProtocolChange>,
+) {
+    if old.kinds != new.kinds {
+        changes.push(ProtocolChange::KindChanged {
+            message: message.to_string(),
+            field: field.to_string(),
+            old: old.kinds.iter().copied().collect(),
+            new: new.kinds.iter().copied().collect(),
+        });
+    }
+    if let (Some(old_range), Some(new_range)) = (old.range, new.range)
+        && (new_range.0 < old_range.0 || new_range.1 > old_range.1)
+    {
+        changes.push(ProtocolChange::RangeWidened {
+            message: message.to_string(),
+            field: field.to_string(),
+            old: old_range,
+            new: new_range,
+        });
+    }
+    if let (Some(old_strings), Some(new_strings)) = (&old.strings, &new.strings)
+        && old.kinds.contains("string")
+    {
+        let values: Vec<String> = new_strings.difference(old_strings).cloned().collect();
+        if !values.is_empty() {
+            changes.push(ProtocolChange::ValuesAdded {
+                message: message.to_string(),
+                field: field.to_string(),
+                values,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = r#"{"direction":"SENT","timestampMs":1,"frame":"{\"messageType\":\"GET_PLAYER_INFO\",\"token\":\"1\"}"}
+{"messageType":"GAME_STATISTICS","currentStage":"REST","ticks":0,"scores":[{"token":"1","score":0}]}
+{"messageType":"GAME_STATISTICS","currentStage":"BATTLE","ticks":200,"scores":[]}
+"#;
+
+    const NEW: &str = r#"{"direction":"SENT","timestampMs":1,"frame":"{\"messageType\":\"GET_PLAYER_INFO\",\"token\":\"1\"}"}
+{"messageType":"GAME_STATISTICS","currentStage":"PREPARE","ticks":300,"scores":[{"token":"1","score":"0"}]}
+{"messageType":"BUFF_SPAWNED","x":1}
+"#;
+
+    #[test]
+    fn reports_protocol_changes() {
+        let old = Schema::from_recording(OLD.as_bytes()).unwrap();
+        let new = Schema::from_recording(NEW.as_bytes()).unwrap();
+
+        let changes: Vec<String> = diff(&old, &new).iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(
+            changes,
+            [
+                "+ Received BUFF_SPAWNED",
+                r#"~ Received GAME_STATISTICS: currentStage has new values ["PREPARE"]"#,
+                "~ Received GAME_STATISTICS: scores[].score was number, now string",
+                "~ Received GAME_STATISTICS: ticks ranged 0..=200, now 300..=300",
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+}
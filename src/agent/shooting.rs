@@ -0,0 +1,215 @@
+//! Where to aim for a shot to hit, bounces included.
+//!
+//! Lasers and reflecting bullets bounce off walls and the edge of the map,
+//! so a target behind a wall may still be hit off a bounce. A
+//! [`ShotPlanner`] tries every firing angle the tank can turn to and keeps
+//! those whose path reaches the target, shortest path first.
+
+use super::ballistics::LaserPath;
+use super::map::GameMap;
+use super::model::{Angle, Position};
+use crate::math::geometry::{self, Segment};
+use getset::CopyGetters;
+
+/// Default difference between two firing angles tried, as
+/// [`PerformTurn`](super::connection::PerformMessage::PerformTurn) turns by
+/// whole degrees.
+pub const DEFAULT_ANGLE_STEP_DEGREES: f64 = 1.0;
+
+/// A firing angle that hits the target.
+///
+/// Fields should be get through getter method `field()`.
+#[derive(Debug, Clone, Copy, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct ShotPlan {
+    angle: Angle,
+    /// Bounces before the target is reached.
+    bounces: usize,
+    /// Distance flown until the target is reached.
+    length: f64,
+}
+
+/// Searches firing angles for shots that hit a target.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Position, Wall};
+/// use thuai_8_agent_rust::agent::shooting::ShotPlanner;
+///
+/// // A wall between both tanks, open at the top.
+/// let vertical = Angle::from_degrees(90.0);
+/// let environment = EnvironmentInfo::new(
+///     4,
+///     vec![Wall::new(2, 0, vertical), Wall::new(2, 1, vertical), Wall::new(2, 2, vertical)],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+/// let me = Position::new(1.0, 1.0, 0.0);
+/// let enemy = Position::new(3.0, 1.0, 0.0);
+///
+/// let plans = ShotPlanner::new(1, 0.3).plan(&map, &me, &enemy);
+///
+/// // Only off the top edge of the map.
+/// let best = plans[0];
+/// assert_eq!(best.bounces(), 1);
+/// assert!((best.angle().degrees() - 71.6).abs() < 1.0);
+/// assert!(plans.iter().all(|plan| plan.bounces() == 1));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShotPlanner {
+    max_reflections: usize,
+    radius: f64,
+    step: Angle,
+    max_length: f64,
+}
+
+impl ShotPlanner {
+    /// Constructs a [`ShotPlanner`] for shots bouncing at most
+    /// `max_reflections` times, at tanks taken as circles of `radius`.
+    pub fn new(max_reflections: usize, radius: f64) -> ShotPlanner {
+        ShotPlanner {
+            max_reflections,
+            radius,
+            step: Angle::from_degrees(DEFAULT_ANGLE_STEP_DEGREES),
+            max_length: f64::INFINITY,
+        }
+    }
+
+    /// Try firing angles `step` apart. A step of zero or less is ignored.
+    pub fn with_step(mut self, step: Angle) -> Self {
+        if step.radians() > 0.0 {
+            self.step = step;
+        }
+        self
+    }
+
+    /// Only keep shots reaching the target within `max_length`, e.g. the
+    /// range of a bullet.
+    pub fn with_max_length(mut self, max_length: f64) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Shots from `shooter` hitting `target` without passing through the
+    /// shooter itself, shortest first.
+    ///
+    /// Angles are tried from the facing of `shooter` in steps, so each is a
+    /// whole number of steps away from it. Of neighbouring angles hitting
+    /// with the same number of bounces, only the shortest is kept.
+    pub fn plan(
+        &self,
+        map: &GameMap,
+        shooter: &Position<f64>,
+        target: &Position<f64>,
+    ) -> Vec<ShotPlan> {
+        let steps = (std::f64::consts::TAU / self.step.radians()).round() as usize;
+        let mut plans: Vec<ShotPlan> = Vec::new();
+        let mut previous: Option<ShotPlan> = None;
+        for step in 0..steps {
+            let angle = (*shooter.angle() + self.step * step as f64).normalized();
+            let muzzle = Position::new(*shooter.x(), *shooter.y(), angle);
+            let path = LaserPath::new(map, &muzzle, self.max_reflections);
+            let plan = self
+                .hit(&path, shooter, target)
+                .filter(|(_, length)| *length <= self.max_length)
+                .map(|(bounces, length)| ShotPlan {
+                    angle,
+                    bounces,
+                    length,
+                });
+            match (plan, previous.as_mut()) {
+                (Some(plan), Some(last)) if last.bounces == plan.bounces => {
+                    if plan.length < last.length {
+                        *last = plan;
+                    }
+                }
+                (plan, _) => {
+                    plans.extend(previous.take());
+                    previous = plan;
+                }
+            }
+        }
+        plans.extend(previous);
+        plans.sort_by(|a, b| a.length.total_cmp(&b.length));
+        plans
+    }
+
+    /// Bounces and distance until `path` reaches `target`, if it does
+    /// before passing through `shooter`.
+    fn hit(
+        &self,
+        path: &LaserPath,
+        shooter: &Position<f64>,
+        target: &Position<f64>,
+    ) -> Option<(usize, f64)> {
+        let mut flown = 0.0;
+        for (bounces, (start, end)) in path.segments().enumerate() {
+            let segment = Segment::new(start.clone(), end.clone());
+            let entry = if start.distance_to(target) <= self.radius {
+                Some(start.clone())
+            } else {
+                geometry::circle_segment_intersection(target, self.radius, &segment)
+                    .into_iter()
+                    .next()
+            };
+            let reach = entry.as_ref().unwrap_or(end).clone();
+            // The first segment leaves the shooter, so only later ones can
+            // come back through it.
+            if bounces > 0 {
+                let before = Segment::new(start.clone(), reach);
+                if geometry::point_segment_distance(shooter, &before) < self.radius {
+                    return None;
+                }
+            }
+            if let Some(entry) = entry {
+                return Some((bounces, flown + start.distance_to(&entry)));
+            }
+            flown += segment.length();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::EnvironmentInfo;
+
+    #[test]
+    fn prefers_direct_shots_and_avoids_itself() {
+        let map = GameMap::new(&EnvironmentInfo::new(4, Vec::new(), Vec::new(), Vec::new()));
+        let me = Position::new(1.0, 2.0, 0.3);
+        let enemy = Position::new(3.0, 2.0, 0.0);
+
+        let plans = ShotPlanner::new(1, 0.2).plan(&map, &me, &enemy);
+        let direct = plans[0];
+        assert_eq!(direct.bounces(), 0);
+        assert!(direct.angle().approx_eq(Angle::ZERO, 0.1));
+        assert!((direct.length() - 1.8).abs() < 0.05);
+        // Every angle is a whole number of degrees from the facing.
+        for plan in &plans {
+            let turn = plan.angle().difference(*me.angle()).degrees();
+            assert!((turn - turn.round()).abs() < 1e-6);
+        }
+        assert!(
+            plans
+                .windows(2)
+                .all(|pair| pair[0].length() <= pair[1].length())
+        );
+
+        // Off the left edge, straight back through the shooter.
+        assert!(
+            plans
+                .iter()
+                .all(|plan| !plan.angle().approx_eq(Angle::HALF_TURN, 0.1))
+        );
+
+        let short = ShotPlanner::new(1, 0.2)
+            .with_max_length(1.0)
+            .plan(&map, &me, &enemy);
+        assert!(short.is_empty());
+    }
+}
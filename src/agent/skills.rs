@@ -0,0 +1,148 @@
+//! Skill cool downs, mirrored between updates.
+//!
+//! A [`SkillManager`] keeps the cool down of each own skill as last
+//! reported by the server and counts it down with the ticks passed since,
+//! so a skill still cooling down is refused locally with [`SkillNotReady`]
+//! instead of wasting a message.
+
+use super::model::{Skill, SkillKind};
+use std::{error::Error, fmt::Display};
+
+/// A skill that cannot be used yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkillNotReady {
+    pub skill: SkillKind,
+    /// Ticks until it is ready, [`None`] if the player does not have it.
+    pub ticks: Option<u64>,
+}
+
+impl Display for SkillNotReady {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.ticks {
+            Some(ticks) => write!(f, "{} is ready in {} ticks", self.skill, ticks),
+            None => write!(f, "{} is not owned", self.skill),
+        }
+    }
+}
+
+impl Error for SkillNotReady {}
+
+/// The cool down of one skill at a tick.
+#[derive(Debug, Clone, PartialEq)]
+struct Mirror {
+    skill: SkillKind,
+    max_cool_down: u32,
+    cool_down: u32,
+    since: Option<u64>,
+}
+
+/// Cool downs of the own skills.
+///
+/// Before the first update nothing is known, and every skill is left to the
+/// server to refuse.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::{Skill, SkillKind};
+/// use thuai_8_agent_rust::agent::skills::SkillManager;
+///
+/// let mut skills = SkillManager::default();
+/// skills.update(&[Skill::new(SkillKind::Flash, 20, 5, false)], Some(100));
+///
+/// assert_eq!(skills.ticks_until_ready(SkillKind::Flash, Some(102)), Some(3));
+/// assert!(skills.check(SkillKind::Flash, Some(102)).is_err());
+/// assert!(skills.check(SkillKind::Flash, Some(105)).is_ok());
+/// assert_eq!(skills.ticks_until_ready(SkillKind::Kamui, Some(105)), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SkillManager {
+    skills: Option<Vec<Mirror>>,
+}
+
+impl SkillManager {
+    /// Mirror `skills` as reported at `tick`.
+    pub fn update(&mut self, skills: &[Skill], tick: Option<u64>) {
+        let skills = skills
+            .iter()
+            .map(|skill| Mirror {
+                skill: *skill.name(),
+                max_cool_down: *skill.max_cool_down(),
+                cool_down: *skill.current_cool_down(),
+                since: tick,
+            })
+            .collect();
+        self.skills = Some(skills);
+    }
+
+    /// Record that `skill` was used at `tick`, starting its full cool down
+    /// until the next update tells otherwise.
+    pub fn used(&mut self, skill: SkillKind, tick: Option<u64>) {
+        if let Some(mirror) = self
+            .skills
+            .iter_mut()
+            .flatten()
+            .find(|mirror| mirror.skill == skill)
+        {
+            mirror.cool_down = mirror.max_cool_down;
+            mirror.since = tick;
+        }
+    }
+
+    /// Ticks from `tick` until `skill` is ready, [`None`] if it is not
+    /// owned. Without both ticks known, the cool down last reported.
+    pub fn ticks_until_ready(&self, skill: SkillKind, tick: Option<u64>) -> Option<u64> {
+        let mirror = self
+            .skills
+            .iter()
+            .flatten()
+            .find(|mirror| mirror.skill == skill)?;
+        let passed = mirror
+            .since
+            .zip(tick)
+            .map_or(0, |(since, now)| now.saturating_sub(since));
+        Some((mirror.cool_down as u64).saturating_sub(passed))
+    }
+
+    /// Whether `skill` can be used at `tick`.
+    pub fn check(&self, skill: SkillKind, tick: Option<u64>) -> Result<(), SkillNotReady> {
+        if self.skills.is_none() {
+            return Ok(());
+        }
+        match self.ticks_until_ready(skill, tick) {
+            Some(0) => Ok(()),
+            ticks => Err(SkillNotReady { skill, ticks }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn use_restarts_the_cool_down() {
+        let mut skills = SkillManager::default();
+        assert!(skills.check(SkillKind::Kamui, None).is_ok());
+        skills.update(&[Skill::new(SkillKind::Flash, 20, 0, false)], None);
+        assert!(skills.check(SkillKind::Flash, None).is_ok());
+
+        skills.used(SkillKind::Flash, Some(10));
+        assert_eq!(
+            skills.check(SkillKind::Flash, Some(15)),
+            Err(SkillNotReady {
+                skill: SkillKind::Flash,
+                ticks: Some(15)
+            })
+        );
+        // Unknown ticks fall back to the full cool down.
+        assert_eq!(skills.ticks_until_ready(SkillKind::Flash, None), Some(20));
+        assert!(skills.check(SkillKind::Flash, Some(30)).is_ok());
+
+        skills.update(&[], Some(31));
+        assert_eq!(
+            skills.check(SkillKind::Flash, Some(31)).unwrap_err().ticks,
+            None
+        );
+    }
+}
@@ -0,0 +1,93 @@
+/*! Contains the read-only [`SpectatorAgent`] used to observe a match. */
+use std::error::Error;
+
+use tracing::info;
+
+use super::connection::{AgentClient, ClientConfig, PerformMessage, QueryAPI};
+use super::model::{PlayerToken, RequestType};
+
+/// An agent connected with a spectator (observer) token.
+///
+/// It only implements [`QueryAPI`], so it can request the game state but can
+/// never perform actions. This makes it suitable for a process that watches
+/// a match between two other agents, e.g. to record it.
+///
+/// Should be created with [`SpectatorAgent::new`] or
+/// [`SpectatorAgent::with_config`].
+pub struct SpectatorAgent {
+    client: AgentClient,
+    token: PlayerToken,
+}
+
+impl SpectatorAgent {
+    /// Connect to `server` as a spectator with `token`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if connecting to server always fail, see [`AgentClient::new`].
+    pub async fn new(server: String, token: impl Into<PlayerToken>) -> SpectatorAgent {
+        Self::with_config(server, token, ClientConfig::default()).await
+    }
+
+    /// Connect to `server` as a spectator with `token`, applying `config` to
+    /// the handshake.
+    ///
+    /// # Panics
+    ///
+    /// Panics if connecting to server always fail, see [`AgentClient::with_config`].
+    pub async fn with_config(
+        server: String,
+        token: impl Into<PlayerToken>,
+        config: ClientConfig,
+    ) -> SpectatorAgent {
+        let token = token.into();
+        info!("Spectating on {server}");
+        let client = AgentClient::with_config(server, token.clone(), config).await;
+        SpectatorAgent { client, token }
+    }
+
+    /// The spectator token in use.
+    pub fn token(&self) -> &PlayerToken {
+        &self.token
+    }
+}
+
+impl QueryAPI for SpectatorAgent {
+    async fn send_get_player_info(&mut self) -> Result<(), Box<dyn Error>> {
+        let msg = PerformMessage::GetPlayerInfo {
+            token: self.token.clone(),
+            request: RequestType::Opponent,
+        };
+        let msg2 = PerformMessage::GetPlayerInfo {
+            token: self.token.clone(),
+            request: RequestType::TheSelf,
+        };
+        self.client.send_perform(msg).await?;
+        self.client.send_perform(msg2).await?;
+        Ok(())
+    }
+
+    async fn send_get_environment_info(&mut self) -> Result<(), Box<dyn Error>> {
+        let msg = PerformMessage::GetEnvironmentInfo {
+            token: self.token.clone(),
+        };
+        self.client.send_perform(msg).await?;
+        Ok(())
+    }
+
+    async fn send_get_game_statistics(&mut self) -> Result<(), Box<dyn Error>> {
+        let msg = PerformMessage::GetGameStatistics {
+            token: self.token.clone(),
+        };
+        self.client.send_perform(msg).await?;
+        Ok(())
+    }
+
+    async fn send_get_available_buffs(&mut self) -> Result<(), Box<dyn Error>> {
+        let msg = PerformMessage::GetAvailableBuffs {
+            token: self.token.clone(),
+        };
+        self.client.send_perform(msg).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+//! Symmetries of the square battlefield.
+//!
+//! Maps are usually laid out symmetrically so neither side is favoured.
+//! [`detect`] finds which [`Symmetry`]s leave the walls and fences in place,
+//! and the `apply_*` methods transform the game state, e.g. to play the
+//! same scenario from both sides in self-play, or to reuse analysis of one
+//! half of the map for the other.
+//!
+//! Walls and fences are unit segments on the grid lines: one at `(x, y)`
+//! with angle 0 runs to `(x + 1, y)`, one with angle 90 runs to `(x, y + 1)`.
+
+use super::model::{Angle, Bullet, EnvironmentInfo, Fence, Player, Players, Position, Wall};
+use std::collections::HashSet;
+
+/// A non-trivial symmetry of a square map of side `map_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    /// Quarter turn counter-clockwise about the centre.
+    Rotate90,
+    /// Half turn about the centre.
+    Rotate180,
+    /// Quarter turn clockwise about the centre.
+    Rotate270,
+    /// Reflection across the vertical centre line (`x -> size - x`).
+    MirrorX,
+    /// Reflection across the horizontal centre line (`y -> size - y`).
+    MirrorY,
+    /// Reflection across the main diagonal (`x <-> y`).
+    Transpose,
+    /// Reflection across the other diagonal.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    /// Every [`Symmetry`].
+    pub const ALL: [Symmetry; 7] = [
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::MirrorX,
+        Symmetry::MirrorY,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    /// The symmetry undoing this one.
+    pub fn inverse(self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => other,
+        }
+    }
+
+    /// Transform the point `(x, y)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use thuai_8_agent_rust::agent::symmetry::Symmetry;
+    ///
+    /// assert_eq!(Symmetry::MirrorX.apply_point(10.0, 2.0, 3.0), (8.0, 3.0));
+    /// assert_eq!(Symmetry::Rotate180.apply_point(10.0, 2.0, 3.0), (8.0, 7.0));
+    /// ```
+    pub fn apply_point(self, map_size: f64, x: f64, y: f64) -> (f64, f64) {
+        match self {
+            Symmetry::Rotate90 => (map_size - y, x),
+            Symmetry::Rotate180 => (map_size - x, map_size - y),
+            Symmetry::Rotate270 => (y, map_size - x),
+            Symmetry::MirrorX => (map_size - x, y),
+            Symmetry::MirrorY => (x, map_size - y),
+            Symmetry::Transpose => (y, x),
+            Symmetry::AntiTranspose => (map_size - y, map_size - x),
+        }
+    }
+
+    /// Transform a heading.
+    pub fn apply_angle(self, angle: Angle) -> Angle {
+        match self {
+            Symmetry::Rotate90 => angle + Angle::QUARTER_TURN,
+            Symmetry::Rotate180 => angle + Angle::HALF_TURN,
+            Symmetry::Rotate270 => angle - Angle::QUARTER_TURN,
+            Symmetry::MirrorX => Angle::HALF_TURN - angle,
+            Symmetry::MirrorY => -angle,
+            Symmetry::Transpose => Angle::QUARTER_TURN - angle,
+            Symmetry::AntiTranspose => -Angle::QUARTER_TURN - angle,
+        }
+    }
+
+    /// Transform a position and its heading.
+    pub fn apply_position(self, map_size: u32, position: &Position<f64>) -> Position<f64> {
+        let (x, y) = self.apply_point(map_size as f64, *position.x(), *position.y());
+        Position::new(x, y, self.apply_angle(*position.angle()))
+    }
+
+    /// Transform a grid segment starting at `(x, y)`, returning its new
+    /// start and whether it is vertical.
+    fn apply_segment(self, map_size: u32, x: i32, y: i32, vertical: bool) -> (i32, i32, bool) {
+        let size = map_size as f64;
+        let (end_x, end_y) = if vertical { (x, y + 1) } else { (x + 1, y) };
+        let (ax, ay) = self.apply_point(size, x as f64, y as f64);
+        let (bx, by) = self.apply_point(size, end_x as f64, end_y as f64);
+        let vertical = (ax - bx).abs() < 0.5;
+        (
+            ax.min(bx).round() as i32,
+            ay.min(by).round() as i32,
+            vertical,
+        )
+    }
+
+    /// Transform a wall.
+    pub fn apply_wall(self, map_size: u32, wall: &Wall) -> Wall {
+        let (x, y, vertical) =
+            self.apply_segment(map_size, *wall.x(), *wall.y(), is_vertical(*wall.angle()));
+        Wall::new(x, y, along(vertical))
+    }
+
+    /// Transform a fence.
+    pub fn apply_fence(self, map_size: u32, fence: &Fence) -> Fence {
+        let position = fence.position();
+        let (x, y, vertical) = self.apply_segment(
+            map_size,
+            *position.x(),
+            *position.y(),
+            is_vertical(*position.angle()),
+        );
+        Fence::new(Position::new(x, y, along(vertical)), *fence.health())
+    }
+
+    /// Transform a bullet.
+    pub fn apply_bullet(self, map_size: u32, bullet: &Bullet) -> Bullet {
+        Bullet::new(
+            *bullet.id(),
+            *bullet.is_missile(),
+            *bullet.is_anti_armor(),
+            self.apply_position(map_size, bullet.position()),
+            *bullet.speed(),
+            *bullet.damage(),
+            *bullet.traveled_distance(),
+        )
+    }
+
+    /// Transform everything on the map.
+    pub fn apply_environment(self, environment: &EnvironmentInfo) -> EnvironmentInfo {
+        let size = *environment.map_size();
+        EnvironmentInfo::new(
+            size,
+            environment
+                .walls()
+                .iter()
+                .map(|wall| self.apply_wall(size, wall))
+                .collect(),
+            environment
+                .fences()
+                .iter()
+                .map(|fence| self.apply_fence(size, fence))
+                .collect(),
+            environment
+                .bullets()
+                .iter()
+                .map(|bullet| self.apply_bullet(size, bullet))
+                .collect(),
+        )
+    }
+
+    /// Transform a player.
+    pub fn apply_player(self, map_size: u32, player: &Player) -> Player {
+        Player::new(
+            player.token().clone(),
+            self.apply_position(map_size, player.position()),
+            player.weapon().clone(),
+            player.armor().clone(),
+            player.skills().clone(),
+        )
+    }
+
+    /// Transform every player.
+    pub fn apply_players(self, map_size: u32, players: &Players) -> Players {
+        players
+            .iter()
+            .map(|player| self.apply_player(map_size, player))
+            .collect()
+    }
+}
+
+pub(super) fn is_vertical(angle: Angle) -> bool {
+    (angle.degrees().rem_euclid(180.0) - 90.0).abs() < 45.0
+}
+
+/// Angle of a vertical or horizontal wall or fence.
+fn along(vertical: bool) -> Angle {
+    Angle::from_degrees(if vertical { 90.0 } else { 0.0 })
+}
+
+/// The symmetries that leave every wall and fence of `environment` in
+/// place. Empty for an asymmetric map.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Wall};
+/// use thuai_8_agent_rust::agent::symmetry::{self, Symmetry};
+///
+/// let map = EnvironmentInfo::new(
+///     4,
+///     vec![Wall::new(0, 1, Angle::from_degrees(0.0)), Wall::new(3, 3, Angle::from_degrees(0.0))],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+///
+/// assert_eq!(symmetry::detect(&map), vec![Symmetry::Rotate180]);
+/// ```
+pub fn detect(environment: &EnvironmentInfo) -> Vec<Symmetry> {
+    let size = *environment.map_size();
+    let segments = |symmetry: Option<Symmetry>| {
+        let walls = environment
+            .walls()
+            .iter()
+            .map(|wall| (*wall.x(), *wall.y(), is_vertical(*wall.angle()), true));
+        let fences = environment.fences().iter().map(|fence| {
+            let position = fence.position();
+            (
+                *position.x(),
+                *position.y(),
+                is_vertical(*position.angle()),
+                false,
+            )
+        });
+        walls
+            .chain(fences)
+            .map(|(x, y, vertical, wall)| match symmetry {
+                Some(symmetry) => {
+                    let (x, y, vertical) = symmetry.apply_segment(size, x, y, vertical);
+                    (x, y, vertical, wall)
+                }
+                None => (x, y, vertical, wall),
+            })
+            .collect::<HashSet<_>>()
+    };
+    let original = segments(None);
+    Symmetry::ALL
+        .into_iter()
+        .filter(|symmetry| segments(Some(*symmetry)) == original)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverse_restores_state() {
+        let position = Position::new(1.5, 2.5, 0.3);
+        let wall = Wall::new(1, 2, Angle::from_degrees(90.0));
+        for symmetry in Symmetry::ALL {
+            let back = symmetry
+                .inverse()
+                .apply_position(10, &symmetry.apply_position(10, &position));
+            assert_eq!(back, position);
+            assert!(back.angle().approx_eq(*position.angle(), 1e-9));
+
+            let back = symmetry
+                .inverse()
+                .apply_wall(10, &symmetry.apply_wall(10, &wall));
+            assert_eq!((back.x(), back.y()), (&1, &2));
+            assert!(is_vertical(*back.angle()));
+        }
+    }
+
+    #[test]
+    fn empty_map_is_fully_symmetric() {
+        let map = EnvironmentInfo::new(10, Vec::new(), Vec::new(), Vec::new());
+
+        assert_eq!(detect(&map), Symmetry::ALL.to_vec());
+    }
+}
@@ -0,0 +1,185 @@
+//! What can be seen from a position.
+//!
+//! While BLACK_OUT is active, information about the map is limited, so the
+//! bot has to reason about which parts of the map it can see, and, as sight
+//! goes both ways, be seen from. A [`VisibilityRegion`] is the polygon of
+//! points in line of sight of a position, bounded by walls, fences and the
+//! edge of the map.
+
+use super::map::GameMap;
+use super::model::Position;
+use getset::Getters;
+use std::f64::consts::TAU;
+
+/// Angle rays are cast at to either side of a barrier end, to see past it.
+const GRAZE: f64 = 1e-6;
+
+/// Points closer than this are merged.
+const EPSILON: f64 = 1e-9;
+
+/// The area in line of sight of a position.
+///
+/// Fields should be get through getter method `field()`.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::map::GameMap;
+/// use thuai_8_agent_rust::agent::model::{Angle, EnvironmentInfo, Position, Wall};
+/// use thuai_8_agent_rust::agent::visibility::VisibilityRegion;
+///
+/// let vertical = Angle::from_degrees(90.0);
+/// let environment = EnvironmentInfo::new(
+///     4,
+///     vec![Wall::new(2, 0, vertical), Wall::new(2, 1, vertical)],
+///     Vec::new(),
+///     Vec::new(),
+/// );
+/// let map = GameMap::new(&environment);
+///
+/// let region = VisibilityRegion::new(&map, &Position::new(1.0, 1.0, 0.0));
+///
+/// assert!(region.contains(&Position::new(2.5, 3.5, 0.0)));
+/// assert!(!region.contains(&Position::new(3.5, 0.5, 0.0)));
+/// // All but the shadow of the wall, below the diagonal from (2, 2).
+/// assert!((region.area() - 10.0).abs() < 1e-3);
+/// assert!(region.cells(&map).contains(&Position::new(0, 3, 0.0)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct VisibilityRegion {
+    origin: Position<f64>,
+    /// Corners of the region, counterclockwise around `origin`.
+    polygon: Vec<Position<f64>>,
+}
+
+impl VisibilityRegion {
+    /// The region of `map` in line of sight of `origin`.
+    pub fn new(map: &GameMap, origin: &Position<f64>) -> VisibilityRegion {
+        let size = map.size() as f64;
+        let corners = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)]
+            .map(|(x, y)| Position::new(x, y, 0.0));
+        let ends = map
+            .segments()
+            .flat_map(|(segment, _)| [segment.start().clone(), segment.end().clone()])
+            .chain(corners);
+        let mut angles: Vec<f64> = ends
+            .filter(|end| end.distance_to(origin) > EPSILON)
+            .flat_map(|end| {
+                let angle = origin.angle_to(&end).radians();
+                [angle - GRAZE, angle, angle + GRAZE]
+            })
+            .map(|angle| angle.rem_euclid(TAU))
+            .collect();
+        angles.sort_by(f64::total_cmp);
+        let mut polygon: Vec<Position<f64>> = Vec::with_capacity(angles.len());
+        for angle in angles {
+            let point = cast(map, origin, angle);
+            if polygon
+                .last()
+                .is_none_or(|last| last.distance_to(&point) > EPSILON)
+            {
+                polygon.push(point);
+            }
+        }
+        VisibilityRegion {
+            origin: origin.clone(),
+            polygon,
+        }
+    }
+
+    /// Area of the region, in square cells.
+    pub fn area(&self) -> f64 {
+        let twice: f64 = self
+            .edges()
+            .map(|(a, b)| a.x() * b.y() - b.x() * a.y())
+            .sum();
+        twice.abs() / 2.0
+    }
+
+    /// Whether `point` lies in the region.
+    pub fn contains(&self, point: &Position<f64>) -> bool {
+        let (x, y) = (*point.x(), *point.y());
+        self.edges()
+            .filter(|(a, b)| (*a.y() > y) != (*b.y() > y))
+            .filter(|(a, b)| x < a.x() + (y - a.y()) * (b.x() - a.x()) / (b.y() - a.y()))
+            .count()
+            % 2
+            == 1
+    }
+
+    /// Cells of `map` whose centre lies in the region.
+    pub fn cells(&self, map: &GameMap) -> Vec<Position<i32>> {
+        let size = map.size() as i32;
+        (0..size)
+            .flat_map(|y| (0..size).map(move |x| Position::new(x, y, 0.0)))
+            .filter(|cell: &Position<i32>| self.contains(&cell.center()))
+            .collect()
+    }
+
+    /// Consecutive corners, the last one wrapping around to the first.
+    fn edges(&self) -> impl Iterator<Item = (&Position<f64>, &Position<f64>)> {
+        self.polygon.iter().zip(self.polygon.iter().cycle().skip(1))
+    }
+}
+
+/// Where a ray from `origin` along `angle` meets a barrier or the edge of
+/// `map`.
+fn cast(map: &GameMap, origin: &Position<f64>, angle: f64) -> Position<f64> {
+    let size = map.size() as f64;
+    let (sin, cos) = angle.sin_cos();
+    let until = |start: f64, delta: f64| {
+        if delta > EPSILON {
+            (size - start) / delta
+        } else if delta < -EPSILON {
+            -start / delta
+        } else {
+            f64::INFINITY
+        }
+    };
+    let reach = until(*origin.x(), cos)
+        .min(until(*origin.y(), sin))
+        .max(0.0);
+    let edge = Position::new(origin.x() + reach * cos, origin.y() + reach * sin, 0.0);
+    match map.first_barrier(origin, &edge) {
+        Some((_, point)) => Position::new(*point.x(), *point.y(), 0.0),
+        None => edge,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model::{Angle, EnvironmentInfo, Fence, Wall};
+
+    #[test]
+    fn fences_block_sight_until_broken() {
+        let environment = EnvironmentInfo::new(
+            4,
+            vec![Wall::new(0, 2, Angle::ZERO)],
+            vec![Fence::new(Position::new(1, 2, Angle::ZERO), 1)],
+            Vec::new(),
+        );
+        let mut map = GameMap::new(&environment);
+        let origin = Position::new(0.5, 0.5, 0.0);
+
+        let open = VisibilityRegion::new(
+            &GameMap::new(&EnvironmentInfo::new(4, Vec::new(), Vec::new(), Vec::new())),
+            &origin,
+        );
+        assert!((open.area() - 16.0).abs() < 1e-3);
+        assert_eq!(open.cells(&map).len(), 16);
+
+        let region = VisibilityRegion::new(&map, &origin);
+        assert!(region.contains(&Position::new(0.5, 1.9, 0.0)));
+        assert!(!region.contains(&Position::new(0.5, 2.1, 0.0)));
+        assert!(!region.contains(&Position::new(1.8, 3.5, 0.0)));
+        assert!(region.contains(&Position::new(3.5, 2.5, 0.0)));
+        assert!(region.area() < 16.0);
+
+        map.remove_barrier(1, 2, false);
+        let broken = VisibilityRegion::new(&map, &origin);
+        assert!(broken.contains(&Position::new(1.8, 3.5, 0.0)));
+        assert!(broken.area() > region.area());
+    }
+}
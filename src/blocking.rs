@@ -0,0 +1,201 @@
+/*! A synchronous [`Agent`], for strategies written without async Rust.
+
+A [`BlockingAgent`] owns the [`Agent`] and a runtime of its own, which keeps
+the connection alive between calls. Every method blocks until the
+underlying async one is done:
+
+```no_run
+use std::time::Duration;
+use thuai_8_agent_rust::blocking::BlockingAgent;
+
+let mut agent = BlockingAgent::new("ws://127.0.0.1:14514".to_string(), "1919810").unwrap();
+loop {
+    agent.sync();
+    if agent.self_player().is_some_and(|me| *me.weapon().current_bullets() > 0) {
+        agent.attack().ok();
+    } else {
+        agent.move_forward(1.0).ok();
+    }
+    agent.request_state().ok();
+    std::thread::sleep(Duration::from_millis(100));
+}
+```
+
+Like any blocking client, it must not be used from within an async
+runtime, where blocking panics.
+*/
+
+use std::error::Error;
+use std::io;
+
+use tokio::runtime::{Builder, Runtime};
+
+use crate::agent::connection::{ClientConfig, ConnectionStats};
+use crate::agent::model::{
+    AvailableBuffs, BuffKind, EnvironmentInfo, GameStatistics, Player, PlayerToken, Players,
+    SkillKind, Stage,
+};
+use crate::agent::player_api::PlayerOperate;
+use crate::agent::{Agent, AgentError};
+
+/// An [`Agent`] with blocking methods.
+///
+/// The game state is cached as by [`Agent`]: call [`BlockingAgent::sync`]
+/// to apply the messages received meanwhile.
+pub struct BlockingAgent {
+    runtime: Runtime,
+    agent: Agent,
+}
+
+impl BlockingAgent {
+    /// Connect to `server` as the player with `token`, see [`Agent::new`].
+    ///
+    /// Fails if the runtime cannot be started, or if connecting to the
+    /// server fails, see [`Agent::try_with_config`].
+    pub fn new(server: String, token: impl Into<PlayerToken>) -> io::Result<BlockingAgent> {
+        BlockingAgent::with_config(server, token, ClientConfig::default())
+    }
+
+    /// Connect like [`BlockingAgent::new`] with the options of `config`.
+    pub fn with_config(
+        server: String,
+        token: impl Into<PlayerToken>,
+        config: ClientConfig,
+    ) -> io::Result<BlockingAgent> {
+        // One worker keeps receiving while the caller does not block.
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let agent = runtime
+            .block_on(Agent::try_with_config(server, token, config))
+            .map_err(io::Error::other)?;
+        Ok(BlockingAgent { runtime, agent })
+    }
+
+    /// Run `action` on the wrapped [`Agent`] and block until it is done, for
+    /// what has no blocking method.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let mut agent = thuai_8_agent_rust::blocking::BlockingAgent::new(
+    /// #     "ws://127.0.0.1:14514".to_string(), "1919810").unwrap();
+    /// use thuai_8_agent_rust::agent::model::Angle;
+    ///
+    /// let turned = agent.run(async |agent| agent.turn_to(Angle::HALF_TURN).await.ok());
+    /// ```
+    pub fn run<R>(&mut self, action: impl AsyncFnOnce(&mut Agent) -> R) -> R {
+        self.runtime.block_on(action(&mut self.agent))
+    }
+
+    /// The wrapped [`Agent`].
+    pub fn agent(&self) -> &Agent {
+        &self.agent
+    }
+
+    /// Apply every message received so far, see [`Agent::sync`].
+    pub fn sync(&mut self) -> usize {
+        // Callbacks of the applied messages are spawned on the runtime.
+        let _runtime = self.runtime.enter();
+        self.agent.sync()
+    }
+
+    /// Request the whole game state, see [`Agent::request_state`].
+    pub fn request_state(&mut self) -> Result<(), Box<dyn Error>> {
+        self.run(async |agent| agent.request_state().await)
+    }
+
+    /// The stage of the game, if known yet.
+    pub fn stage(&self) -> Option<Stage> {
+        self.agent
+            .game_statistics()
+            .map(|statistics| *statistics.current_stage())
+    }
+
+    pub fn token(&self) -> &PlayerToken {
+        self.agent.token()
+    }
+
+    pub fn players_info(&self) -> Option<&Players> {
+        self.agent.players_info()
+    }
+
+    pub fn game_statistics(&self) -> Option<&GameStatistics> {
+        self.agent.game_statistics()
+    }
+
+    pub fn environment_info(&self) -> Option<&EnvironmentInfo> {
+        self.agent.environment_info()
+    }
+
+    pub fn available_buffs(&self) -> Option<&AvailableBuffs> {
+        self.agent.available_buffs()
+    }
+
+    /// See [`PlayerOperate::self_player`].
+    pub fn self_player(&self) -> Option<&Player> {
+        self.agent.self_player()
+    }
+
+    /// See [`PlayerOperate::opponent`].
+    pub fn opponent(&self) -> Option<&Player> {
+        self.agent.opponent()
+    }
+
+    /// See [`PlayerOperate::opponents`].
+    pub fn opponents(&self) -> Vec<&Player> {
+        self.agent.opponents()
+    }
+
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.agent.connection_stats()
+    }
+
+    pub fn move_forward(&mut self, distance: f64) -> Result<(), AgentError> {
+        self.run(async |agent| agent.move_forward(distance).await)
+    }
+
+    pub fn move_backward(&mut self, distance: f64) -> Result<(), AgentError> {
+        self.run(async |agent| agent.move_backward(distance).await)
+    }
+
+    pub fn turn_clockwise(&mut self, angle: u32) -> Result<(), AgentError> {
+        self.run(async |agent| agent.turn_clockwise(angle).await)
+    }
+
+    pub fn turn_counter_clockwise(&mut self, angle: u32) -> Result<(), AgentError> {
+        self.run(async |agent| agent.turn_counter_clockwise(angle).await)
+    }
+
+    pub fn attack(&mut self) -> Result<(), AgentError> {
+        self.run(async |agent| agent.attack().await)
+    }
+
+    pub fn use_skill(&mut self, skill: SkillKind) -> Result<(), AgentError> {
+        self.run(async |agent| agent.use_skill(skill).await)
+    }
+
+    pub fn select_buff(&mut self, buff: BuffKind) -> Result<(), AgentError> {
+        self.run(async |agent| PlayerOperate::select_buff(agent, buff).await)
+    }
+
+    /// Close the connection, see [`Agent::shutdown`].
+    pub fn shutdown(&self) {
+        self.agent.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::connection::FixedDelay;
+    use std::time::Duration;
+
+    #[test]
+    fn failed_connect_is_an_error() {
+        let config = ClientConfig::default().with_retry_policy(FixedDelay::new(1, Duration::ZERO));
+        let result = BlockingAgent::with_config("ws://127.0.0.1:1".to_string(), "1919810", config);
+        assert!(result.is_err());
+    }
+}
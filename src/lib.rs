@@ -2,17 +2,205 @@
 extern crate strum;
 
 pub mod agent;
+pub mod blocking;
 pub mod logic;
+pub mod math;
+pub mod testing;
 
 use std::time::Duration;
 
-use agent::connection::AgentClient;
-use tokio::time::sleep;
+use agent::Agent;
+use agent::connection::{CancellationToken, ClientConfig};
+use agent::control::AgentControl;
+use agent::model::Stage;
+use agent::player_api::PlayerOperate;
+use agent::poller::PollSchedule;
+use futures::future::join_all;
+use getset::Getters;
+use logic::Logic;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{error, info, warn};
 
-// use agent;
+/// Default time between two polls of the game state.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
+/// Options of an agent run by [`run_agent_with_config`].
+///
+/// Should be created with [`RunConfig::new`] (or [`Default`]) and then
+/// extended with the `with_*` methods.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use thuai_8_agent_rust::RunConfig;
+///
+/// let config = RunConfig::new().with_poll_interval(Duration::from_millis(50));
+///
+/// assert_eq!(config.poll_interval(), &Duration::from_millis(50));
+/// ```
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct RunConfig {
+    poll_interval: Duration,
+    client: ClientConfig,
+    control: AgentControl,
+    poll_schedule: PollSchedule,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            client: ClientConfig::default(),
+            control: AgentControl::new(),
+            poll_schedule: PollSchedule::default(),
+        }
+    }
+}
+
+impl RunConfig {
+    /// Constructs a [`RunConfig`] with the defaults.
+    pub fn new() -> RunConfig {
+        RunConfig::default()
+    }
+
+    /// Poll the game state and run the [`Logic`] every `poll_interval`.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Connect with `client`. Cancelling its cancellation token stops the
+    /// agent, see [`ClientConfig::with_cancellation`].
+    pub fn with_client_config(mut self, client: ClientConfig) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Request each part of the game state as often as `schedule` sets,
+    /// see [`Agent::poll`].
+    pub fn with_poll_schedule(mut self, poll_schedule: PollSchedule) -> Self {
+        self.poll_schedule = poll_schedule;
+        self
+    }
+
+    /// Pause, resume and stop the agent through `control`, see
+    /// [`Agent::set_control`].
+    pub fn with_control(mut self, control: AgentControl) -> Self {
+        self.control = control;
+        self
+    }
+}
+
+/// Connect to `server` as the player with `token` and run the [`Logic`] of
+/// [`Agent`] until the game ends, see [`run_agent_with_config`].
 pub async fn run_agent(server: String, token: String) {
-    let agent = AgentClient::new(server, token).await;
-    sleep(Duration::from_secs(10)).await;
-    // TODO: finish the function
+    run_agent_until(server, token, CancellationToken::new()).await;
+}
+
+/// Run an agent like [`run_agent`] until `cancellation` is cancelled, which
+/// stops the agent and every task it spawned.
+pub async fn run_agent_until(server: String, token: String, cancellation: CancellationToken) {
+    let client = ClientConfig::new().with_cancellation(cancellation);
+    run_agent_with_config(server, token, RunConfig::new().with_client_config(client)).await;
+}
+
+/// Run an agent until the game ends, the connection closes, it is
+/// cancelled or stopped.
+///
+/// Every message received updates the cached game state of the agent.
+/// Every poll interval the [`Logic`] of [`Agent`] runs for the current
+/// stage, [`Logic::select_buff`] once per rest stage through
+/// [`Agent::handle_buff_selection`] and [`Logic::game_loop`] in battle, and
+/// then the parts of the game state due are requested, see
+/// [`RunConfig::with_poll_schedule`]. Nothing runs before the server told
+/// the stage, nor while paused, see [`RunConfig::with_control`].
+///
+/// If connecting fails, the error is logged and nothing runs.
+pub async fn run_agent_with_config(server: String, token: String, config: RunConfig) {
+    let cancellation = config.client.cancellation().clone();
+    let mut agent = match Agent::try_with_config(server, token, config.client).await {
+        Ok(agent) => agent,
+        Err(err) => {
+            error!("Agent not started: {}", err);
+            return;
+        }
+    };
+    agent.set_control(config.control.clone());
+    agent.set_poll_schedule(config.poll_schedule);
+    let mut poll = interval(config.poll_interval);
+    poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let current_stage = |agent: &Agent| {
+        agent
+            .game_statistics()
+            .map(|statistics| *statistics.current_stage())
+    };
+    let mut stage = None;
+    loop {
+        tokio::select! {
+            _ = cancellation.cancelled() => {
+                info!("Agent cancelled");
+                break;
+            }
+            _ = config.control.stopped() => {
+                info!("Agent stopped");
+                break;
+            }
+            message = agent.next_message() => {
+                if message.is_none() {
+                    warn!("Connection to the server closed");
+                    break;
+                }
+            }
+            _ = poll.tick() => {
+                agent.sync();
+                if config.control.is_paused() {
+                    continue;
+                }
+                match current_stage(&agent) {
+                    Some(Stage::Rest) => {
+                        if let Err(err) = agent.handle_buff_selection().await {
+                            warn!("Selecting a buff failed: {}", err);
+                        }
+                    }
+                    Some(Stage::Battle) => <Agent as Logic>::game_loop(&mut agent).await,
+                    _ => {}
+                }
+                if let Err(err) = agent.poll().await {
+                    warn!("Polling the game state failed: {}", err);
+                }
+            }
+        }
+        let current = current_stage(&agent);
+        if current != stage {
+            info!("Stage changed to {:?}", current);
+            stage = current;
+        }
+        if stage == Some(Stage::End) {
+            break;
+        }
+    }
+    agent.shutdown();
+}
+
+/// Run several agents concurrently in the same process, e.g. for self-play.
+///
+/// Each `(server, token)` pair gets its own connection and runs on its own
+/// task. Returns after every agent has finished.
+pub async fn run_agents(agents: Vec<(String, String)>) {
+    run_agents_until(agents, CancellationToken::new()).await;
+}
+
+/// Run several agents like [`run_agents`], stopping all of them once
+/// `cancellation` is cancelled.
+pub async fn run_agents_until(agents: Vec<(String, String)>, cancellation: CancellationToken) {
+    let handles = agents.into_iter().map(|(server, token)| {
+        tokio::spawn(run_agent_until(server, token, cancellation.child_token()))
+    });
+    for result in join_all(handles).await {
+        if let Err(err) = result {
+            error!("Agent task failed: {}", err);
+        }
+    }
 }
@@ -1,21 +1,31 @@
+use crate::agent::model::{AvailableBuffs, BuffKind};
 use crate::agent::{Agent, player_api::PlayerOperate};
 pub use crate::agent::{connection, model, player_api};
 
+/// The strategy of an agent, run by [`run_agent`](crate::run_agent).
 pub trait Logic: PlayerOperate {
-    fn game_loop(agent: &mut Self);
+    /// Called at the poll rate during the `BATTLE` stage.
+    fn game_loop(agent: &mut Self) -> impl std::future::Future<Output = ()> + Send;
 
-    fn select_buff(agent: &mut Self);
+    /// Called during the `REST` stage to pick one of `buffs`, until a pick
+    /// was selected, see [`Agent::handle_buff_selection`]. [`None`] picks
+    /// nothing for now.
+    fn select_buff(
+        agent: &mut Self,
+        buffs: &AvailableBuffs,
+    ) -> impl std::future::Future<Output = Option<BuffKind>> + Send;
 }
 
 impl Logic for Agent {
-    fn game_loop(agent: &mut Self) {
+    async fn game_loop(_agent: &mut Self) {
         // Your code here...
         // You can use the methods offered by [`PlayerOperate`] trait.
-        // agent.move_forward();
+        // agent.move_forward(1.0).await.ok();
     }
 
-    fn select_buff(agent: &mut Self) {
+    async fn select_buff(_agent: &mut Self, buffs: &AvailableBuffs) -> Option<BuffKind> {
         // Your code here...
         // You can use the methods offered by [`PlayerOperate`] trait.
+        buffs.first().copied()
     }
 }
@@ -1,7 +1,11 @@
 use clap::Parser;
 use std::env;
-use thuai_8_agent_rust::run_agent;
-use tracing::{Level, error};
+use std::io::IsTerminal;
+use thuai_8_agent_rust::agent::connection::CancellationToken;
+use thuai_8_agent_rust::{run_agent_until, run_agents_until};
+use time::UtcOffset;
+use time::format_description::well_known::Rfc3339;
+use tracing::{Level, info, warn};
 use tracing_subscriber::fmt::time::OffsetTime;
 
 #[derive(Parser)]
@@ -9,12 +13,29 @@ use tracing_subscriber::fmt::time::OffsetTime;
 struct Cli {
     #[arg(long)]
     server: Option<String>,
+    /// Can be given multiple times to run several agents (e.g. self-play).
     #[arg(long)]
-    token: Option<String>,
+    token: Vec<String>,
     #[arg(long)]
     logging_level: Option<String>,
 }
 
+/// Whether to color the logs: only on a terminal that supports it, and not
+/// if `NO_COLOR` is set.
+fn use_ansi() -> bool {
+    if env::var_os("NO_COLOR").is_some() || !std::io::stdout().is_terminal() {
+        return false;
+    }
+    #[cfg(windows)]
+    {
+        nu_ansi_term::enable_ansi_support().is_ok()
+    }
+    #[cfg(not(windows))]
+    {
+        true
+    }
+}
+
 const SERVER_DEFAULT: &str = "ws://127.0.0.1:14514";
 const TOKEN_DEFAULT: &str = "1919810";
 
@@ -24,11 +45,33 @@ async fn run(cli: Cli) {
     let server = cli
         .server
         .unwrap_or(env::var("SERVER").unwrap_or(SERVER_DEFAULT.to_string()));
-    let token = cli
-        .token
-        .unwrap_or(env::var("TOKEN").unwrap_or(TOKEN_DEFAULT.to_string()));
+    let mut tokens = cli.token;
+    if tokens.is_empty() {
+        tokens.push(env::var("TOKEN").unwrap_or(TOKEN_DEFAULT.to_string()));
+    }
+
+    // Shut every agent down cleanly on Ctrl-C.
+    let cancellation = CancellationToken::new();
+    let on_interrupt = cancellation.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("Interrupted, shutting down");
+            on_interrupt.cancel();
+        }
+    });
 
-    run_agent(server, token).await;
+    if tokens.len() == 1 {
+        run_agent_until(server, tokens.remove(0), cancellation).await;
+    } else {
+        run_agents_until(
+            tokens
+                .into_iter()
+                .map(|token| (server.clone(), token))
+                .collect(),
+            cancellation,
+        )
+        .await;
+    }
 }
 
 fn main() {
@@ -41,13 +84,18 @@ fn main() {
         .parse()
         .unwrap();
 
+    // The local offset cannot always be determined, e.g. without a time zone
+    // database or once threads are running; fall back to UTC then.
+    let local_timer = OffsetTime::local_rfc_3339();
+    let local_offset = local_timer.is_ok();
     tracing_subscriber::fmt()
         .with_max_level(logging_level)
-        .with_timer(OffsetTime::local_rfc_3339().unwrap_or_else(|_| {
-            error!("Could not get local offset!");
-            panic!("Could not get local offset!");
-        }))
+        .with_ansi(use_ansi())
+        .with_timer(local_timer.unwrap_or_else(|_| OffsetTime::new(UtcOffset::UTC, Rfc3339)))
         .init();
+    if !local_offset {
+        warn!("Could not get local offset, logging in UTC");
+    }
 
     run(cli);
 }
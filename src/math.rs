@@ -0,0 +1,3 @@
+//! Plain math, on the positions of the game model but free of game rules.
+
+pub mod geometry;
@@ -0,0 +1,234 @@
+//! Segments, and where they meet points, other segments and circles.
+//!
+//! Positions are used as plain points here; their angles are ignored and
+//! results carry an angle of 0.
+
+use crate::agent::model::Position;
+use getset::Getters;
+
+/// Tolerance for points lying on a segment or circle.
+const EPSILON: f64 = 1e-9;
+
+/// A straight segment between two points.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::Position;
+/// use thuai_8_agent_rust::math::geometry::Segment;
+///
+/// let segment = Segment::new(Position::new(1.0, 1.0, 0.0), Position::new(4.0, 5.0, 0.0));
+///
+/// assert_eq!(segment.length(), 5.0);
+/// assert_eq!(segment.point_at(0.5), Position::new(2.5, 3.0, 0.0));
+/// assert_eq!(segment.closest_point(&Position::new(0.0, 0.0, 0.0)), *segment.start());
+/// ```
+#[derive(Debug, Clone, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct Segment {
+    start: Position<f64>,
+    end: Position<f64>,
+}
+
+impl Segment {
+    /// Constructs the [`Segment`] from `start` to `end`.
+    pub fn new(start: Position<f64>, end: Position<f64>) -> Segment {
+        Segment { start, end }
+    }
+
+    /// `end - start`, as `(dx, dy)`.
+    pub fn delta(&self) -> (f64, f64) {
+        (self.end.x() - self.start.x(), self.end.y() - self.start.y())
+    }
+
+    pub fn length(&self) -> f64 {
+        self.start.distance_to(&self.end)
+    }
+
+    /// The point a fraction `t` of the way from `start` to `end`.
+    pub fn point_at(&self, t: f64) -> Position<f64> {
+        let (dx, dy) = self.delta();
+        let point = self.start.offset(dx * t, dy * t);
+        Position::new(*point.x(), *point.y(), 0.0)
+    }
+
+    /// Fraction of the way along the segment of the point closest to
+    /// `point`, from 0 to 1.
+    fn closest_fraction(&self, point: &Position<f64>) -> f64 {
+        let (dx, dy) = self.delta();
+        let length = dx * dx + dy * dy;
+        if length > 0.0 {
+            (((point.x() - self.start.x()) * dx + (point.y() - self.start.y()) * dy) / length)
+                .clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// The point of the segment closest to `point`.
+    pub fn closest_point(&self, point: &Position<f64>) -> Position<f64> {
+        self.point_at(self.closest_fraction(point))
+    }
+}
+
+/// Distance from `point` to the closest point of `segment`.
+pub fn point_segment_distance(point: &Position<f64>, segment: &Segment) -> f64 {
+    point.distance_to(&segment.closest_point(point))
+}
+
+/// Where `a` and `b` cross, ends included. [`None`] if they do not meet or
+/// are parallel, even when overlapping.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::Position;
+/// use thuai_8_agent_rust::math::geometry::{self, Segment};
+///
+/// let at = |x, y| Position::new(x, y, 0.0);
+/// let a = Segment::new(at(0.0, 0.0), at(2.0, 2.0));
+///
+/// assert_eq!(
+///     geometry::segment_intersection(&a, &Segment::new(at(0.0, 2.0), at(2.0, 0.0))),
+///     Some(at(1.0, 1.0))
+/// );
+/// assert_eq!(geometry::segment_intersection(&a, &Segment::new(at(0.0, 1.0), at(1.0, 2.0))), None);
+/// ```
+pub fn segment_intersection(a: &Segment, b: &Segment) -> Option<Position<f64>> {
+    let (adx, ady) = a.delta();
+    let (bdx, bdy) = b.delta();
+    let denominator = adx * bdy - ady * bdx;
+    if denominator.abs() < EPSILON {
+        return None;
+    }
+    let (sx, sy) = (b.start.x() - a.start.x(), b.start.y() - a.start.y());
+    let t = (sx * bdy - sy * bdx) / denominator;
+    let u = (sx * ady - sy * adx) / denominator;
+    let within = |value: f64| (-EPSILON..=1.0 + EPSILON).contains(&value);
+    (within(t) && within(u)).then(|| a.point_at(t.clamp(0.0, 1.0)))
+}
+
+/// Where `segment` crosses the circle of `radius` around `center`, ordered
+/// from its start. Empty if it stays inside or outside; a tangent touches
+/// once.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::agent::model::Position;
+/// use thuai_8_agent_rust::math::geometry::{self, Segment};
+///
+/// let at = |x, y| Position::new(x, y, 0.0);
+/// let center = at(0.0, 0.0);
+///
+/// let through = Segment::new(at(-2.0, 0.0), at(2.0, 0.0));
+/// assert_eq!(
+///     geometry::circle_segment_intersection(&center, 1.0, &through),
+///     [at(-1.0, 0.0), at(1.0, 0.0)]
+/// );
+/// let inside = Segment::new(at(-0.5, 0.0), at(0.5, 0.0));
+/// assert!(geometry::circle_segment_intersection(&center, 1.0, &inside).is_empty());
+/// ```
+pub fn circle_segment_intersection(
+    center: &Position<f64>,
+    radius: f64,
+    segment: &Segment,
+) -> Vec<Position<f64>> {
+    let (dx, dy) = segment.delta();
+    let (fx, fy) = (
+        segment.start.x() - center.x(),
+        segment.start.y() - center.y(),
+    );
+    let a = dx * dx + dy * dy;
+    if a == 0.0 {
+        return Vec::new();
+    }
+    let b = 2.0 * (fx * dx + fy * dy);
+    let c = fx * fx + fy * fy - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < -EPSILON {
+        return Vec::new();
+    }
+    let root = discriminant.max(0.0).sqrt();
+    let mut fractions = vec![(-b - root) / (2.0 * a)];
+    if root > EPSILON {
+        fractions.push((-b + root) / (2.0 * a));
+    }
+    fractions
+        .into_iter()
+        .filter(|t| (-EPSILON..=1.0 + EPSILON).contains(t))
+        .map(|t| segment.point_at(t.clamp(0.0, 1.0)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f64, y: f64) -> Position<f64> {
+        Position::new(x, y, 0.0)
+    }
+
+    fn segment(x1: f64, y1: f64, x2: f64, y2: f64) -> Segment {
+        Segment::new(at(x1, y1), at(x2, y2))
+    }
+
+    #[test]
+    fn measures_distance_to_body_and_ends() {
+        let wall = segment(0.0, 0.0, 0.0, 2.0);
+        assert_eq!(point_segment_distance(&at(3.0, 1.0), &wall), 3.0);
+        assert_eq!(point_segment_distance(&at(0.0, 5.0), &wall), 3.0);
+        assert_eq!(point_segment_distance(&at(-3.0, -4.0), &wall), 5.0);
+        assert_eq!(point_segment_distance(&at(0.0, 1.5), &wall), 0.0);
+
+        let point = segment(1.0, 1.0, 1.0, 1.0);
+        assert_eq!(point.length(), 0.0);
+        assert_eq!(point_segment_distance(&at(4.0, 5.0), &point), 5.0);
+    }
+
+    #[test]
+    fn intersects_segments() {
+        let a = segment(0.0, 0.0, 4.0, 0.0);
+        // Crossing, touching at an end, and T-shaped.
+        assert_eq!(
+            segment_intersection(&a, &segment(1.0, -1.0, 1.0, 1.0)),
+            Some(at(1.0, 0.0))
+        );
+        assert_eq!(
+            segment_intersection(&a, &segment(4.0, 0.0, 5.0, 1.0)),
+            Some(at(4.0, 0.0))
+        );
+        assert_eq!(
+            segment_intersection(&a, &segment(2.0, 0.0, 2.0, 3.0)),
+            Some(at(2.0, 0.0))
+        );
+        // Missing, parallel and collinear.
+        assert_eq!(
+            segment_intersection(&a, &segment(5.0, -1.0, 5.0, 1.0)),
+            None
+        );
+        assert_eq!(segment_intersection(&a, &segment(0.0, 1.0, 4.0, 1.0)), None);
+        assert_eq!(segment_intersection(&a, &segment(1.0, 0.0, 3.0, 0.0)), None);
+        // Order does not matter.
+        let b = segment(3.0, -2.0, 1.0, 2.0);
+        assert_eq!(segment_intersection(&a, &b), segment_intersection(&b, &a));
+    }
+
+    #[test]
+    fn intersects_circles() {
+        let center = at(1.0, 1.0);
+        let tangent = circle_segment_intersection(&center, 1.0, &segment(0.0, 2.0, 2.0, 2.0));
+        assert_eq!(tangent, [at(1.0, 2.0)]);
+        // Leaving the circle from inside crosses once.
+        let leaving = circle_segment_intersection(&center, 1.0, &segment(1.0, 1.0, 4.0, 1.0));
+        assert_eq!(leaving, [at(2.0, 1.0)]);
+        // Reversed, the points come in the other order.
+        let through = segment(3.0, 1.0, -1.0, 1.0);
+        assert_eq!(
+            circle_segment_intersection(&center, 1.0, &through),
+            [at(2.0, 1.0), at(0.0, 1.0)]
+        );
+        assert!(circle_segment_intersection(&center, 1.0, &segment(3.0, 0.0, 3.0, 3.0)).is_empty());
+        assert!(circle_segment_intersection(&center, 1.0, &segment(1.0, 1.0, 1.0, 1.0)).is_empty());
+    }
+}
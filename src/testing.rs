@@ -0,0 +1,338 @@
+/*! Helpers to unit-test a strategy without a server.
+
+A [`MockAgent`] serves a fixed [`Snapshot`] of the game and records the
+actions performed on it, so a strategy written against [`PlayerOperate`]
+can be checked decision by decision:
+
+```
+use thuai_8_agent_rust::agent::AgentError;
+use thuai_8_agent_rust::agent::player_api::PlayerOperate;
+use thuai_8_agent_rust::testing::{Action, fixtures, given_snapshot};
+
+async fn strategy(agent: &mut impl PlayerOperate) -> Result<(), AgentError> {
+    let has_ammo = agent
+        .self_player()
+        .is_some_and(|me| *me.weapon().current_bullets() > 0);
+    if has_ammo {
+        agent.attack().await
+    } else {
+        agent.move_backward(1.0).await
+    }
+}
+
+given_snapshot(fixtures::open_field())
+    .run(async |agent| strategy(agent).await)
+    .expect_actions(|actions| actions.contains(Action::Attack));
+
+given_snapshot(fixtures::low_ammo())
+    .run(async |agent| strategy(agent).await)
+    .expect_actions(|actions| !actions.contains(Action::Attack));
+```
+*/
+pub mod fixtures;
+
+use std::error::Error;
+
+use crate::agent::AgentError;
+use crate::agent::connection::{ConnectionAPI, ConnectionStats, PerformMessage, QueryAPI};
+use crate::agent::legality;
+use crate::agent::model::{
+    AvailableBuffs, BuffKind, EnvironmentInfo, GameStatistics, MoveDirection, PlayerToken, Players,
+    SkillKind, TurnDirection,
+};
+use crate::agent::player_api::PlayerOperate;
+
+/// An action performed on a [`MockAgent`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    MoveForward(f64),
+    MoveBackward(f64),
+    TurnClockwise(u32),
+    TurnCounterClockwise(u32),
+    Attack,
+    UseSkill(SkillKind),
+    SelectBuff(BuffKind),
+}
+
+/// The actions a strategy performed, in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Actions(Vec<Action>);
+
+impl Actions {
+    /// Whether `action` was performed at least once.
+    pub fn contains(&self, action: Action) -> bool {
+        self.0.contains(&action)
+    }
+
+    /// Whether nothing was performed.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The performed actions, in order.
+    pub fn as_slice(&self) -> &[Action] {
+        &self.0
+    }
+}
+
+/// Everything the agent knows about the game at one moment.
+///
+/// Ready-made snapshots live in [`fixtures`].
+#[derive(Debug)]
+pub struct Snapshot {
+    pub token: PlayerToken,
+    pub players_info: Option<Players>,
+    pub game_statistics: Option<GameStatistics>,
+    pub environment_info: Option<EnvironmentInfo>,
+    pub available_buffs: Option<AvailableBuffs>,
+}
+
+/// A [`PlayerOperate`] that serves a [`Snapshot`] and records every action
+/// instead of sending it.
+///
+/// Actions the rules forbid in the snapshot's stage are rejected as by the
+/// live agent, see [`legality`], and not recorded.
+#[derive(Debug)]
+pub struct MockAgent {
+    snapshot: Snapshot,
+    actions: Actions,
+}
+
+impl MockAgent {
+    /// Constructs a [`MockAgent`] serving `snapshot`.
+    pub fn new(snapshot: Snapshot) -> MockAgent {
+        MockAgent {
+            snapshot,
+            actions: Actions::default(),
+        }
+    }
+
+    /// The actions performed so far.
+    pub fn actions(&self) -> &Actions {
+        &self.actions
+    }
+
+    /// Record `action`, unless `message` is illegal in the snapshot's stage.
+    fn record(&mut self, message: PerformMessage, action: Action) -> Result<(), Box<dyn Error>> {
+        let stage = self
+            .snapshot
+            .game_statistics
+            .as_ref()
+            .map(|statistics| statistics.current_stage());
+        legality::check(stage, &message)?;
+        self.actions.0.push(action);
+        Ok(())
+    }
+}
+
+impl QueryAPI for MockAgent {
+    async fn send_get_player_info(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    async fn send_get_environment_info(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    async fn send_get_game_statistics(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    async fn send_get_available_buffs(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+impl ConnectionAPI for MockAgent {
+    async fn send_perform_turn(
+        &mut self,
+        direction: TurnDirection,
+        angle: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        let action = match direction {
+            TurnDirection::Clockwise => Action::TurnClockwise(angle),
+            TurnDirection::CounterClockwise => Action::TurnCounterClockwise(angle),
+        };
+        let message = PerformMessage::PerformTurn {
+            token: self.snapshot.token.clone(),
+            direction,
+            angle,
+        };
+        self.record(message, action)
+    }
+
+    async fn send_perform_move(
+        &mut self,
+        direction: MoveDirection,
+        distance: f64,
+    ) -> Result<(), Box<dyn Error>> {
+        let action = match direction {
+            MoveDirection::Forth => Action::MoveForward(distance),
+            MoveDirection::Back => Action::MoveBackward(distance),
+        };
+        let message = PerformMessage::PerformMove {
+            token: self.snapshot.token.clone(),
+            direction,
+            distance,
+        };
+        self.record(message, action)
+    }
+
+    async fn send_perform_attack(&mut self) -> Result<(), Box<dyn Error>> {
+        let message = PerformMessage::PerformAttack {
+            token: self.snapshot.token.clone(),
+        };
+        self.record(message, Action::Attack)
+    }
+
+    async fn send_perform_skill(&mut self, skill_name: SkillKind) -> Result<(), Box<dyn Error>> {
+        let message = PerformMessage::PerformSkill {
+            token: self.snapshot.token.clone(),
+            skill_name,
+        };
+        self.record(message, Action::UseSkill(skill_name))
+    }
+
+    async fn send_perform_select(&mut self, buff_name: BuffKind) -> Result<(), Box<dyn Error>> {
+        let message = PerformMessage::PerformSelect {
+            token: self.snapshot.token.clone(),
+            buff_name,
+        };
+        self.record(message, Action::SelectBuff(buff_name))
+    }
+}
+
+impl PlayerOperate for MockAgent {
+    fn token(&self) -> &PlayerToken {
+        &self.snapshot.token
+    }
+
+    fn players_info(&self) -> Option<&Players> {
+        self.snapshot.players_info.as_ref()
+    }
+
+    fn game_statistics(&self) -> Option<&GameStatistics> {
+        self.snapshot.game_statistics.as_ref()
+    }
+
+    fn environment_info(&self) -> Option<&EnvironmentInfo> {
+        self.snapshot.environment_info.as_ref()
+    }
+
+    fn available_buffs(&self) -> Option<&AvailableBuffs> {
+        self.snapshot.available_buffs.as_ref()
+    }
+
+    fn connection_stats(&self) -> ConnectionStats {
+        ConnectionStats::default()
+    }
+
+    async fn move_forward(&mut self, distance: f64) -> Result<(), AgentError> {
+        Ok(self
+            .send_perform_move(MoveDirection::Forth, distance)
+            .await?)
+    }
+
+    async fn move_backward(&mut self, distance: f64) -> Result<(), AgentError> {
+        Ok(self
+            .send_perform_move(MoveDirection::Back, distance)
+            .await?)
+    }
+
+    async fn turn_clockwise(&mut self, angle: u32) -> Result<(), AgentError> {
+        Ok(self
+            .send_perform_turn(TurnDirection::Clockwise, angle)
+            .await?)
+    }
+
+    async fn turn_counter_clockwise(&mut self, angle: u32) -> Result<(), AgentError> {
+        Ok(self
+            .send_perform_turn(TurnDirection::CounterClockwise, angle)
+            .await?)
+    }
+
+    async fn attack(&mut self) -> Result<(), AgentError> {
+        Ok(self.send_perform_attack().await?)
+    }
+
+    async fn use_skill(&mut self, skill: SkillKind) -> Result<(), AgentError> {
+        Ok(self.send_perform_skill(skill).await?)
+    }
+
+    async fn select_buff(&mut self, buff: BuffKind) -> Result<(), AgentError> {
+        Ok(self.send_perform_select(buff).await?)
+    }
+}
+
+/// A strategy test: a snapshot, and the actions a strategy took on it.
+///
+/// Created with [`given_snapshot`].
+#[derive(Debug)]
+pub struct Scenario {
+    agent: MockAgent,
+}
+
+/// Start a strategy test on `snapshot`.
+pub fn given_snapshot(snapshot: Snapshot) -> Scenario {
+    Scenario {
+        agent: MockAgent::new(snapshot),
+    }
+}
+
+impl Scenario {
+    /// Run `strategy` once on the snapshot, recording its actions.
+    pub fn run<R>(mut self, strategy: impl AsyncFnOnce(&mut MockAgent) -> R) -> Self {
+        futures::executor::block_on(strategy(&mut self.agent));
+        self
+    }
+
+    /// The actions recorded so far.
+    pub fn actions(&self) -> &Actions {
+        self.agent.actions()
+    }
+
+    /// Check the recorded actions.
+    ///
+    /// # Panics
+    ///
+    /// Panics, listing the recorded actions, if `check` returns `false`.
+    pub fn expect_actions(self, check: impl FnOnce(&Actions) -> bool) -> Self {
+        assert!(
+            check(self.agent.actions()),
+            "Unexpected actions: {:?}",
+            self.agent.actions().as_slice()
+        );
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "Unexpected actions: [TurnClockwise(90)]")]
+    fn expect_actions_reports_actions() {
+        given_snapshot(fixtures::cornered())
+            .run(async |agent| agent.turn_clockwise(90).await)
+            .expect_actions(|actions| actions.contains(Action::Attack));
+    }
+
+    #[test]
+    fn illegal_actions_are_not_recorded() {
+        given_snapshot(fixtures::open_field())
+            .run(async |agent| {
+                let result = agent.select_buff(BuffKind::Missile).await;
+                assert!(matches!(result, Err(AgentError::Illegal(_))));
+            })
+            .expect_actions(Actions::is_empty);
+    }
+
+    #[test]
+    fn splits_players_by_token() {
+        let agent = MockAgent::new(fixtures::open_field());
+        assert_eq!(agent.self_player().unwrap().token(), fixtures::SELF_TOKEN);
+        assert_eq!(agent.opponent().unwrap().token(), fixtures::OPPONENT_TOKEN);
+        assert_eq!(agent.opponents().len(), 1);
+    }
+}
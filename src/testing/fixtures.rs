@@ -0,0 +1,185 @@
+/*! Curated [`Snapshot`]s for strategy tests.
+
+Every fixture is a battle on a 10 x 10 map between the agent, with token
+[`SELF_TOKEN`], and one opponent, with token [`OPPONENT_TOKEN`]. */
+use super::Snapshot;
+use crate::agent::model::{
+    Angle, Armor, ArmorKnifeState, BuffKind, Bullet, EnvironmentInfo, Fence, GameStatistics,
+    Player, Position, ScoreBoard, Skill, SkillKind, Stage, TokenScore, Wall, Weapon,
+};
+
+/// Token of the agent in every fixture.
+pub const SELF_TOKEN: &str = "self";
+/// Token of the opponent in every fixture.
+pub const OPPONENT_TOKEN: &str = "opponent";
+
+const MAP_SIZE: u32 = 10;
+
+/// Builds a [`Player`] like the ones of the fixtures, for tests that need
+/// players of their own: at the origin facing along x, with 10 health, no
+/// armor, the flash skill and a weapon of 10 bullets dealing 1 damage.
+///
+/// # Example
+///
+/// ```
+/// use thuai_8_agent_rust::testing::fixtures::{PlayerBuilder, SELF_TOKEN};
+///
+/// let me = PlayerBuilder::new(SELF_TOKEN)
+///     .at(1.5, 2.5, 0.0)
+///     .with_health(4)
+///     .build();
+///
+/// assert_eq!(*me.position().x(), 1.5);
+/// assert_eq!(me.effective_health(), 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlayerBuilder {
+    token: String,
+    position: Position<f64>,
+    weapon: Weapon,
+    armor_value: u32,
+    health: i32,
+    dodge_rate: f64,
+    knife: ArmorKnifeState,
+    skills: Vec<Skill>,
+}
+
+impl PlayerBuilder {
+    pub fn new(token: &str) -> PlayerBuilder {
+        PlayerBuilder {
+            token: token.to_string(),
+            position: Position::new(0.0, 0.0, 0.0),
+            weapon: Weapon::new(1.0, 2.0, false, false, 1, 10, 10),
+            armor_value: 0,
+            health: 10,
+            dodge_rate: 0.0,
+            knife: ArmorKnifeState::NotOwned,
+            skills: vec![Skill::new(SkillKind::Flash, 20, 0, false)],
+        }
+    }
+
+    /// Place the player at `x`, `y`, facing `angle` in radians.
+    pub fn at(mut self, x: f64, y: f64, angle: f64) -> Self {
+        self.position = Position::new(x, y, angle);
+        self
+    }
+
+    pub fn with_weapon(mut self, weapon: Weapon) -> Self {
+        self.weapon = weapon;
+        self
+    }
+
+    pub fn with_health(mut self, health: i32) -> Self {
+        self.health = health;
+        self
+    }
+
+    pub fn with_armor_value(mut self, armor_value: u32) -> Self {
+        self.armor_value = armor_value;
+        self
+    }
+
+    pub fn with_dodge_rate(mut self, dodge_rate: f64) -> Self {
+        self.dodge_rate = dodge_rate;
+        self
+    }
+
+    pub fn with_knife(mut self, knife: ArmorKnifeState) -> Self {
+        self.knife = knife;
+        self
+    }
+
+    pub fn with_skills(mut self, skills: Vec<Skill>) -> Self {
+        self.skills = skills;
+        self
+    }
+
+    pub fn build(self) -> Player {
+        Player::new(
+            self.token,
+            self.position,
+            self.weapon,
+            Armor::new(
+                false,
+                false,
+                self.armor_value,
+                self.health,
+                self.dodge_rate,
+                self.knife,
+            ),
+            self.skills,
+        )
+    }
+}
+
+/// A bullet at `x`, `y`, flying along `angle` in radians at speed 1 and
+/// dealing 1 damage, not yet traveled.
+pub fn bullet(id: u32, x: f64, y: f64, angle: f64) -> Bullet {
+    Bullet::new(id, false, false, Position::new(x, y, angle), 1.0, 1.0, 0.0)
+}
+
+fn player(token: &str, x: f64, y: f64, angle: f64, bullets: u32) -> Player {
+    PlayerBuilder::new(token)
+        .at(x, y, angle)
+        .with_weapon(Weapon::new(1.0, 2.0, false, false, 1, 10, bullets))
+        .build()
+}
+
+fn battle(players: Vec<Player>, walls: Vec<Wall>, fences: Vec<Fence>) -> Snapshot {
+    Snapshot {
+        token: SELF_TOKEN.into(),
+        players_info: Some(players),
+        game_statistics: Some(GameStatistics::new(
+            Stage::Battle,
+            100,
+            42,
+            ScoreBoard::new(vec![
+                TokenScore::new(SELF_TOKEN.to_string(), 0),
+                TokenScore::new(OPPONENT_TOKEN.to_string(), 0),
+            ]),
+        )),
+        environment_info: Some(EnvironmentInfo::new(MAP_SIZE, walls, fences, Vec::new())),
+        available_buffs: Some(vec![BuffKind::Missile, BuffKind::Reflect]),
+    }
+}
+
+/// Both players in the middle of an empty map, facing each other, with full
+/// ammunition.
+pub fn open_field() -> Snapshot {
+    battle(
+        vec![
+            player(SELF_TOKEN, 3.5, 5.5, 0.0, 10),
+            player(OPPONENT_TOKEN, 6.5, 5.5, std::f64::consts::PI, 10),
+        ],
+        Vec::new(),
+        Vec::new(),
+    )
+}
+
+/// The agent in the bottom-left corner, walled in on two sides with a fence
+/// on the third, and the opponent right in front of it.
+pub fn cornered() -> Snapshot {
+    battle(
+        vec![
+            player(SELF_TOKEN, 0.5, 0.5, 0.0, 10),
+            player(OPPONENT_TOKEN, 2.5, 0.5, std::f64::consts::PI, 10),
+        ],
+        vec![
+            Wall::new(0, 0, Angle::from_degrees(0.0)),
+            Wall::new(0, 0, Angle::from_degrees(90.0)),
+        ],
+        vec![Fence::new(Position::new(0, 1, 0.0), 2)],
+    )
+}
+
+/// Like [`open_field`], but the agent has no bullets left.
+pub fn low_ammo() -> Snapshot {
+    battle(
+        vec![
+            player(SELF_TOKEN, 3.5, 5.5, 0.0, 0),
+            player(OPPONENT_TOKEN, 6.5, 5.5, std::f64::consts::PI, 10),
+        ],
+        Vec::new(),
+        Vec::new(),
+    )
+}